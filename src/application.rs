@@ -1,14 +1,17 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use crate::{
-    backend::PwvucontrolManager,
+    backend::{MockBackend, PwvucontrolManager, WpBackend},
     config::{APP_ID, VERSION},
     ui::PwvucontrolWindow,
 };
 use adw::subclass::prelude::*;
 use glib::{ExitCode, OptionArg, OptionFlags};
 use gtk::{gio, prelude::*};
-use std::cell::{Cell, OnceCell};
+use std::{
+    cell::{Cell, OnceCell},
+    rc::Rc,
+};
 
 mod imp {
     use super::*;
@@ -21,6 +24,7 @@ mod imp {
         pub manager: PwvucontrolManager,
 
         pub(super) tab: Cell<i32>,
+        pub(super) demo: Cell<bool>,
     }
 
     #[glib::object_subclass]
@@ -30,7 +34,7 @@ mod imp {
         type ParentType = adw::Application;
 
         fn new() -> PwvucontrolApplication {
-            PwvucontrolApplication { window: OnceCell::default(), manager: PwvucontrolManager::new(), tab: Default::default() }
+            PwvucontrolApplication { window: OnceCell::default(), manager: PwvucontrolManager::new(), tab: Default::default(), demo: Default::default() }
         }
     }
 
@@ -42,12 +46,15 @@ mod imp {
             let obj = self.obj();
             obj.setup_gactions();
             obj.set_accels_for_action("app.quit", &["<primary>q"]);
+            obj.set_accels_for_action("win.search", &["<primary>f"]);
+            obj.set_accels_for_action("win.preferences", &["<primary>comma"]);
 
             obj.set_accels_for_action("win.switchtab(1)", &["<alt>1"]);
             obj.set_accels_for_action("win.switchtab(2)", &["<alt>2"]);
             obj.set_accels_for_action("win.switchtab(3)", &["<alt>3"]);
             obj.set_accels_for_action("win.switchtab(4)", &["<alt>4"]);
             obj.set_accels_for_action("win.switchtab(5)", &["<alt>5"]);
+            obj.set_accels_for_action("win.switchtab(6)", &["<alt>6"]);
         }
     }
 
@@ -68,6 +75,12 @@ mod imp {
         fn startup(&self) {
             self.parent_startup();
 
+            if self.demo.get() {
+                self.manager.start(Rc::new(MockBackend::demo()));
+            } else {
+                self.manager.start(Rc::new(WpBackend::new()));
+            }
+
             let window = PwvucontrolWindow::new(&self.obj());
             self.window.set(window).expect("Failed to initialize application window");
         }
@@ -88,6 +101,7 @@ mod imp {
                 println!("pwvucontrol version {}", VERSION);
                 return ExitCode::SUCCESS;
             }
+            self.demo.set(options.lookup_value("demo", None).is_some());
 
             self.parent_handle_local_options(options)
         }
@@ -115,6 +129,7 @@ impl PwvucontrolApplication {
 
         app.add_main_option("tab", b't'.into(), OptionFlags::NONE, OptionArg::Int, "Select tab to open.", Some("number"));
         app.add_main_option("version", b'v'.into(), OptionFlags::NONE, OptionArg::None, "Show version.", None);
+        app.add_main_option("demo", b'd'.into(), OptionFlags::NONE, OptionArg::None, "Show made-up devices instead of connecting to PipeWire.", None);
 
         ApplicationExtManual::run(&app)
     }
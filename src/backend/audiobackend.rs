@@ -0,0 +1,206 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! The boundary between the model objects and the sound server.
+//!
+//! [`PwNodeObject`](super::PwNodeObject), [`PwDeviceObject`](super::PwDeviceObject) and the manager
+//! only talk to these traits. [`WpBackend`] implements them with WirePlumber, [`MockBackend`] keeps
+//! everything in memory for tests and the `--demo` mode.
+
+use super::{
+    pwnodeobject::AudioFormat, DumpSection, NodeProfile, ParamAvailability, PropsParamValue, PwvucontrolManager, RouteDirection, SupportedFormats,
+};
+use std::rc::Rc;
+
+mod bluezbattery;
+mod mockbackend;
+mod wpbackend;
+
+pub use mockbackend::{MockBackend, MockRoute};
+pub use wpbackend::WpBackend;
+
+/// Volume state of a node as the session manager's mixer reports it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NodeVolume {
+    pub mute: bool,
+    pub channel_volumes: Vec<f32>,
+}
+
+/// The parts of a node's Props param that the mixer does not cover.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NodeProps {
+    /// Volume applied on top of the channel volumes, hidden from the mixer.
+    pub volume: Option<f32>,
+    /// The node's own channel volumes and mute state, used when there is no mixer.
+    pub channel_volumes: Vec<f32>,
+    pub mute: Option<bool>,
+    pub monitor_volumes: Vec<f32>,
+    /// Entries of the `params` struct, like `channelmix.upmix`.
+    pub params: Vec<(String, PropsParamValue)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfileInfo {
+    pub index: u32,
+    pub name: String,
+    pub description: String,
+    pub availability: ParamAvailability,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteInfo {
+    pub index: u32,
+    pub description: String,
+    pub direction: RouteDirection,
+    pub availability: ParamAvailability,
+    /// Profiles the route can be used with.
+    pub profiles: Vec<u32>,
+}
+
+/// The route a card device (a sink or source of the card) is currently using.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActiveRoute {
+    pub index: i32,
+    /// Card device the route is active on, see `card.profile.device` of nodes.
+    pub device: i32,
+    pub name: String,
+    pub description: String,
+    pub direction: RouteDirection,
+    pub channel_volumes: Vec<f32>,
+    pub mute: bool,
+}
+
+/// Changes to apply to the properties of a route, `None` keeps the current value.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RouteProps {
+    pub channel_volumes: Option<Vec<f32>>,
+    pub mute: Option<bool>,
+}
+
+/// What changed on a node, reported to the handler given to [`NodeBackend::connect_changed`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeChange {
+    Properties,
+    Props,
+    Format,
+    State,
+    Volume,
+}
+
+/// What changed on a device, reported to the handler given to [`DeviceBackend::connect_changed`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceChange {
+    Properties,
+    Profiles,
+    Profile,
+    Routes,
+    Route,
+    Battery,
+}
+
+/// Connection to the sound server, shared by all nodes and devices.
+///
+/// Nodes and devices are reported to the manager passed to [`AudioBackend::start`], through
+/// [`PwvucontrolManager::add_node`] and its siblings.
+pub trait AudioBackend {
+    fn start(&self, manager: &PwvucontrolManager);
+    /// Tries connecting again after the connection was lost.
+    fn reconnect(&self);
+    fn is_connected(&self) -> bool;
+
+    /// Id of the node the session manager currently uses as default for `media_class`.
+    fn default_node(&self, media_class: &str) -> Option<u32>;
+    /// Node name the session manager was told to prefer for `media_class`.
+    fn default_configured_node_name(&self, media_class: &str) -> Option<String>;
+    /// Sets the node name the session manager should prefer for `media_class`, an empty name clears it.
+    fn set_default_configured_node_name(&self, media_class: &str, node_name: &str) -> bool;
+
+    /// Reads `key` of `subject` from the `default` metadata.
+    fn metadata_value(&self, subject: u32, key: &str) -> Option<String>;
+    /// Writes `key` of `subject` to the `default` metadata, `None` removes it.
+    fn set_metadata_value(&self, subject: u32, key: &str, type_: Option<&str>, value: Option<&str>);
+
+    /// Whether the `settings` metadata holding the global clock settings is there.
+    fn has_settings(&self) -> bool;
+    /// Reads a global setting such as `clock.force-rate`, like `pw-metadata -n settings`.
+    fn setting(&self, key: &str) -> Option<String>;
+    fn set_setting(&self, key: &str, value: Option<&str>);
+
+    /// Scheduling info of the node with `id`, `None` when it isn't scheduled or there is no profiler.
+    fn node_profile(&self, id: u32) -> Option<NodeProfile>;
+    /// Scheduling info of every node in the last cycle, empty when there is no profiler.
+    fn node_profiles(&self) -> Vec<NodeProfile>;
+    fn profiler_available(&self) -> bool;
+
+    /// Whether the peak meters can record from nodes through a PipeWire stream.
+    fn can_monitor(&self) -> bool;
+
+    /// Whether the interface can play and record through PipeWire streams of its own, for sound
+    /// tests, feedback sounds and listening.
+    fn can_create_streams(&self) -> bool;
+}
+
+/// A sink, source or stream.
+pub trait NodeBackend {
+    fn id(&self) -> u32;
+    fn property(&self, key: &str) -> Option<String>;
+    fn is_running(&self) -> bool;
+    /// Id of the device providing the node.
+    fn device_id(&self) -> Option<u32>;
+    /// The card device the node belongs to, which routes are selected for.
+    fn card_device(&self) -> Option<i32>;
+
+    /// Calls `handler` whenever something about the node changes.
+    fn connect_changed(&self, handler: Rc<dyn Fn(NodeChange)>);
+
+    /// `None` when the node has no volume controls.
+    fn volume(&self) -> Option<NodeVolume>;
+    /// Sets the channel volumes, the mute state or both. `positions` are the channel positions
+    /// of the negotiated format the volumes are meant for.
+    fn set_volume(&self, channel_volumes: Option<&[f32]>, positions: &[u32], mute: Option<bool>) -> bool;
+
+    fn props(&self) -> Option<NodeProps>;
+    fn set_main_volume(&self, volume: f32);
+    fn set_monitor_volumes(&self, volumes: &[f32]);
+    /// Updates node properties at runtime through the `params` struct of the Props param.
+    fn set_props_params(&self, params: &[(&str, PropsParamValue)]);
+
+    /// Calls `callback` with the negotiated format once it is known.
+    fn format(&self, callback: Box<dyn FnOnce(Option<AudioFormat>)>);
+    /// Rates and sample formats the node offers.
+    fn supported_formats(&self) -> SupportedFormats;
+    /// Reconfigures the adapter ports with a new channel map, see [`super::PwNodeObject::set_channel_positions`].
+    /// `direction` is the direction of the ports, input for sinks and output for sources.
+    fn set_port_config(&self, direction: RouteDirection, rate: u32, positions: &[u32]);
+    fn suspend(&self);
+
+    /// Properties and params for the properties window.
+    fn dump(&self) -> Vec<DumpSection>;
+
+    /// Battery level in percent, for Bluetooth devices that report one.
+    fn battery_level(&self) -> Option<u32>;
+}
+
+/// A sound card or Bluetooth device with profiles and routes.
+pub trait DeviceBackend {
+    fn id(&self) -> u32;
+    fn property(&self, key: &str) -> Option<String>;
+
+    /// Calls `handler` whenever something about the device changes.
+    fn connect_changed(&self, handler: Rc<dyn Fn(DeviceChange)>);
+
+    /// Calls `callback` with every profile the device has.
+    fn profiles(&self, callback: Box<dyn FnOnce(Vec<ProfileInfo>)>);
+    fn current_profile(&self) -> Option<ProfileInfo>;
+    fn set_profile(&self, index: i32);
+
+    /// Calls `callback` with every route the device has.
+    fn routes(&self, callback: Box<dyn FnOnce(Vec<RouteInfo>)>);
+    /// Availability of each route by index, read synchronously.
+    fn route_availability(&self) -> Vec<(i32, ParamAvailability)>;
+    fn active_routes(&self) -> Vec<ActiveRoute>;
+    /// Selects route `index` on card device `device`, saving the choice and optionally changing its properties.
+    fn set_route(&self, index: i32, device: i32, props: &RouteProps);
+
+    /// Properties and params for the properties window.
+    fn dump(&self) -> Vec<DumpSection>;
+}
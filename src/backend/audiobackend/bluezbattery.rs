@@ -0,0 +1,127 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::macros::*;
+use glib::clone;
+use gtk::{gio, prelude::*};
+use std::{
+    cell::{Cell, RefCell},
+    rc::{Rc, Weak},
+};
+
+const BLUEZ: &str = "org.bluez";
+const BATTERY_INTERFACE: &str = "org.bluez.Battery1";
+const DEVICE_INTERFACE: &str = "org.bluez.Device1";
+
+/// Battery level of a Bluetooth device, read from BlueZ's `org.bluez.Battery1` interface on the system bus.
+///
+/// PipeWire doesn't publish the level in the device properties, so the device is looked up by its address.
+pub(super) struct BluezBattery {
+    level: Cell<Option<u32>>,
+    proxy: RefCell<Option<gio::DBusProxy>>,
+}
+
+impl BluezBattery {
+    /// Starts looking up the device with `address`, calling `changed` whenever its battery level changes.
+    pub(super) fn watch(address: &str, changed: impl Fn() + 'static) -> Rc<Self> {
+        let battery = Rc::new(Self { level: Cell::new(None), proxy: RefCell::new(None) });
+        let changed: Rc<dyn Fn()> = Rc::new(changed);
+        let address = address.to_string();
+
+        let weak = Rc::downgrade(&battery);
+        gio::bus_get(gio::BusType::System, gio::Cancellable::NONE, move |connection| {
+            let connection = match connection {
+                Ok(connection) => connection,
+                Err(error) => {
+                    pwvucontrol_warning!("Cannot connect to the system bus for the battery level: {error}");
+                    return;
+                }
+            };
+
+            connection.call(
+                Some(BLUEZ),
+                "/",
+                "org.freedesktop.DBus.ObjectManager",
+                "GetManagedObjects",
+                None,
+                glib::VariantTy::new("(a{oa{sa{sv}}})").ok(),
+                gio::DBusCallFlags::NONE,
+                -1,
+                gio::Cancellable::NONE,
+                clone!(@strong connection => move |objects| {
+                    let objects = match objects {
+                        Ok(objects) => objects,
+                        Err(error) => {
+                            pwvucontrol_info!("Cannot list BlueZ devices: {error}");
+                            return;
+                        }
+                    };
+                    let Some(path) = device_path(&objects.child_value(0), &address) else {
+                        pwvucontrol_info!("No BlueZ device with address {address}");
+                        return;
+                    };
+                    if weak.upgrade().is_some() {
+                        Self::create_proxy(weak, &connection, &path, changed);
+                    }
+                }),
+            );
+        });
+
+        battery
+    }
+
+    pub(super) fn level(&self) -> Option<u32> {
+        self.level.get()
+    }
+
+    fn create_proxy(weak: Weak<Self>, connection: &gio::DBusConnection, path: &str, changed: Rc<dyn Fn()>) {
+        gio::DBusProxy::new(
+            connection,
+            gio::DBusProxyFlags::NONE,
+            None,
+            Some(BLUEZ),
+            path,
+            BATTERY_INTERFACE,
+            gio::Cancellable::NONE,
+            move |proxy| {
+                let proxy = match proxy {
+                    Ok(proxy) => proxy,
+                    Err(error) => {
+                        pwvucontrol_info!("Cannot read the battery level: {error}");
+                        return;
+                    }
+                };
+                let Some(battery) = weak.upgrade() else {
+                    return;
+                };
+
+                let update = clone!(@strong weak, @strong changed => move |proxy: &gio::DBusProxy| {
+                    let Some(battery) = weak.upgrade() else {
+                        return;
+                    };
+                    let level = proxy.cached_property("Percentage").and_then(|value| value.get::<u8>()).map(u32::from);
+                    if battery.level.replace(level) != level {
+                        changed();
+                    }
+                });
+                update(&proxy);
+
+                proxy.connect_local("g-properties-changed", false, move |values| {
+                    if let Some(proxy) = values.first().and_then(|value| value.get::<gio::DBusProxy>().ok()) {
+                        update(&proxy);
+                    }
+                    None
+                });
+                battery.proxy.replace(Some(proxy));
+            },
+        );
+    }
+}
+
+/// Object path of the BlueZ device with `address`, among the result of `GetManagedObjects`.
+fn device_path(objects: &glib::Variant, address: &str) -> Option<String> {
+    objects.iter().find_map(|object| {
+        let device = object.child_value(1).lookup_value(DEVICE_INTERFACE, None)?;
+        let device_address = device.lookup_value("Address", Some(glib::VariantTy::STRING))?;
+        device_address.str()?.eq_ignore_ascii_case(address).then(|| object.child_value(0).str().map(str::to_string))?
+    })
+}
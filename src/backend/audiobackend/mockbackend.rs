@@ -0,0 +1,1001 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use super::*;
+use crate::macros::*;
+use glib::ControlFlow;
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    time::Duration,
+};
+use wireplumber as wp;
+
+/// Settings a fresh PipeWire instance reports in its `settings` metadata.
+const DEFAULT_SETTINGS: [(&str, &str); 8] = [
+    ("log.level", "2"),
+    ("clock.rate", "48000"),
+    ("clock.allowed-rates", "[ 48000 ]"),
+    ("clock.quantum", "1024"),
+    ("clock.min-quantum", "32"),
+    ("clock.max-quantum", "2048"),
+    ("clock.force-quantum", "0"),
+    ("clock.force-rate", "0"),
+];
+
+/// A route of a [`MockBackend`] device, the index is its position in the device's list.
+#[derive(Debug, Clone)]
+pub struct MockRoute {
+    pub name: String,
+    pub description: String,
+    pub direction: RouteDirection,
+    pub available: bool,
+    /// Profiles the route can be used with.
+    pub profiles: Vec<u32>,
+    /// Card device the route belongs to, matching `card.profile.device` of the node.
+    pub device: i32,
+}
+
+impl MockRoute {
+    pub fn new(name: &str, description: &str, direction: RouteDirection, profiles: &[u32], device: i32) -> Self {
+        Self {
+            name: name.to_string(),
+            description: description.to_string(),
+            direction,
+            available: true,
+            profiles: profiles.to_vec(),
+            device,
+        }
+    }
+
+    fn unavailable(mut self) -> Self {
+        self.available = false;
+        self
+    }
+}
+
+/// Keeps a made-up sound server in memory, for the tests and for trying the interface with `--demo`.
+///
+/// Clones share the same state, so a test can keep one to change things behind the manager's back.
+#[derive(Clone)]
+pub struct MockBackend {
+    state: Rc<State>,
+}
+
+#[derive(Default)]
+struct State {
+    manager: glib::WeakRef<PwvucontrolManager>,
+    last_id: Cell<u32>,
+    nodes: RefCell<Vec<Rc<MockNode>>>,
+    devices: RefCell<Vec<Rc<MockDevice>>>,
+    /// Node names the session manager was told to prefer, by media class.
+    configured_defaults: RefCell<HashMap<String, String>>,
+    metadata: RefCell<HashMap<(u32, String), String>>,
+    settings: RefCell<HashMap<String, String>>,
+}
+
+impl MockBackend {
+    pub fn new() -> Self {
+        // The channel and format names come from the SPA type tables.
+        wp::core::Core::init_with_flags(wp::InitFlags::ALL);
+
+        let state = State::default();
+        state.settings.replace(DEFAULT_SETTINGS.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect());
+        Self { state: Rc::new(state) }
+    }
+
+    /// A backend with a few sound cards, a Bluetooth headset and some applications playing and
+    /// recording, which keep changing while the application runs.
+    pub fn demo() -> Self {
+        let backend = Self::new();
+        backend.populate_demo();
+        backend.run_demo_script();
+        backend
+    }
+
+    /// Adds a node with a volume on `channels`, given by short names like `FL`. `device` is the id of
+    /// its device and its card device.
+    pub fn add_node(&self, properties: &[(&str, &str)], channels: &[&str], device: Option<(u32, i32)>) -> u32 {
+        let id = self.next_id();
+        let node = Rc::new(MockNode::new(id, properties, channels, device));
+        self.state.nodes.borrow_mut().push(node.clone());
+
+        if let Some(manager) = self.state.manager.upgrade() {
+            manager.add_node(node);
+        }
+        id
+    }
+
+    pub fn remove_node(&self, id: u32) {
+        self.state.nodes.borrow_mut().retain(|node| node.id != id);
+        if let Some(manager) = self.state.manager.upgrade() {
+            manager.remove_node_by_id(id);
+        }
+    }
+
+    /// Adds a device with the profiles given as name and description pairs, starting on `profile`.
+    /// The first available route of each card device is active.
+    pub fn add_device(&self, properties: &[(&str, &str)], profiles: &[(&str, &str)], profile: u32, routes: Vec<MockRoute>) -> u32 {
+        let id = self.next_id();
+        let device = Rc::new(MockDevice::new(id, properties, profiles, profile, routes));
+        self.state.devices.borrow_mut().push(device.clone());
+
+        if let Some(manager) = self.state.manager.upgrade() {
+            manager.add_device(device);
+        }
+        id
+    }
+
+    pub fn remove_device(&self, id: u32) {
+        self.state.devices.borrow_mut().retain(|device| device.id != id);
+        if let Some(manager) = self.state.manager.upgrade() {
+            manager.remove_device_by_id(id);
+        }
+    }
+
+    pub fn set_node_running(&self, id: u32, running: bool) {
+        if let Some(node) = self.node(id) {
+            if node.running.replace(running) != running {
+                node.changed(NodeChange::State);
+            }
+        }
+    }
+
+    pub fn node_volume(&self, id: u32) -> Option<NodeVolume> {
+        self.node(id).map(|node| node.volume.borrow().clone())
+    }
+
+    pub fn set_device_property(&self, id: u32, key: &str, value: &str) {
+        if let Some(device) = self.device(id) {
+            device.properties.borrow_mut().insert(key.to_string(), value.to_string());
+            device.changed(DeviceChange::Properties);
+        }
+    }
+
+    /// Sets the battery level a Bluetooth device reports, `None` when it has none.
+    pub fn set_device_battery(&self, id: u32, level: Option<u32>) {
+        if let Some(device) = self.device(id) {
+            if device.battery.replace(level) != level {
+                device.changed(DeviceChange::Battery);
+            }
+        }
+    }
+
+    /// Plugs or unplugs a route. Unplugging the active route moves its card device to another
+    /// available route in the same direction, like a sound card does for headphones.
+    pub fn set_route_available(&self, id: u32, index: u32, available: bool) {
+        if let Some(device) = self.device(id) {
+            device.set_route_available(index, available);
+        }
+    }
+
+    /// Drops everything from the manager and reports it again, like after a sound server restart.
+    pub fn restart(&self) {
+        if let Some(manager) = self.state.manager.upgrade() {
+            manager.set_connected(false);
+            manager.clear();
+            self.start(&manager);
+        }
+    }
+
+    fn next_id(&self) -> u32 {
+        let id = self.state.last_id.get() + 1;
+        self.state.last_id.set(id);
+        id
+    }
+
+    fn node(&self, id: u32) -> Option<Rc<MockNode>> {
+        self.state.nodes.borrow().iter().find(|node| node.id == id).cloned()
+    }
+
+    fn device(&self, id: u32) -> Option<Rc<MockDevice>> {
+        self.state.devices.borrow().iter().find(|device| device.id == id).cloned()
+    }
+
+    fn node_by_name(&self, name: &str) -> Option<Rc<MockNode>> {
+        self.state.nodes.borrow().iter().find(|node| node.property("node.name").as_deref() == Some(name)).cloned()
+    }
+
+    /// Runs `f` with the manager once the current change is processed, as a real server would report it.
+    fn notify_manager(&self, f: impl FnOnce(&PwvucontrolManager) + 'static) {
+        let manager = self.state.manager.clone();
+        glib::idle_add_local_once(move || {
+            if let Some(manager) = manager.upgrade() {
+                f(&manager);
+            }
+        });
+    }
+
+    fn populate_demo(&self) {
+        let builtin = self.add_device(
+            &[
+                ("device.name", "alsa_card.pci-0000_00_1f.3"),
+                ("device.description", "Built-in Audio"),
+                ("device.api", "alsa"),
+                ("device.bus", "pci"),
+                ("device.icon-name", "audio-card-analog"),
+            ],
+            &[
+                ("off", "Off"),
+                ("output:analog-stereo+input:analog-stereo", "Analog Stereo Duplex"),
+                ("output:analog-stereo", "Analog Stereo Output"),
+            ],
+            1,
+            vec![
+                MockRoute::new("analog-output-speaker", "Speakers", RouteDirection::Output, &[1, 2], 0),
+                MockRoute::new("analog-output-headphones", "Headphones", RouteDirection::Output, &[1, 2], 0).unavailable(),
+                MockRoute::new("analog-input-internal-mic", "Internal Microphone", RouteDirection::Input, &[1], 1),
+                MockRoute::new("analog-input-mic", "Microphone", RouteDirection::Input, &[1], 1).unavailable(),
+            ],
+        );
+        self.add_node(
+            &[
+                ("node.name", "alsa_output.pci-0000_00_1f.3.analog-stereo"),
+                ("node.description", "Built-in Audio Analog Stereo"),
+                ("media.class", "Audio/Sink"),
+                ("device.api", "alsa"),
+                ("api.alsa.path", "front:0"),
+            ],
+            &["FL", "FR"],
+            Some((builtin, 0)),
+        );
+        self.add_node(
+            &[
+                ("node.name", "alsa_input.pci-0000_00_1f.3.analog-stereo"),
+                ("node.description", "Built-in Audio Analog Stereo"),
+                ("media.class", "Audio/Source"),
+                ("device.api", "alsa"),
+                ("api.alsa.path", "front:0"),
+            ],
+            &["FL", "FR"],
+            Some((builtin, 1)),
+        );
+
+        let hdmi = self.add_device(
+            &[
+                ("device.name", "alsa_card.pci-0000_01_00.1"),
+                ("device.description", "HDA NVidia"),
+                ("device.api", "alsa"),
+                ("device.bus", "pci"),
+                ("device.icon-name", "video-display"),
+            ],
+            &[("off", "Off"), ("output:hdmi-surround", "Digital Surround 5.1 (HDMI) Output")],
+            1,
+            vec![MockRoute::new("hdmi-output-0", "HDMI / DisplayPort", RouteDirection::Output, &[1], 0)],
+        );
+        self.add_node(
+            &[
+                ("node.name", "alsa_output.pci-0000_01_00.1.hdmi-surround"),
+                ("node.description", "HDA NVidia Digital Surround 5.1 (HDMI)"),
+                ("media.class", "Audio/Sink"),
+                ("device.api", "alsa"),
+                ("api.alsa.path", "hdmi:1"),
+            ],
+            &["FL", "FR", "FC", "LFE", "RL", "RR"],
+            Some((hdmi, 0)),
+        );
+
+        let headset = self.add_device(
+            &[
+                ("device.name", "bluez_card.00_1B_66_AA_BB_CC"),
+                ("device.description", "WH-1000XM4"),
+                ("device.api", "bluez5"),
+                ("device.bus", "bluetooth"),
+                ("device.icon-name", "audio-headphones"),
+                ("api.bluez5.address", "00:1B:66:AA:BB:CC"),
+            ],
+            &[
+                ("off", "Off"),
+                ("a2dp-sink", "High Fidelity Playback (A2DP Sink)"),
+                ("a2dp-sink-sbc", "High Fidelity Playback (A2DP Sink, codec SBC)"),
+                ("a2dp-sink-aac", "High Fidelity Playback (A2DP Sink, codec AAC)"),
+                ("a2dp-sink-ldac", "High Fidelity Playback (A2DP Sink, codec LDAC)"),
+                ("headset-head-unit", "Headset Head Unit (HSP/HFP)"),
+                ("headset-head-unit-msbc", "Headset Head Unit (HSP/HFP, codec mSBC)"),
+            ],
+            4,
+            vec![MockRoute::new("headset-output", "Headphones", RouteDirection::Output, &[1, 2, 3, 4, 5, 6], 0)],
+        );
+        self.add_node(
+            &[
+                ("node.name", "bluez_output.00_1B_66_AA_BB_CC.1"),
+                ("node.description", "WH-1000XM4"),
+                ("media.class", "Audio/Sink"),
+                ("device.api", "bluez5"),
+                ("api.bluez5.codec", "ldac"),
+            ],
+            &["FL", "FR"],
+            Some((headset, 0)),
+        );
+
+        let microphone = self.add_device(
+            &[
+                ("device.name", "alsa_card.usb-Blue_Microphones_Yeti-00"),
+                ("device.description", "Yeti Stereo Microphone"),
+                ("device.api", "alsa"),
+                ("device.bus", "usb"),
+                ("device.icon-name", "audio-input-microphone"),
+            ],
+            &[("off", "Off"), ("input:analog-stereo", "Analog Stereo Input")],
+            1,
+            vec![MockRoute::new("analog-input", "Microphone", RouteDirection::Input, &[1], 0)],
+        );
+        self.add_node(
+            &[
+                ("node.name", "alsa_input.usb-Blue_Microphones_Yeti-00.analog-stereo"),
+                ("node.description", "Yeti Stereo Microphone Analog Stereo"),
+                ("media.class", "Audio/Source"),
+                ("device.api", "alsa"),
+                ("api.alsa.path", "front:2"),
+            ],
+            &["FL", "FR"],
+            Some((microphone, 0)),
+        );
+
+        let firefox = self.add_node(
+            &[
+                ("node.name", "Firefox"),
+                ("media.class", "Stream/Output/Audio"),
+                ("media.name", "Big Buck Bunny - YouTube"),
+                ("application.name", "Firefox"),
+                ("application.icon-name", "firefox"),
+                ("application.process.id", "4242"),
+            ],
+            &["FL", "FR"],
+            None,
+        );
+        self.set_node_running(firefox, true);
+
+        let music = self.add_node(
+            &[
+                ("node.name", "Music Player"),
+                ("media.class", "Stream/Output/Audio"),
+                ("media.name", "Symphony No. 9 - IV. Presto"),
+                ("application.name", "Music Player"),
+                ("application.icon-name", "multimedia-player"),
+                ("application.process.id", "4343"),
+            ],
+            &["FL", "FR"],
+            None,
+        );
+        self.set_node_running(music, true);
+
+        let chat = self.add_node(
+            &[
+                ("node.name", "Voice chat"),
+                ("media.class", "Stream/Input/Audio"),
+                ("media.name", "Voice chat capture"),
+                ("application.name", "Voice chat"),
+                ("application.icon-name", "call-start"),
+                ("application.process.id", "4444"),
+            ],
+            &["MONO"],
+            None,
+        );
+        self.set_node_running(chat, true);
+    }
+
+    fn run_demo_script(&self) {
+        let state = Rc::downgrade(&self.state);
+        let with_backend = move |f: &dyn Fn(&MockBackend)| -> ControlFlow {
+            match state.upgrade() {
+                Some(state) => {
+                    f(&MockBackend { state });
+                    ControlFlow::Continue
+                }
+                None => ControlFlow::Break,
+            }
+        };
+
+        let script = with_backend.clone();
+        glib::timeout_add_local(Duration::from_secs(4), move || {
+            script(&|backend| {
+                if let Some(music) = backend.node_by_name("Music Player") {
+                    backend.set_node_running(music.id, !music.running.get());
+                }
+            })
+        });
+
+        let script = with_backend.clone();
+        glib::timeout_add_local(Duration::from_secs(20), move || {
+            script(&|backend| {
+                let device = backend.state.devices.borrow().iter().find(|device| device.name() == "alsa_card.pci-0000_00_1f.3").cloned();
+                if let Some(device) = device {
+                    let plugged = device.routes.borrow().get(1).is_some_and(|route| route.available);
+                    pwvucontrol_info!("Demo: headphones {}", if plugged { "unplugged" } else { "plugged in" });
+                    device.set_route_available(1, !plugged);
+                }
+            })
+        });
+
+        let script = with_backend.clone();
+        glib::timeout_add_local(Duration::from_secs(30), move || {
+            script(&|backend| {
+                let device = backend.state.devices.borrow().iter().find(|device| device.name() == "bluez_card.00_1B_66_AA_BB_CC").cloned();
+                if let Some(device) = device {
+                    let battery = device.battery.get().unwrap_or(100);
+                    let battery = if battery <= 10 { 100 } else { battery - 5 };
+                    backend.set_device_battery(device.id, Some(battery));
+                }
+            })
+        });
+
+        glib::timeout_add_local(Duration::from_secs(15), move || {
+            with_backend(&|backend| match backend.node_by_name("Meeting") {
+                Some(meeting) => backend.remove_node(meeting.id),
+                None => {
+                    let id = backend.add_node(
+                        &[
+                            ("node.name", "Meeting"),
+                            ("media.class", "Stream/Output/Audio"),
+                            ("media.name", "Weekly meeting"),
+                            ("application.name", "Meeting"),
+                            ("application.icon-name", "camera-web"),
+                            ("application.process.id", "4545"),
+                        ],
+                        &["FL", "FR"],
+                        None,
+                    );
+                    backend.set_node_running(id, true);
+                }
+            })
+        });
+    }
+}
+
+impl Default for MockBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AudioBackend for MockBackend {
+    fn start(&self, manager: &PwvucontrolManager) {
+        self.state.manager.set(Some(manager));
+        manager.set_connected(true);
+
+        let devices = self.state.devices.borrow().clone();
+        for device in devices {
+            manager.add_device(device);
+        }
+        let nodes = self.state.nodes.borrow().clone();
+        for node in nodes {
+            manager.add_node(node);
+        }
+
+        manager.default_nodes_changed();
+        manager.settings_changed();
+    }
+
+    fn reconnect(&self) {}
+
+    fn is_connected(&self) -> bool {
+        self.state.manager.upgrade().is_some()
+    }
+
+    fn default_node(&self, media_class: &str) -> Option<u32> {
+        let nodes = self.state.nodes.borrow();
+        let candidates: Vec<&Rc<MockNode>> = nodes.iter().filter(|node| node.property("media.class").as_deref() == Some(media_class)).collect();
+
+        let configured = self.state.configured_defaults.borrow().get(media_class).cloned();
+        configured
+            .and_then(|name| candidates.iter().find(|node| node.property("node.name") == Some(name.clone())))
+            .or_else(|| candidates.first())
+            .map(|node| node.id)
+    }
+
+    fn default_configured_node_name(&self, media_class: &str) -> Option<String> {
+        self.state.configured_defaults.borrow().get(media_class).cloned()
+    }
+
+    fn set_default_configured_node_name(&self, media_class: &str, node_name: &str) -> bool {
+        {
+            let mut defaults = self.state.configured_defaults.borrow_mut();
+            if node_name.is_empty() {
+                defaults.remove(media_class);
+            } else {
+                defaults.insert(media_class.to_string(), node_name.to_string());
+            }
+        }
+        self.notify_manager(|manager| manager.default_nodes_changed());
+        true
+    }
+
+    fn metadata_value(&self, subject: u32, key: &str) -> Option<String> {
+        self.state.metadata.borrow().get(&(subject, key.to_string())).cloned()
+    }
+
+    fn set_metadata_value(&self, subject: u32, key: &str, _type_: Option<&str>, value: Option<&str>) {
+        {
+            let mut metadata = self.state.metadata.borrow_mut();
+            match value {
+                Some(value) => metadata.insert((subject, key.to_string()), value.to_string()),
+                None => metadata.remove(&(subject, key.to_string())),
+            };
+        }
+        let key = key.to_string();
+        self.notify_manager(move |manager| manager.metadata_changed(subject, &key));
+    }
+
+    fn has_settings(&self) -> bool {
+        true
+    }
+
+    fn setting(&self, key: &str) -> Option<String> {
+        self.state.settings.borrow().get(key).cloned()
+    }
+
+    fn set_setting(&self, key: &str, value: Option<&str>) {
+        {
+            let mut settings = self.state.settings.borrow_mut();
+            let default = DEFAULT_SETTINGS.iter().find(|(k, _)| *k == key).map(|(_, v)| v.to_string());
+            match value.map(str::to_string).or(default) {
+                Some(value) => settings.insert(key.to_string(), value),
+                None => settings.remove(key),
+            };
+        }
+        self.notify_manager(|manager| manager.settings_changed());
+    }
+
+    fn node_profile(&self, id: u32) -> Option<NodeProfile> {
+        let node = self.node(id)?;
+        if !node.running.get() {
+            return None;
+        }
+
+        let driver = self.default_node("Audio/Sink").and_then(|id| self.node(id)).unwrap_or(node);
+        let rate = self.setting("clock.rate").and_then(|rate| rate.parse().ok()).unwrap_or(48000);
+        // Made up timings, enough to tell the nodes apart when sorting.
+        let profile = NodeProfile {
+            id,
+            name: node.property("node.name").unwrap_or_default(),
+            driver_id: driver.id,
+            driver_name: driver.property("node.name").unwrap_or_default(),
+            quantum: 1024,
+            rate,
+            latency: (1024, rate),
+            xruns: 0,
+            wait: 20_000 + id as i64 * 1_000,
+            busy: 50_000 + id as i64 * 3_000,
+            ..Default::default()
+        };
+        Some(NodeProfile {
+            wait_quantum: profile.share_of_period(profile.wait),
+            busy_quantum: profile.share_of_period(profile.busy),
+            dsp_load: 0.05,
+            ..profile
+        })
+    }
+
+    fn node_profiles(&self) -> Vec<NodeProfile> {
+        let ids: Vec<u32> = self.state.nodes.borrow().iter().map(|node| node.id).collect();
+        ids.into_iter().filter_map(|id| self.node_profile(id)).collect()
+    }
+
+    fn profiler_available(&self) -> bool {
+        true
+    }
+
+    fn can_monitor(&self) -> bool {
+        false
+    }
+
+    fn can_create_streams(&self) -> bool {
+        false
+    }
+}
+
+struct MockNode {
+    id: u32,
+    properties: RefCell<HashMap<String, String>>,
+    device: Option<(u32, i32)>,
+    running: Cell<bool>,
+    volume: RefCell<NodeVolume>,
+    props: RefCell<NodeProps>,
+    format: Cell<AudioFormat>,
+    handlers: RefCell<Vec<Rc<dyn Fn(NodeChange)>>>,
+}
+
+impl MockNode {
+    fn new(id: u32, properties: &[(&str, &str)], channels: &[&str], device: Option<(u32, i32)>) -> Self {
+        let mut properties: HashMap<String, String> = properties.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect();
+        properties.insert("object.id".to_string(), id.to_string());
+        properties.insert("object.serial".to_string(), id.to_string());
+        if let Some((device_id, _)) = device {
+            properties.insert("device.id".to_string(), device_id.to_string());
+        }
+
+        let is_stream = properties.get("media.class").is_some_and(|class| class.starts_with("Stream/"));
+        let params = if is_stream {
+            Vec::new()
+        } else {
+            vec![
+                ("channelmix.upmix".to_string(), PropsParamValue::Bool(false)),
+                ("channelmix.normalize".to_string(), PropsParamValue::Bool(false)),
+                ("channelmix.lfe-cutoff".to_string(), PropsParamValue::Float(0.0)),
+            ]
+        };
+
+        let t_audiochannel = wp::spa::SpaIdTable::from_name("Spa:Enum:AudioChannel").expect("audio channel type");
+        let mut positions = [0u32; 64];
+        for (position, channel) in positions.iter_mut().zip(channels) {
+            *position = t_audiochannel.find_value_from_short_name(channel).map(|value| value.number()).unwrap_or_default();
+        }
+        let format = AudioFormat { format: format_id("F32LE"), channels: channels.len() as u32, rate: 48000, positions };
+
+        Self {
+            id,
+            properties: RefCell::new(properties),
+            device,
+            running: Cell::new(false),
+            volume: RefCell::new(NodeVolume { mute: false, channel_volumes: vec![1.0; channels.len()] }),
+            props: RefCell::new(NodeProps { volume: Some(1.0), monitor_volumes: vec![1.0; channels.len()], params, ..Default::default() }),
+            format: Cell::new(format),
+            handlers: Default::default(),
+        }
+    }
+
+    /// Reports `change` to the handlers once the current change is processed.
+    fn changed(&self, change: NodeChange) {
+        let handlers = self.handlers.borrow().clone();
+        glib::idle_add_local_once(move || {
+            for handler in handlers {
+                handler(change);
+            }
+        });
+    }
+}
+
+impl NodeBackend for MockNode {
+    fn id(&self) -> u32 {
+        self.id
+    }
+
+    fn property(&self, key: &str) -> Option<String> {
+        self.properties.borrow().get(key).cloned()
+    }
+
+    fn is_running(&self) -> bool {
+        self.running.get()
+    }
+
+    fn device_id(&self) -> Option<u32> {
+        self.device.map(|(id, _)| id)
+    }
+
+    fn card_device(&self) -> Option<i32> {
+        self.device.map(|(_, card_device)| card_device)
+    }
+
+    fn connect_changed(&self, handler: Rc<dyn Fn(NodeChange)>) {
+        self.handlers.borrow_mut().push(handler);
+    }
+
+    fn volume(&self) -> Option<NodeVolume> {
+        Some(self.volume.borrow().clone())
+    }
+
+    fn set_volume(&self, channel_volumes: Option<&[f32]>, _positions: &[u32], mute: Option<bool>) -> bool {
+        {
+            let mut volume = self.volume.borrow_mut();
+            if let Some(channel_volumes) = channel_volumes {
+                volume.channel_volumes = channel_volumes.to_vec();
+            }
+            if let Some(mute) = mute {
+                volume.mute = mute;
+            }
+        }
+        self.changed(NodeChange::Volume);
+        true
+    }
+
+    fn props(&self) -> Option<NodeProps> {
+        Some(self.props.borrow().clone())
+    }
+
+    fn set_main_volume(&self, volume: f32) {
+        self.props.borrow_mut().volume = Some(volume);
+        self.changed(NodeChange::Props);
+    }
+
+    fn set_monitor_volumes(&self, volumes: &[f32]) {
+        self.props.borrow_mut().monitor_volumes = volumes.to_vec();
+        self.changed(NodeChange::Props);
+    }
+
+    fn set_props_params(&self, params: &[(&str, PropsParamValue)]) {
+        {
+            let mut props = self.props.borrow_mut();
+            for (key, value) in params {
+                match props.params.iter_mut().find(|(k, _)| k == key) {
+                    Some((_, v)) => *v = value.clone(),
+                    None => props.params.push((key.to_string(), value.clone())),
+                }
+            }
+        }
+
+        let mut format = self.format.get();
+        for (key, value) in params {
+            match (*key, value) {
+                ("audio.rate", PropsParamValue::Int(rate)) => format.rate = *rate as u32,
+                ("audio.format", PropsParamValue::String(name)) => format.format = format_id(name),
+                _ => {}
+            }
+        }
+        self.format.set(format);
+
+        self.changed(NodeChange::Props);
+    }
+
+    fn format(&self, callback: Box<dyn FnOnce(Option<AudioFormat>)>) {
+        let format = self.format.get();
+        glib::idle_add_local_once(move || callback(Some(format)));
+    }
+
+    fn supported_formats(&self) -> SupportedFormats {
+        if self.property("device.api").as_deref() != Some("alsa") {
+            return SupportedFormats::default();
+        }
+        SupportedFormats {
+            rates: vec![44100, 48000, 88200, 96000],
+            formats: ["F32LE", "S16LE", "S24LE", "S32LE"].map(str::to_string).to_vec(),
+        }
+    }
+
+    fn set_port_config(&self, _direction: RouteDirection, rate: u32, positions: &[u32]) {
+        let mut format = self.format.get();
+        format.rate = rate;
+        format.channels = positions.len() as u32;
+        format.positions = [0; 64];
+        format.positions.iter_mut().zip(positions).for_each(|(position, new)| *position = *new);
+        self.format.set(format);
+
+        {
+            let mut volume = self.volume.borrow_mut();
+            let max = volume.channel_volumes.iter().copied().max_by(f32::total_cmp).unwrap_or(1.0);
+            volume.channel_volumes = vec![max; positions.len()];
+        }
+        self.props.borrow_mut().monitor_volumes.resize(positions.len(), 1.0);
+
+        self.changed(NodeChange::Format);
+        self.changed(NodeChange::Volume);
+    }
+
+    fn suspend(&self) {
+        if self.running.replace(false) {
+            self.changed(NodeChange::State);
+        }
+        // The new format is picked up when the node starts again.
+        self.changed(NodeChange::Format);
+    }
+
+    fn dump(&self) -> Vec<DumpSection> {
+        let volume = self.volume.borrow();
+        vec![
+            DumpSection { title: "Properties".into(), entries: sorted_entries(&self.properties.borrow()) },
+            DumpSection {
+                title: "Volume".into(),
+                entries: vec![
+                    ("mute".into(), volume.mute.to_string()),
+                    ("channelVolumes".into(), format!("{:?}", volume.channel_volumes)),
+                ],
+            },
+        ]
+    }
+}
+
+struct MockDevice {
+    id: u32,
+    properties: RefCell<HashMap<String, String>>,
+    profiles: Vec<ProfileInfo>,
+    profile: Cell<u32>,
+    routes: RefCell<Vec<MockRoute>>,
+    active_routes: RefCell<Vec<ActiveRoute>>,
+    handlers: RefCell<Vec<Rc<dyn Fn(DeviceChange)>>>,
+    battery: Cell<Option<u32>>,
+}
+
+impl MockDevice {
+    fn new(id: u32, properties: &[(&str, &str)], profiles: &[(&str, &str)], profile: u32, routes: Vec<MockRoute>) -> Self {
+        let mut properties: HashMap<String, String> = properties.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect();
+        properties.insert("object.id".to_string(), id.to_string());
+
+        let profiles = (0..)
+            .zip(profiles)
+            .map(|(index, (name, description))| ProfileInfo {
+                index,
+                name: name.to_string(),
+                description: description.to_string(),
+                availability: ParamAvailability::Yes,
+            })
+            .collect();
+
+        let device = Self {
+            id,
+            properties: RefCell::new(properties),
+            profiles,
+            profile: Cell::new(profile),
+            routes: RefCell::new(routes),
+            active_routes: Default::default(),
+            handlers: Default::default(),
+            battery: Default::default(),
+        };
+
+        let card_devices: Vec<i32> = device.routes.borrow().iter().map(|route| route.device).collect();
+        for card_device in card_devices {
+            device.activate_first_available(card_device, None);
+        }
+        device
+    }
+
+    fn name(&self) -> String {
+        self.property("device.name").unwrap_or_default()
+    }
+
+    fn changed(&self, change: DeviceChange) {
+        let handlers = self.handlers.borrow().clone();
+        glib::idle_add_local_once(move || {
+            for handler in handlers {
+                handler(change);
+            }
+        });
+    }
+
+    fn activate(&self, index: u32, card_device: i32, channel_volumes: Option<Vec<f32>>, mute: Option<bool>) {
+        let Some(route) = self.routes.borrow().get(index as usize).cloned() else {
+            return;
+        };
+
+        let mut active_routes = self.active_routes.borrow_mut();
+        let previous = active_routes.iter().position(|active| active.device == card_device);
+        let channel_volumes = channel_volumes
+            .or_else(|| previous.map(|previous| active_routes[previous].channel_volumes.clone()))
+            .unwrap_or_else(|| vec![1.0, 1.0]);
+        let mute = mute.or_else(|| previous.map(|previous| active_routes[previous].mute)).unwrap_or_default();
+
+        let active = ActiveRoute {
+            index: index as i32,
+            device: card_device,
+            name: route.name,
+            description: route.description,
+            direction: route.direction,
+            channel_volumes,
+            mute,
+        };
+        match previous {
+            Some(previous) => active_routes[previous] = active,
+            None => active_routes.push(active),
+        }
+    }
+
+    /// Switches `card_device` to its first available route, keeping the current one if it still is.
+    fn activate_first_available(&self, card_device: i32, direction: Option<RouteDirection>) {
+        let current = self.active_routes.borrow().iter().find(|active| active.device == card_device).map(|active| active.index as u32);
+        let index = {
+            let routes = self.routes.borrow();
+            let usable = |index: &u32| {
+                let route = &routes[*index as usize];
+                route.available && route.device == card_device && direction.map_or(true, |direction| route.direction == direction)
+            };
+            current.filter(usable).or_else(|| (0..routes.len() as u32).find(usable))
+        };
+
+        if let Some(index) = index {
+            if Some(index) != current {
+                self.activate(index, card_device, None, None);
+            }
+        }
+    }
+
+    fn set_route_available(&self, index: u32, available: bool) {
+        let route = {
+            let mut routes = self.routes.borrow_mut();
+            let Some(route) = routes.get_mut(index as usize) else {
+                return;
+            };
+            route.available = available;
+            route.clone()
+        };
+        self.changed(DeviceChange::Routes);
+
+        let active = self.active_routes.borrow().iter().find(|active| active.device == route.device).map(|active| active.index as u32);
+        let active_available = active.is_some_and(|active| self.routes.borrow()[active as usize].available);
+
+        if available && (route.direction == RouteDirection::Output || !active_available) {
+            // Plugging in headphones switches to them.
+            self.activate(index, route.device, None, None);
+        } else if !available && active == Some(index) {
+            self.activate_first_available(route.device, Some(route.direction));
+        } else {
+            return;
+        }
+        self.changed(DeviceChange::Route);
+    }
+}
+
+impl DeviceBackend for MockDevice {
+    fn id(&self) -> u32 {
+        self.id
+    }
+
+    fn property(&self, key: &str) -> Option<String> {
+        self.properties.borrow().get(key).cloned()
+    }
+
+    fn connect_changed(&self, handler: Rc<dyn Fn(DeviceChange)>) {
+        self.handlers.borrow_mut().push(handler);
+    }
+
+    fn profiles(&self, callback: Box<dyn FnOnce(Vec<ProfileInfo>)>) {
+        let profiles = self.profiles.clone();
+        glib::idle_add_local_once(move || callback(profiles));
+    }
+
+    fn current_profile(&self) -> Option<ProfileInfo> {
+        self.profiles.get(self.profile.get() as usize).cloned()
+    }
+
+    fn set_profile(&self, index: i32) {
+        if self.profiles.get(index as usize).is_none() {
+            pwvucontrol_warning!("Mock device {} has no profile {index}", self.id);
+            return;
+        }
+        self.profile.set(index as u32);
+        self.changed(DeviceChange::Profile);
+    }
+
+    fn routes(&self, callback: Box<dyn FnOnce(Vec<RouteInfo>)>) {
+        let routes = (0..)
+            .zip(self.routes.borrow().iter())
+            .map(|(index, route)| RouteInfo {
+                index,
+                description: route.description.clone(),
+                direction: route.direction,
+                availability: if route.available { ParamAvailability::Yes } else { ParamAvailability::No },
+                profiles: route.profiles.clone(),
+            })
+            .collect();
+        glib::idle_add_local_once(move || callback(routes));
+    }
+
+    fn route_availability(&self) -> Vec<(i32, ParamAvailability)> {
+        (0..)
+            .zip(self.routes.borrow().iter())
+            .map(|(index, route)| (index, if route.available { ParamAvailability::Yes } else { ParamAvailability::No }))
+            .collect()
+    }
+
+    fn active_routes(&self) -> Vec<ActiveRoute> {
+        self.active_routes.borrow().clone()
+    }
+
+    fn set_route(&self, index: i32, device: i32, props: &RouteProps) {
+        self.activate(index as u32, device, props.channel_volumes.clone(), props.mute);
+        self.changed(DeviceChange::Route);
+    }
+
+    fn dump(&self) -> Vec<DumpSection> {
+        let profile = self.current_profile().map(|profile| profile.name).unwrap_or_default();
+        let routes = self.active_routes.borrow().iter().map(|route| (route.device.to_string(), route.name.clone())).collect();
+        vec![
+            DumpSection { title: "Properties".into(), entries: sorted_entries(&self.properties.borrow()) },
+            DumpSection { title: "Profile".into(), entries: vec![("name".into(), profile)] },
+            DumpSection { title: "Route".into(), entries: routes },
+        ]
+    }
+
+    fn battery_level(&self) -> Option<u32> {
+        self.battery.get()
+    }
+}
+
+/// Id of a sample format short name like `F32LE`.
+fn format_id(name: &str) -> u32 {
+    let t_format = wp::spa::SpaIdTable::from_name("Spa:Enum:AudioFormat").expect("audio format type");
+    t_format.find_value_from_short_name(name).map(|value| value.number()).unwrap_or_default()
+}
+
+fn sorted_entries(properties: &HashMap<String, String>) -> Vec<(String, String)> {
+    let mut entries: Vec<(String, String)> = properties.iter().map(|(key, value)| (key.clone(), value.clone())).collect();
+    entries.sort();
+    entries
+}
@@ -0,0 +1,1117 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use super::{bluezbattery::BluezBattery, *};
+use crate::{
+    backend::{
+        dump_object, json_object_string, json_string, parse_active_route, parse_format, parse_profile, parse_props, parse_route, ParamResult,
+        PwNodeObject, PwProfiler,
+    },
+    macros::*,
+};
+use glib::{clone, SignalHandlerId};
+use gtk::prelude::*;
+use std::{
+    cell::{Cell, OnceCell, RefCell},
+    collections::HashMap,
+    time::Duration,
+};
+use wireplumber as wp;
+use wp::{
+    plugin::{Plugin, PluginFeatures, *},
+    pw::{GlobalProxyExt, MetadataExt, PipewireObjectExt, PipewireObjectExt2, ProxyExt},
+    registry::{Constraint, ConstraintType, Interest, ObjectManager},
+    spa::SpaPodBuilder,
+};
+
+// From spa/param/format.h, spa/param/audio/raw.h and spa/param/port-config.h
+const SPA_MEDIA_TYPE_AUDIO: u32 = 1;
+const SPA_MEDIA_SUBTYPE_RAW: u32 = 1;
+const SPA_AUDIO_FORMAT_F32P: u32 = 0x206;
+const SPA_PARAM_PORT_CONFIG_MODE_DSP: u32 = 3;
+
+/// Rates offered when a device accepts a range instead of a list of rates.
+const COMMON_RATES: [u32; 8] = [22050, 32000, 44100, 48000, 88200, 96000, 176400, 192000];
+
+/// Delay before the first reconnection attempt, doubled after every failed one.
+const RECONNECT_DELAY: Duration = Duration::from_millis(500);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// Talks to PipeWire through WirePlumber's mixer-api and default-nodes-api plugins.
+///
+/// Either plugin can be missing, for example with a session manager other than WirePlumber.
+/// Volumes are then set through the `Props` param of the nodes and the defaults are read from
+/// and written to the `default` metadata directly, and the manager is marked as degraded.
+///
+/// When the connection is lost, everything tied to it is dropped and the manager's models are
+/// cleared, then a new connection is tried with increasing delays until PipeWire is back.
+pub struct WpBackend {
+    inner: Rc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    manager: glib::WeakRef<PwvucontrolManager>,
+    /// The current connection, rebuilt from scratch after PipeWire restarts.
+    session: RefCell<Option<Rc<Session>>>,
+    reconnect_source: RefCell<Option<glib::SourceId>>,
+    /// Reconnection attempts since the last successful connection.
+    attempts: Cell<u32>,
+}
+
+/// Everything that belongs to one connection to PipeWire.
+struct Session {
+    core: wp::core::Core,
+    om: ObjectManager,
+    metadata_om: ObjectManager,
+    mixer_api: OnceCell<Plugin>,
+    default_nodes_api: OnceCell<Plugin>,
+    metadata: RefCell<Option<wp::pw::Metadata>>,
+    /// The `settings` metadata holding the global clock settings.
+    settings_metadata: RefCell<Option<wp::pw::Metadata>>,
+    /// Started on first use, `None` when connecting to PipeWire for it failed.
+    profiler: OnceCell<Option<PwProfiler>>,
+}
+
+impl WpBackend {
+    pub fn new() -> Self {
+        wp::core::Core::init_with_flags(wp::InitFlags::ALL);
+
+        if !wp::Log::level_is_enabled(glib::LogLevelFlags::LEVEL_WARNING) {
+            wp::Log::set_default_level("1");
+        }
+
+        Self { inner: Default::default() }
+    }
+
+    fn session(&self) -> Option<Rc<Session>> {
+        self.inner.session.borrow().clone()
+    }
+
+    fn metadata(&self) -> Option<wp::pw::Metadata> {
+        self.session()?.metadata.borrow().clone()
+    }
+
+    fn settings_metadata(&self) -> Option<wp::pw::Metadata> {
+        self.session()?.settings_metadata.borrow().clone()
+    }
+
+    fn default_nodes_api(&self) -> Option<Plugin> {
+        self.session()?.default_nodes_api.get().cloned()
+    }
+
+    /// The node name in a `default` metadata entry like `default.audio.sink`.
+    fn default_metadata_name(&self, key: &str) -> Option<String> {
+        let value = self.metadata_value(0, key)?;
+        json_name(&value)
+    }
+}
+
+impl Default for WpBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Inner {
+    fn connect(self: &Rc<Self>) {
+        let Some(manager) = self.manager.upgrade() else {
+            return;
+        };
+
+        let session = Session::new(self, &manager);
+        self.session.replace(Some(session.clone()));
+
+        if !session.core.connect() {
+            pwvucontrol_warning!("Cannot connect to PipeWire");
+            self.session.replace(None);
+            self.schedule_reconnect();
+        }
+    }
+
+    fn connected(&self) {
+        self.attempts.set(0);
+        if let Some(manager) = self.manager.upgrade() {
+            manager.set_connected(true);
+        }
+    }
+
+    fn disconnected(self: &Rc<Self>) {
+        pwvucontrol_warning!("Lost connection to PipeWire");
+        self.teardown();
+        self.schedule_reconnect();
+    }
+
+    /// Drops the connection and everything the manager got from it.
+    fn teardown(&self) {
+        if let Some(session) = self.session.take() {
+            session.core.disconnect();
+        }
+
+        if let Some(manager) = self.manager.upgrade() {
+            manager.set_connected(false);
+            manager.clear();
+            manager.default_nodes_changed();
+            manager.settings_changed();
+        }
+    }
+
+    fn schedule_reconnect(self: &Rc<Self>) {
+        if self.reconnect_source.borrow().is_some() {
+            return;
+        }
+
+        let attempts = self.attempts.get();
+        self.attempts.set(attempts + 1);
+        let delay = RECONNECT_DELAY.saturating_mul(1 << attempts.min(6)).min(RECONNECT_MAX_DELAY);
+        pwvucontrol_info!("Reconnecting to PipeWire in {} ms", delay.as_millis());
+
+        let inner = Rc::downgrade(self);
+        let source = glib::timeout_add_local_once(delay, move || {
+            if let Some(inner) = inner.upgrade() {
+                inner.reconnect_source.replace(None);
+                inner.connect();
+            }
+        });
+        self.reconnect_source.replace(Some(source));
+    }
+
+    /// Tries to connect right away, for the reconnect button.
+    fn reconnect_now(self: &Rc<Self>) {
+        if let Some(source) = self.reconnect_source.take() {
+            source.remove();
+        }
+        self.attempts.set(0);
+
+        if self.session.borrow().as_ref().is_some_and(|session| session.core.is_connected()) {
+            return;
+        }
+        self.teardown();
+        self.connect();
+    }
+}
+
+impl Session {
+    fn new(inner: &Rc<Inner>, manager: &PwvucontrolManager) -> Rc<Self> {
+        let props = wp::pw::Properties::new_string("media.category=Manager");
+        let core = wp::core::Core::new(Some(&glib::MainContext::default()), Some(props));
+
+        let weak_inner = Rc::downgrade(inner);
+        core.connect_connected(move |_| {
+            if let Some(inner) = weak_inner.upgrade() {
+                inner.connected();
+            }
+        });
+        let weak_inner = Rc::downgrade(inner);
+        core.connect_disconnected(move |_| {
+            // Tearing down drops this core, so leave its signal emission first.
+            let weak_inner = weak_inner.clone();
+            glib::idle_add_local_once(move || {
+                if let Some(inner) = weak_inner.upgrade() {
+                    inner.disconnected();
+                }
+            });
+        });
+
+        if let Err(e) = core.load_component("libwireplumber-module-mixer-api", "module", None) {
+            pwvucontrol_critical!("Cannot load mixer-api: {e}");
+        }
+        if let Err(e) = core.load_component("libwireplumber-module-default-nodes-api", "module", None) {
+            pwvucontrol_critical!("Cannot load default-nodes-api: {e}");
+        }
+
+        let session = Rc::new(Session {
+            core,
+            om: ObjectManager::new(),
+            metadata_om: ObjectManager::new(),
+            mixer_api: Default::default(),
+            default_nodes_api: Default::default(),
+            metadata: Default::default(),
+            settings_metadata: Default::default(),
+            profiler: Default::default(),
+        });
+        session.setup_object_manager(manager);
+        session.setup_metadata_om(manager);
+        session
+    }
+
+    fn setup_object_manager(self: &Rc<Self>, manager: &PwvucontrolManager) {
+        let wp_om = &self.om;
+
+        wp_om.add_interest({
+            let interest: Interest<wp::pw::Node> = wp::registry::Interest::new();
+            let variant = glib::Variant::tuple_from_iter(
+                ["Stream/Output/Audio", "Stream/Input/Audio", "Audio/Source", "Audio/Source/Virtual", "Audio/Sink"].map(ToVariant::to_variant),
+            );
+
+            interest.add_constraint(
+                wp::registry::ConstraintType::PwGlobalProperty,
+                "media.class",
+                wp::registry::ConstraintVerb::InList,
+                Some(&variant),
+            );
+
+            interest
+        });
+
+        wp_om.add_interest({
+            let interest: Interest<wp::pw::Device> = wp::registry::Interest::new();
+            interest.add_constraint(
+                wp::registry::ConstraintType::PwGlobalProperty,
+                "media.class",
+                wp::registry::ConstraintVerb::Equals,
+                Some(&"Audio/Device".to_variant()),
+            );
+
+            interest
+        });
+
+        wp_om.request_object_features(wp::pw::Node::static_type(), wp::core::ObjectFeatures::ALL);
+
+        wp_om.request_object_features(wp::pw::GlobalProxy::static_type(), wp::core::ObjectFeatures::ALL);
+
+        let session = Rc::downgrade(self);
+        wp_om.connect_object_added(clone!(@weak manager => move |_, object| {
+            if let Some(node) = object.downcast_ref::<wp::pw::Node>() {
+                pwvucontrol_info!("Got node: {} bound id {}", node.name().unwrap_or_default(), node.bound_id());
+                let mixer_api = session.upgrade().and_then(|session| session.mixer_api.get().cloned());
+                manager.add_node(Rc::new(WpNodeBackend::new(node, mixer_api)));
+            } else if let Some(device) = object.downcast_ref::<wp::pw::Device>() {
+                pwvucontrol_info!("Got device: {} bound id {}", device.pw_property::<String>("device.name").unwrap_or_default(), device.bound_id());
+                manager.add_device(Rc::new(WpDeviceBackend { device: device.clone(), battery: Default::default() }));
+            } else {
+                unreachable!("Object must be one of the above, but is {:?} instead", object.type_());
+            }
+        }));
+
+        wp_om.connect_object_removed(clone!(@weak manager => move |_, object| {
+            if let Some(node) = object.downcast_ref::<wp::pw::Node>() {
+                pwvucontrol_info!("removed: {} id: {}", node.name().unwrap_or_default(), node.bound_id());
+                manager.remove_node_by_id(node.bound_id());
+            } else if let Some(device) = object.downcast_ref::<wp::pw::Device>() {
+                manager.remove_device_by_id(device.bound_id());
+            } else {
+                pwvucontrol_info!("Object must be one of the above, but is {:?} instead", object.type_());
+            }
+        }));
+
+        let session = Rc::downgrade(self);
+        glib::MainContext::default().spawn_local(clone!(@weak manager => async move {
+            let Some(session) = session.upgrade() else {
+                return;
+            };
+            for (plugin_name, plugin_cell) in [("mixer-api", &session.mixer_api), ("default-nodes-api", &session.default_nodes_api)] {
+                let Some(plugin) = Plugin::find(&session.core, plugin_name) else {
+                    pwvucontrol_warning!("Cannot find plugin {plugin_name}, continuing without it");
+                    continue;
+                };
+                if let Err(e) = plugin.activate_future(PluginFeatures::ENABLED).await {
+                    pwvucontrol_warning!("Cannot activate plugin {plugin_name}, continuing without it: {e}");
+                    continue;
+                }
+                if plugin_cell.set(plugin).is_err() {
+                    pwvucontrol_critical!("Plugin {plugin_name} activated twice");
+                }
+                pwvucontrol_info!("Activated plugin {plugin_name}");
+            }
+
+            if let Some(api) = session.default_nodes_api.get() {
+                api.connect_local("changed", false, clone!(@weak manager => @default-return None, move |_| {
+                    manager.default_nodes_changed();
+                    None
+                }));
+            }
+            manager.set_degraded(session.mixer_api.get().is_none() || session.default_nodes_api.get().is_none());
+            session.core.install_object_manager(&session.om);
+            manager.default_nodes_changed();
+        }));
+    }
+
+    fn setup_metadata_om(self: &Rc<Self>, manager: &PwvucontrolManager) {
+        let metadata_om = &self.metadata_om;
+
+        for name in ["default", "settings"] {
+            metadata_om.add_interest(
+                [Constraint::compare(ConstraintType::PwGlobalProperty, "metadata.name", name, true)]
+                    .iter()
+                    .collect::<Interest<wp::pw::Metadata>>(),
+            );
+        }
+
+        metadata_om.request_object_features(wp::pw::GlobalProxy::static_type(), wp::core::ObjectFeatures::ALL);
+
+        let session = Rc::downgrade(self);
+        metadata_om.connect_object_added(clone!(@weak manager => move |_, object| {
+            if let Some(session) = session.upgrade() {
+                session.metadata_object_added(&manager, object);
+            }
+        }));
+
+        // WirePlumber restarting takes its metadata along, a new one is added once it is back.
+        let session = Rc::downgrade(self);
+        metadata_om.connect_object_removed(clone!(@weak manager => move |_, object| {
+            let Some(session) = session.upgrade() else {
+                return;
+            };
+            let removed = |cell: &RefCell<Option<wp::pw::Metadata>>| {
+                if cell.borrow().as_ref().is_some_and(|metadata| metadata.upcast_ref::<glib::Object>() == object) {
+                    cell.replace(None);
+                }
+            };
+            removed(&session.metadata);
+            removed(&session.settings_metadata);
+            manager.settings_changed();
+        }));
+
+        self.core.install_object_manager(metadata_om);
+    }
+
+    fn metadata_object_added(self: &Rc<Self>, manager: &PwvucontrolManager, object: &glib::Object) {
+        if let Some(metadataobj) = object.dynamic_cast_ref::<wp::pw::Metadata>() {
+            let name = metadataobj.global_properties().and_then(|props| props.get("metadata.name"));
+            if name.as_deref() == Some("settings") {
+                self.settings_metadata.replace(Some(metadataobj.clone()));
+                metadataobj.connect_changed(clone!(@weak manager => move |_, _, _, _, _| manager.settings_changed()));
+                manager.settings_changed();
+                return;
+            }
+
+            self.metadata.replace(Some(metadataobj.clone()));
+
+            if let Some(iterator) = metadataobj.new_iterator(u32::MAX) {
+                for a in iterator {
+                    let (s, k, t, v) = wp::pw::Metadata::iterator_item_extract(&a);
+                    Self::log_metadata_change(s, Some(&k), Some(&t), Some(&v));
+                }
+            }
+
+            let session = Rc::downgrade(self);
+            metadataobj.connect_changed(clone!(@weak manager => move |_, s, k, t, v| {
+                Self::log_metadata_change(s, k, t, v);
+                manager.metadata_changed(s, k.unwrap_or_default());
+
+                // Without the default-nodes-api these entries are all there is to learn about defaults.
+                let without_api = session.upgrade().is_some_and(|session| session.default_nodes_api.get().is_none());
+                if without_api && k.is_some_and(|k| k.starts_with("default.")) {
+                    manager.default_nodes_changed();
+                }
+            }));
+            manager.default_nodes_changed();
+        } else {
+            unreachable!("Object must be one of the above, but is {:?} instead", object.type_());
+        }
+    }
+
+    /// The profiler, started on first use.
+    fn profiler(&self) -> Option<&PwProfiler> {
+        let profiler = self.profiler.get_or_init(|| {
+            PwProfiler::new()
+                .inspect_err(|e| pwvucontrol_warning!("Unable to start profiler: {e}"))
+                .ok()
+        });
+        profiler.as_ref()
+    }
+
+    fn log_metadata_change(_subject: u32, key: Option<&str>, type_: Option<&str>, value: Option<&str>) {
+        if let (Some(key), Some(json_str), Some("Spa:String:JSON")) = (key, value, type_) {
+            if let Some(node_name) = json_name(json_str) {
+                match key {
+                    "default.audio.sink" => {
+                        pwvucontrol_info!("New default sink: {node_name}")
+                    }
+                    "default.audio.source" => {
+                        pwvucontrol_info!("New default source: {node_name}")
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+impl AudioBackend for WpBackend {
+    fn start(&self, manager: &PwvucontrolManager) {
+        self.inner.manager.set(Some(manager));
+        self.inner.connect();
+    }
+
+    fn reconnect(&self) {
+        self.inner.reconnect_now();
+    }
+
+    fn is_connected(&self) -> bool {
+        self.session().is_some_and(|session| session.core.is_connected())
+    }
+
+    fn default_node(&self, media_class: &str) -> Option<u32> {
+        let Some(api) = self.default_nodes_api() else {
+            let name = self.default_metadata_name(&format!("default.{}", default_metadata_key(media_class)?))?;
+            let nodes = self.inner.manager.upgrade()?.node_model();
+            let mut nodes = nodes.iter::<PwNodeObject>().map_while(Result::ok);
+            return nodes.find(|node| node.node_property::<String>("node.name").as_deref() == Some(name.as_str())).map(|node| node.boundid());
+        };
+        let id: u32 = api.emit_by_name("get-default-node", &[&media_class]);
+        (id != u32::MAX).then_some(id)
+    }
+
+    fn default_configured_node_name(&self, media_class: &str) -> Option<String> {
+        let Some(api) = self.default_nodes_api() else {
+            return self.default_metadata_name(&format!("default.configured.{}", default_metadata_key(media_class)?));
+        };
+        api.emit_by_name("get-default-configured-node-name", &[&media_class])
+    }
+
+    fn set_default_configured_node_name(&self, media_class: &str, node_name: &str) -> bool {
+        if let Some(api) = self.default_nodes_api() {
+            return api.emit_by_name("set-default-configured-node-name", &[&media_class, &node_name]);
+        }
+
+        let (Some(key), Some(metadata)) = (default_metadata_key(media_class), self.metadata()) else {
+            return false;
+        };
+        let value = (!node_name.is_empty()).then(|| format!(r#"{{"name":"{}"}}"#, node_name.replace('\\', "\\\\").replace('"', "\\\"")));
+        metadata.set(0, Some(&format!("default.configured.{key}")), Some("Spa:String:JSON"), value.as_deref());
+        true
+    }
+
+    fn metadata_value(&self, subject: u32, key: &str) -> Option<String> {
+        self.metadata()?.find_notype(subject, key).map(|value| value.to_string())
+    }
+
+    fn set_metadata_value(&self, subject: u32, key: &str, type_: Option<&str>, value: Option<&str>) {
+        if let Some(metadata) = self.metadata() {
+            metadata.set(subject, Some(key), type_, value);
+        } else {
+            pwvucontrol_warning!("Cannot get metadata object");
+        }
+    }
+
+    fn has_settings(&self) -> bool {
+        self.settings_metadata().is_some()
+    }
+
+    fn setting(&self, key: &str) -> Option<String> {
+        let metadata = self.settings_metadata()?;
+        metadata.new_iterator(0)?.into_iter().find_map(|item| {
+            let (_, k, _, v) = wp::pw::Metadata::iterator_item_extract(&item);
+            (k == key).then(|| v.to_string())
+        })
+    }
+
+    fn set_setting(&self, key: &str, value: Option<&str>) {
+        if let Some(metadata) = self.settings_metadata() {
+            metadata.set(0, Some(key), None, value);
+        } else {
+            pwvucontrol_warning!("Cannot get settings metadata object");
+        }
+    }
+
+    fn node_profile(&self, id: u32) -> Option<NodeProfile> {
+        self.session()?.profiler()?.node_profile(id)
+    }
+
+    fn node_profiles(&self) -> Vec<NodeProfile> {
+        self.session().and_then(|session| session.profiler().map(PwProfiler::profiles)).unwrap_or_default()
+    }
+
+    fn profiler_available(&self) -> bool {
+        self.session().is_some_and(|session| session.profiler.get().is_some_and(|profiler| profiler.as_ref().is_some_and(PwProfiler::is_available)))
+    }
+
+    fn can_monitor(&self) -> bool {
+        true
+    }
+
+    fn can_create_streams(&self) -> bool {
+        true
+    }
+}
+
+struct WpNodeBackend {
+    node: wp::pw::Node,
+    /// `None` when the mixer-api is missing, volumes then go through the node's Props param.
+    mixer_api: Option<Plugin>,
+    mixer_handler: RefCell<Option<SignalHandlerId>>,
+}
+
+impl WpNodeBackend {
+    fn new(node: &wp::pw::Node, mixer_api: Option<Plugin>) -> Self {
+        Self { node: node.clone(), mixer_api, mixer_handler: Default::default() }
+    }
+
+    fn props_volume(&self) -> Option<NodeVolume> {
+        let props = self.props()?;
+        if props.channel_volumes.is_empty() {
+            return None;
+        }
+        Some(NodeVolume { mute: props.mute.unwrap_or_default(), channel_volumes: props.channel_volumes })
+    }
+
+    fn set_props_volume(&self, channel_volumes: Option<&[f32]>, mute: Option<bool>) -> bool {
+        let podbuilder = SpaPodBuilder::new_object("Spa:Pod:Object:Param:Props", "Props");
+
+        if let Some(channel_volumes) = channel_volumes {
+            let array = SpaPodBuilder::new_array();
+            for volume in channel_volumes {
+                array.add_float(*volume);
+            }
+            if let Some(arraypod) = array.end() {
+                podbuilder.add_property("channelVolumes");
+                podbuilder.add_pod(&arraypod);
+            }
+        }
+        if let Some(mute) = mute {
+            podbuilder.add_property("mute");
+            podbuilder.add_boolean(mute);
+        }
+
+        let Some(pod) = podbuilder.end() else {
+            pwvucontrol_warning!("Cannot set volume on {}", self.node.bound_id());
+            return false;
+        };
+        self.node.set_param("Props", 0, pod);
+        true
+    }
+}
+
+impl Drop for WpNodeBackend {
+    fn drop(&mut self) {
+        if let (Some(mixer_api), Some(handler)) = (&self.mixer_api, self.mixer_handler.take()) {
+            mixer_api.disconnect(handler);
+        }
+    }
+}
+
+impl NodeBackend for WpNodeBackend {
+    fn id(&self) -> u32 {
+        self.node.bound_id()
+    }
+
+    fn property(&self, key: &str) -> Option<String> {
+        self.node.pw_property(key).ok()
+    }
+
+    fn is_running(&self) -> bool {
+        self.node.state().0 == wp::pw::NodeState::Running
+    }
+
+    fn device_id(&self) -> Option<u32> {
+        self.node.device_id().ok().flatten()
+    }
+
+    fn card_device(&self) -> Option<i32> {
+        self.node.device_index().ok().flatten().map(|index| index as i32)
+    }
+
+    fn connect_changed(&self, handler: Rc<dyn Fn(NodeChange)>) {
+        for property in ["global-properties", "properties"] {
+            let handler = handler.clone();
+            self.node.connect_notify_local(Some(property), move |_, _| handler(NodeChange::Properties));
+        }
+
+        let params_handler = handler.clone();
+        let volume_in_props = self.mixer_api.is_none();
+        self.node.connect_params_changed(move |node, what| {
+            pwvucontrol_debug!("params-changed! {what} id: {}", node.bound_id());
+            match what {
+                "Props" => {
+                    params_handler(NodeChange::Props);
+                    if volume_in_props {
+                        params_handler(NodeChange::Volume);
+                    }
+                }
+                "Format" => params_handler(NodeChange::Format),
+                _ => {}
+            }
+        });
+
+        let state_handler = handler.clone();
+        self.node.connect_state_changed(move |_, _old, _new| state_handler(NodeChange::State));
+
+        let Some(mixer_api) = &self.mixer_api else {
+            return;
+        };
+        let id = self.node.bound_id();
+        let mixer_handler = mixer_api.connect_local("changed", true, move |values| {
+            if values.get(1).and_then(|value| value.get::<u32>().ok()) == Some(id) {
+                handler(NodeChange::Volume);
+            }
+            None
+        });
+        if let Some(previous) = self.mixer_handler.replace(Some(mixer_handler)) {
+            mixer_api.disconnect(previous);
+        }
+    }
+
+    fn volume(&self) -> Option<NodeVolume> {
+        let Some(mixer_api) = &self.mixer_api else {
+            return self.props_volume();
+        };
+        let result = mixer_api.emit_by_name::<Option<glib::Variant>>("get-volume", &[&self.node.bound_id()])?;
+        let Some(map) = result.get::<HashMap<String, glib::Variant>>() else {
+            pwvucontrol_critical!("Got invalid data via mixer-api for {}", self.node.bound_id());
+            return None;
+        };
+        let t_audiochannel = wp::spa::SpaIdTable::from_name("Spa:Enum:AudioChannel").expect("audio channel type");
+
+        let mut volume = NodeVolume::default();
+
+        let result: Option<HashMap<String, glib::Variant>> = map.get("channelVolumes").and_then(|x| x.get());
+        if let Some(channel_volumes) = result {
+            let mut newvec = vec![0f32; channel_volumes.len()];
+            for (index_str, v) in channel_volumes.iter() {
+                let index = index_str.parse::<usize>().ok().filter(|index| *index < newvec.len());
+                let map: HashMap<String, glib::Variant> = v.get().unwrap_or_default();
+                let volume: Option<f64> = map.get("volume").and_then(|x| x.get());
+                let channelname: String = map.get("channel").and_then(|x| x.get()).unwrap_or_default();
+                let channel = t_audiochannel.find_value_from_short_name(&channelname);
+
+                if let (Some(index), Some(c), Some(v)) = (index, channel, volume) {
+                    pwvucontrol_debug!("Index: {index}, Number: {} = {}", c.number(), v);
+                    newvec[index] = v as f32;
+                } else {
+                    pwvucontrol_critical!("Got invalid data via mixer-api");
+                }
+            }
+            volume.channel_volumes = newvec;
+        } else {
+            pwvucontrol_critical!("Cannot get channel volumes via mixer-api");
+        }
+
+        volume.mute = map.get("mute").and_then(|x| x.get()).unwrap_or_default();
+        Some(volume)
+    }
+
+    fn set_volume(&self, channel_volumes: Option<&[f32]>, positions: &[u32], mute: Option<bool>) -> bool {
+        let Some(mixer_api) = &self.mixer_api else {
+            return self.set_props_volume(channel_volumes, mute);
+        };
+        let bound_id = self.node.bound_id();
+
+        let variant = glib::VariantDict::new(None);
+        if let Some(mute) = mute {
+            variant.insert("mute", mute);
+        }
+        if let Some(cv) = channel_volumes.and_then(|channel_volumes| channel_volumes_variant(channel_volumes, positions)) {
+            variant.insert("channelVolumes", cv);
+        }
+
+        let result = mixer_api.emit_by_name::<bool>("set-volume", &[&bound_id, &variant.to_variant()]);
+        if !result {
+            pwvucontrol_warning!("Cannot set volume on {bound_id}");
+        }
+        result
+    }
+
+    fn props(&self) -> Option<NodeProps> {
+        let Some(params) = self.node.enum_params_sync("Props", None) else {
+            pwvucontrol_warning!("No Props on node {}", self.node.bound_id());
+            return None;
+        };
+
+        let mut props = NodeProps::default();
+        for pod in params.filter_map(|param| param.get::<wp::spa::SpaPod>().ok()) {
+            parsed(parse_props(&pod, &mut props), "Props", self.node.bound_id());
+        }
+        Some(props)
+    }
+
+    fn set_main_volume(&self, volume: f32) {
+        let podbuilder = SpaPodBuilder::new_object("Spa:Pod:Object:Param:Props", "Props");
+
+        podbuilder.add_property("volume");
+        podbuilder.add_float(volume);
+
+        if let Some(pod) = podbuilder.end() {
+            self.node.set_param("Props", 0, pod);
+        }
+    }
+
+    fn set_monitor_volumes(&self, volumes: &[f32]) {
+        let podbuilder = SpaPodBuilder::new_object("Spa:Pod:Object:Param:Props", "Props");
+
+        let array = SpaPodBuilder::new_array();
+        for volume in volumes {
+            array.add_float(*volume);
+        }
+        if let Some(arraypod) = array.end() {
+            podbuilder.add_property("monitorVolumes");
+            podbuilder.add_pod(&arraypod);
+        }
+
+        if let Some(pod) = podbuilder.end() {
+            self.node.set_param("Props", 0, pod);
+        }
+    }
+
+    fn set_props_params(&self, params: &[(&str, PropsParamValue)]) {
+        let podbuilder = SpaPodBuilder::new_object("Spa:Pod:Object:Param:Props", "Props");
+
+        let structbuilder = SpaPodBuilder::new_struct();
+        for (key, value) in params {
+            structbuilder.add_string(key);
+            match value {
+                PropsParamValue::Int(value) => structbuilder.add_int(*value),
+                PropsParamValue::Float(value) => structbuilder.add_float(*value),
+                PropsParamValue::Bool(value) => structbuilder.add_boolean(*value),
+                PropsParamValue::String(value) => structbuilder.add_string(value),
+            }
+        }
+        if let Some(structpod) = structbuilder.end() {
+            podbuilder.add_property("params");
+            podbuilder.add_pod(&structpod);
+        }
+
+        if let Some(pod) = podbuilder.end() {
+            self.node.set_param("Props", 0, pod);
+        }
+    }
+
+    fn format(&self, callback: Box<dyn FnOnce(Option<AudioFormat>)>) {
+        let node = &self.node;
+
+        node.enum_params(Some("Format"), None, gtk::gio::Cancellable::NONE, clone!(@weak node => move |res| {
+            let Ok(Some(iter)) = res else {
+                pwvucontrol_debug!("enum_params async call didn't return anything useful");
+                return;
+            };
+
+            for pod in iter.filter_map(|param| param.get::<wp::spa::SpaPod>().ok()) {
+                if let Some(format) = parsed(parse_format(&pod), "Format", node.bound_id()) {
+                    pwvucontrol_info!(
+                        "For {} bound id {}, Got rate {}, format {}, channels {}",
+                        node.name().unwrap_or_default(),
+                        node.bound_id(),
+                        format.rate,
+                        format.format,
+                        format.channels
+                    );
+                    callback(Some(format));
+                    return;
+                }
+            }
+            callback(None);
+        }));
+    }
+
+    fn supported_formats(&self) -> SupportedFormats {
+        let mut supported = SupportedFormats::default();
+        let Some(params) = self.node.enum_params_sync("EnumFormat", None) else {
+            return supported;
+        };
+
+        let t_format = wp::spa::SpaIdTable::from_name("Spa:Enum:AudioFormat").expect("audio format type");
+
+        for param in params {
+            let Ok(pod) = param.get::<wp::spa::SpaPod>() else {
+                continue;
+            };
+            if !pod.is_object() {
+                continue;
+            }
+
+            if let Some(rate) = pod.find_spa_property(&wp::spa::ffi::SPA_FORMAT_AUDIO_rate) {
+                supported.rates.extend(choice_values(&rate).into_iter().map(|rate| rate as u32));
+            }
+
+            if let Some(format) = pod.find_spa_property(&wp::spa::ffi::SPA_FORMAT_AUDIO_format) {
+                let names = choice_values(&format).into_iter().filter_map(|id| {
+                    t_format.values().into_iter().find(|x| x.number() == id as u32).and_then(|x| x.short_name()).map(|name| name.to_string())
+                });
+                supported.formats.extend(names);
+            }
+        }
+
+        supported.rates.sort_unstable();
+        supported.rates.dedup();
+        supported.formats.sort();
+        supported.formats.dedup();
+        supported
+    }
+
+    fn set_port_config(&self, direction: RouteDirection, rate: u32, positions: &[u32]) {
+        let positionbuilder = SpaPodBuilder::new_array();
+        for position in positions {
+            positionbuilder.add_id(*position);
+        }
+
+        let formatbuilder = SpaPodBuilder::new_object("Spa:Pod:Object:Param:Format", "Format");
+        formatbuilder.add_property("mediaType");
+        formatbuilder.add_id(SPA_MEDIA_TYPE_AUDIO);
+        formatbuilder.add_property("mediaSubtype");
+        formatbuilder.add_id(SPA_MEDIA_SUBTYPE_RAW);
+        formatbuilder.add_property("format");
+        formatbuilder.add_id(SPA_AUDIO_FORMAT_F32P);
+        formatbuilder.add_property("rate");
+        formatbuilder.add_int(rate as i32);
+        formatbuilder.add_property("channels");
+        formatbuilder.add_int(positions.len() as i32);
+        if let Some(positionpod) = positionbuilder.end() {
+            formatbuilder.add_property("position");
+            formatbuilder.add_pod(&positionpod);
+        }
+
+        let podbuilder = SpaPodBuilder::new_object("Spa:Pod:Object:Param:PortConfig", "PortConfig");
+        podbuilder.add_property("direction");
+        podbuilder.add_id(direction.into());
+        podbuilder.add_property("mode");
+        podbuilder.add_id(SPA_PARAM_PORT_CONFIG_MODE_DSP);
+        podbuilder.add_property("monitor");
+        podbuilder.add_boolean(direction == RouteDirection::Input);
+        if let Some(formatpod) = formatbuilder.end() {
+            podbuilder.add_property("format");
+            podbuilder.add_pod(&formatpod);
+        }
+
+        if let Some(pod) = podbuilder.end() {
+            self.node.set_param("PortConfig", 0, pod);
+        }
+    }
+
+    fn suspend(&self) {
+        self.node.send_command("Suspend");
+    }
+
+    fn dump(&self) -> Vec<DumpSection> {
+        dump_object(&self.node, &["Props", "Format", "EnumFormat"])
+    }
+}
+
+struct WpDeviceBackend {
+    device: wp::pw::Device,
+    battery: RefCell<Option<Rc<BluezBattery>>>,
+}
+
+impl DeviceBackend for WpDeviceBackend {
+    fn id(&self) -> u32 {
+        self.device.bound_id()
+    }
+
+    fn property(&self, key: &str) -> Option<String> {
+        self.device.pw_property(key).ok()
+    }
+
+    fn connect_changed(&self, handler: Rc<dyn Fn(DeviceChange)>) {
+        // BlueZ keeps the battery level, PipeWire only tells which Bluetooth device this is.
+        if let Some(address) = self.property("api.bluez5.address") {
+            let battery_handler = handler.clone();
+            self.battery.replace(Some(BluezBattery::watch(&address, move || battery_handler(DeviceChange::Battery))));
+        }
+
+        let properties_handler = handler.clone();
+        self.device.connect_properties_notify(move |device| {
+            pwvucontrol_debug!("properties changed! id: {}", device.bound_id());
+            properties_handler(DeviceChange::Properties);
+        });
+
+        self.device.connect_params_changed(move |device, what| {
+            pwvucontrol_debug!("params-changed! {what} id: {}", device.bound_id());
+
+            match what {
+                "EnumProfile" => handler(DeviceChange::Profiles),
+                "Profile" => handler(DeviceChange::Profile),
+                "EnumRoute" => handler(DeviceChange::Routes),
+                "Route" => handler(DeviceChange::Route),
+                _ => {}
+            }
+        });
+    }
+
+    fn profiles(&self, callback: Box<dyn FnOnce(Vec<ProfileInfo>)>) {
+        let id = self.device.bound_id();
+        self.device.enum_params(Some("EnumProfile"), None, gtk::gio::Cancellable::NONE, move |res| match res {
+            Ok(Some(iter)) => {
+                let profiles = iter
+                    .filter_map(|param| param.get::<wp::spa::SpaPod>().ok())
+                    .filter_map(|pod| parsed(parse_profile(&pod), "profile", id))
+                    .collect();
+                callback(profiles);
+            }
+            Ok(None) => {}
+            Err(e) => pwvucontrol_warning!("Cannot get profiles of device {id}: {e}"),
+        });
+    }
+
+    fn current_profile(&self) -> Option<ProfileInfo> {
+        let params = self.device.enum_params_sync("Profile", None)?;
+        params
+            .filter_map(|param| param.get::<wp::spa::SpaPod>().ok())
+            .find_map(|pod| parsed(parse_profile(&pod), "current profile", self.device.bound_id()))
+    }
+
+    fn set_profile(&self, index: i32) {
+        let podbuilder = SpaPodBuilder::new_object("Spa:Pod:Object:Param:Profile", "Profile");
+
+        podbuilder.add_property("index");
+        podbuilder.add_int(index);
+
+        if let Some(pod) = podbuilder.end() {
+            self.device.set_param("Profile", 0, pod);
+        }
+    }
+
+    fn routes(&self, callback: Box<dyn FnOnce(Vec<RouteInfo>)>) {
+        let id = self.device.bound_id();
+        self.device.enum_params(Some("EnumRoute"), None, gtk::gio::Cancellable::NONE, move |res| match res {
+            Ok(Some(iter)) => {
+                let routes = iter
+                    .filter_map(|param| param.get::<wp::spa::SpaPod>().ok())
+                    .filter_map(|pod| parsed(parse_route(&pod), "route", id))
+                    .collect();
+                callback(routes);
+            }
+            Ok(None) => {}
+            Err(e) => pwvucontrol_warning!("Cannot get routes of device {id}: {e}"),
+        });
+    }
+
+    fn route_availability(&self) -> Vec<(i32, ParamAvailability)> {
+        let Some(params) = self.device.enum_params_sync("EnumRoute", None) else {
+            return Vec::new();
+        };
+
+        params
+            .filter_map(|param| param.get::<wp::spa::SpaPod>().ok())
+            .filter(|pod| pod.is_object())
+            .filter_map(|pod| {
+                let index = pod.find_spa_property(&wp::spa::ffi::SPA_PARAM_ROUTE_index)?.int()?;
+                let available = ParamAvailability::from(&pod.find_spa_property(&wp::spa::ffi::SPA_PARAM_ROUTE_available)?);
+                Some((index, available))
+            })
+            .collect()
+    }
+
+    fn active_routes(&self) -> Vec<ActiveRoute> {
+        let Some(params) = self.device.enum_params_sync("Route", None) else {
+            return Vec::new();
+        };
+
+        params
+            .filter_map(|param| param.get::<wp::spa::SpaPod>().ok())
+            .filter_map(|pod| parsed(parse_active_route(&pod), "active route", self.device.bound_id()))
+            .collect()
+    }
+
+    fn set_route(&self, index: i32, device: i32, props: &RouteProps) {
+        let podbuilder = SpaPodBuilder::new_object("Spa:Pod:Object:Param:Route", "Route");
+        podbuilder.add_property("index");
+        podbuilder.add_int(index);
+        podbuilder.add_property("device");
+        podbuilder.add_int(device);
+
+        if *props != RouteProps::default() {
+            let propsbuilder = SpaPodBuilder::new_object("Spa:Pod:Object:Param:Props", "Route");
+            if let Some(channel_volumes) = &props.channel_volumes {
+                let array = SpaPodBuilder::new_array();
+                for volume in channel_volumes {
+                    array.add_float(*volume);
+                }
+                if let Some(arraypod) = array.end() {
+                    propsbuilder.add_property("channelVolumes");
+                    propsbuilder.add_pod(&arraypod);
+                }
+            }
+            if let Some(mute) = props.mute {
+                propsbuilder.add_property("mute");
+                propsbuilder.add_boolean(mute);
+            }
+            if let Some(propspod) = propsbuilder.end() {
+                podbuilder.add_property("props");
+                podbuilder.add_pod(&propspod);
+            }
+        }
+
+        podbuilder.add_property("save");
+        podbuilder.add_boolean(true);
+
+        if let Some(pod) = podbuilder.end() {
+            self.device.set_param("Route", 0, pod);
+        }
+    }
+
+    fn dump(&self) -> Vec<DumpSection> {
+        dump_object(&self.device, &["Profile", "Route", "EnumProfile", "EnumRoute"])
+    }
+
+    fn battery_level(&self) -> Option<u32> {
+        self.battery.borrow().as_ref().and_then(|battery| battery.level())
+    }
+}
+
+/// The mixer-api `channelVolumes` dict for `channel_volumes` on channels at `positions`.
+fn channel_volumes_variant(channel_volumes: &[f32], positions: &[u32]) -> Option<glib::Variant> {
+    let t_audiochannel = wp::spa::SpaIdTable::from_name("Spa:Enum:AudioChannel").expect("audio channel type");
+
+    let mut channel_volumes_map: HashMap<String, glib::Variant> = HashMap::with_capacity(channel_volumes.len());
+    for (i, v) in channel_volumes.iter().enumerate() {
+        let mut map: HashMap<String, glib::Variant> = HashMap::with_capacity(2);
+        let channel_name = t_audiochannel.find_value(*positions.get(i)?)?.short_name();
+        map.insert("channel".to_string(), channel_name.to_variant());
+        map.insert("volume".to_string(), (*v as f64).to_variant());
+        channel_volumes_map.insert(i.to_string(), map.to_variant());
+    }
+
+    Some(channel_volumes_map.to_variant())
+}
+
+/// The `default` metadata key suffix for `media_class`, like `audio.sink`.
+fn default_metadata_key(media_class: &str) -> Option<&'static str> {
+    match media_class {
+        "Audio/Sink" => Some("audio.sink"),
+        "Audio/Source" | "Audio/Source/Virtual" => Some("audio.source"),
+        _ => None,
+    }
+}
+
+/// The node name in a `{"name":"..."}` value of the `default` metadata.
+fn json_name(value: &str) -> Option<String> {
+    json_object_string(value, "name")
+}
+
+/// The parsed param, or `None` after logging why `what` of object `id` is skipped.
+fn parsed<T>(result: ParamResult<T>, what: &str, id: u32) -> Option<T> {
+    match result {
+        Ok(value) => Some(value),
+        Err(e) => {
+            pwvucontrol_warning!("Ignoring {what} of {id}: {e}");
+            None
+        }
+    }
+}
+
+/// Values of a pod that is a plain int or id, or a choice of them.
+fn choice_values(pod: &wp::spa::SpaPod) -> Vec<i32> {
+    if !pod.is_choice() {
+        return pod.int().or_else(|| pod.id().map(|id| id as i32)).into_iter().collect();
+    }
+
+    let values: Vec<i32> = pod.array_iterator::<i32>().collect();
+    match pod.choice_type().and_then(|t| t.short_name()).as_deref() {
+        // The first value is the default, followed by the minimum and maximum.
+        Some("Range") | Some("Step") if values.len() >= 3 => {
+            let (min, max) = (values[1] as u32, values[2] as u32);
+            COMMON_RATES.iter().filter(|rate| (min..=max).contains(*rate)).map(|rate| *rate as i32).collect()
+        }
+        // The first value is the default, usually repeated among the alternatives, callers dedup.
+        Some("Enum") => values,
+        _ => values.into_iter().take(1).collect(),
+    }
+}
+
+trait MetadataExtFix: 'static {
+    fn find_notype(&self, subject: u32, key: &str) -> Option<glib::GString>;
+}
+
+impl<O: IsA<wp::pw::Metadata>> MetadataExtFix for O {
+    fn find_notype(&self, subject: u32, key: &str) -> Option<glib::GString> {
+        use glib::translate::ToGlibPtr;
+        unsafe {
+            let mut type_ = std::ptr::null();
+            glib::translate::from_glib_none(wp::ffi::wp_metadata_find(
+                self.as_ref().to_glib_none().0,
+                subject,
+                ToGlibPtr::to_glib_none(&key).0,
+                &mut type_,
+            ))
+        }
+    }
+}
@@ -1,20 +1,20 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use crate::macros::*;
-use crate::{backend::NodeType, backend::PwDeviceObject, backend::PwNodeFilterModel, backend::PwNodeObject, PwvucontrolApplication};
+use crate::{
+    backend::AudioBackend, backend::DeviceBackend, backend::NodeBackend, backend::NodeProfile, backend::NodeRule, backend::NodeType,
+    backend::PwDeviceObject, backend::PwNodeFilterModel, backend::PwNodeGroupObject, backend::PwNodeObject, config::APP_ID, PwvucontrolApplication,
+};
 use gtk::{
     gio,
-    glib::{self, clone, Properties},
+    glib::{self, clone, subclass::Signal, Properties},
     prelude::*,
     subclass::prelude::*,
 };
-use std::cell::{OnceCell, RefCell};
-use wireplumber as wp;
-use wp::{
-    plugin::{PluginFeatures, *},
-    pw::{MetadataExt, PipewireObjectExt2, ProxyExt},
-    registry::{Constraint, ConstraintType, Interest, ObjectManager},
-};
+use std::cell::{Cell, OnceCell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::OnceLock;
 
 mod imp {
     use super::*;
@@ -22,10 +22,15 @@ mod imp {
     #[derive(Properties)]
     #[properties(wrapper_type = super::PwvucontrolManager)]
     pub struct PwvucontrolManager {
-        #[property(get)]
-        pub wp_core: OnceCell<wp::core::Core>,
+        pub(crate) backend: OnceCell<Rc<dyn AudioBackend>>,
 
-        pub wp_object_manager: OnceCell<wp::registry::ObjectManager>,
+        /// Whether the backend is connected to the sound server.
+        #[property(get, set)]
+        connected: Cell<bool>,
+
+        /// Whether the backend gets by without some of the session manager's APIs, see [`crate::backend::WpBackend`].
+        #[property(get, set)]
+        degraded: Cell<bool>,
 
         #[property(get)]
         pub(crate) node_model: gio::ListStore,
@@ -45,36 +50,39 @@ mod imp {
         #[property(get)]
         pub(crate) device_model: gio::ListStore,
 
-        pub metadata_om: OnceCell<wp::registry::ObjectManager>,
-        #[property(get)]
-        pub metadata: RefCell<Option<wp::pw::Metadata>>,
-
-        #[property(get)]
-        pub default_nodes_api: OnceCell<Plugin>,
+        /// One [`PwNodeGroupObject`] per application that has had a stream this session.
         #[property(get)]
-        pub mixer_api: OnceCell<Plugin>,
+        pub(crate) application_model: gio::ListStore,
 
         #[property(get, set, construct_only)]
         application: RefCell<Option<PwvucontrolApplication>>,
+
+        pub(crate) settings: gio::Settings,
+        pub(crate) hide_rules: RefCell<Vec<NodeRule>>,
+        /// Rules picking the default sink and source, highest priority first.
+        pub(crate) device_priority: RefCell<Vec<NodeRule>>,
+        pub(crate) device_priority_pending: Cell<bool>,
     }
 
     impl Default for PwvucontrolManager {
         fn default() -> Self {
             let node_model = gio::ListStore::new::<PwNodeObject>();
             Self {
-                wp_core: Default::default(),
-                wp_object_manager: Default::default(),
+                backend: Default::default(),
+                connected: Default::default(),
+                degraded: Default::default(),
                 node_model: node_model.clone(),
                 stream_input_model: PwNodeFilterModel::new(NodeType::StreamInput, Some(node_model.clone())),
                 stream_output_model: PwNodeFilterModel::new(NodeType::StreamOutput, Some(node_model.clone())),
                 source_model: PwNodeFilterModel::new(NodeType::Source, Some(node_model.clone())),
                 sink_model: PwNodeFilterModel::new(NodeType::Sink, Some(node_model.clone())),
                 device_model: gio::ListStore::new::<PwDeviceObject>(),
-                metadata_om: Default::default(),
-                metadata: Default::default(),
-                default_nodes_api: Default::default(),
-                mixer_api: Default::default(),
+                application_model: gio::ListStore::new::<PwNodeGroupObject>(),
                 application: Default::default(),
+                settings: gio::Settings::new(APP_ID),
+                hide_rules: Default::default(),
+                device_priority: Default::default(),
+                device_priority_pending: Default::default(),
             }
         }
     }
@@ -90,208 +98,143 @@ mod imp {
         fn constructed(&self) {
             self.parent_constructed();
 
-            self.setup_wp_connection();
-            self.setup_metadata_om();
+            self.setup_hide_rules();
+            self.setup_device_priority();
+        }
+
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: OnceLock<Vec<Signal>> = OnceLock::new();
+            SIGNALS.get_or_init(|| {
+                vec![
+                    Signal::builder("default-nodes-changed").build(),
+                    Signal::builder("metadata-changed").param_types([u32::static_type(), String::static_type()]).build(),
+                    Signal::builder("settings-changed").build(),
+                ]
+            })
         }
     }
 
     impl PwvucontrolManager {
-        fn setup_wp_connection(&self) {
-            wp::core::Core::init_with_flags(wp::InitFlags::ALL);
+        fn setup_hide_rules(&self) {
+            self.hide_rules.replace(NodeRule::load(&self.settings, "hide-rules"));
+
+            self.settings.connect_changed(
+                Some("hide-rules"),
+                clone!(@weak self as manager => move |settings, key| {
+                    manager.hide_rules.replace(NodeRule::load(settings, key));
+                    manager.obj().reapply_hide_rules();
+                }),
+            );
 
-            if !wp::Log::level_is_enabled(glib::LogLevelFlags::LEVEL_WARNING) {
-                wp::Log::set_default_level("1");
+            for model in [&self.stream_output_model, &self.stream_input_model, &self.source_model, &self.sink_model] {
+                self.settings.bind("show-hidden-streams", model, "show-hidden").get_only().build();
             }
+        }
 
-            let props = wp::pw::Properties::new_string("media.category=Manager");
-
-            let wp_core = wp::core::Core::new(Some(&glib::MainContext::default()), Some(props));
-            let wp_om = ObjectManager::new();
-
-            wp_core.connect();
-
-            wp_core.load_component("libwireplumber-module-mixer-api", "module", None).expect("loadig mixer-api plugin");
-            wp_core.load_component("libwireplumber-module-default-nodes-api", "module", None).expect("loadig mixer-api plugin");
-
-            wp_om.add_interest({
-                let interest: Interest<wp::pw::Node> = wp::registry::Interest::new();
-                let variant = glib::Variant::tuple_from_iter(
-                    ["Stream/Output/Audio", "Stream/Input/Audio", "Audio/Source", "Audio/Source/Virtual", "Audio/Sink"].map(ToVariant::to_variant),
-                );
-
-                interest.add_constraint(
-                    wp::registry::ConstraintType::PwGlobalProperty,
-                    "media.class",
-                    wp::registry::ConstraintVerb::InList,
-                    Some(&variant),
-                );
-
-                interest
-            });
-
-            wp_om.add_interest({
-                let interest: Interest<wp::pw::Device> = wp::registry::Interest::new();
-                interest.add_constraint(
-                    wp::registry::ConstraintType::PwGlobalProperty,
-                    "media.class",
-                    wp::registry::ConstraintVerb::Equals,
-                    Some(&"Audio/Device".to_variant()),
-                );
+        fn setup_device_priority(&self) {
+            self.device_priority.replace(NodeRule::load(&self.settings, "default-device-priority"));
 
-                interest
-            });
+            self.settings.connect_changed(
+                Some("default-device-priority"),
+                clone!(@weak self as manager => move |settings, key| {
+                    manager.device_priority.replace(NodeRule::load(settings, key));
+                    manager.obj().queue_apply_device_priority();
+                }),
+            );
+            self.settings.connect_changed(
+                Some("auto-switch-default-device"),
+                clone!(@weak self as manager => move |_, _| manager.obj().queue_apply_device_priority()),
+            );
+        }
+    }
+}
 
-            wp_om.request_object_features(wp::pw::Node::static_type(), wp::core::ObjectFeatures::ALL);
+glib::wrapper! {
+    pub struct PwvucontrolManager(ObjectSubclass<imp::PwvucontrolManager>);
+}
 
-            wp_om.request_object_features(wp::pw::GlobalProxy::static_type(), wp::core::ObjectFeatures::ALL);
+impl PwvucontrolManager {
+    pub fn new() -> Self {
+        glib::Object::builder().build()
+    }
 
-            wp_om.connect_object_added(clone!(@weak self as imp, @weak wp_core as core => move |_, object| {
-                if let Some(node) = object.downcast_ref::<wp::pw::Node>() {
-                    let mut hidden: bool = false;
-                    // Hide ourselves.
-                    if node.name().unwrap_or_default() == "PulseAudio Volume Control" {
-                        hidden = true;
-                    }
+    /// Connects to the sound server through `backend`, which then fills the models.
+    pub fn start(&self, backend: Rc<dyn AudioBackend>) {
+        if self.imp().backend.set(backend.clone()).is_err() {
+            pwvucontrol_critical!("Manager started twice");
+            return;
+        }
+        backend.start(self);
+    }
 
-                    // Hide any playback from pavucontrol (mainly volume control notification sound).
-                    if node.name().unwrap_or_default() == "pavucontrol" {
-                        hidden = true;
-                    }
+    pub(crate) fn backend(&self) -> &dyn AudioBackend {
+        self.imp().backend.get().expect("Manager started with a backend").as_ref()
+    }
 
-                    // Hide any notification sounds.
-                    // The presence of the event.id property means most likely this is an event sound.
-                    if node.pw_property::<String>("event.id").is_ok() {
-                        hidden = true;
-                    }
-                    // Or media.role being Notification.
-                    if node.pw_property::<String>("media.role").unwrap_or_default() == "Notification" {
-                        hidden = true;
-                    }
+    pub(crate) fn reconnect(&self) {
+        self.backend().reconnect();
+    }
 
-                    // Hide applications that only record for peak meter.
-                    if node.pw_property::<String>("stream.monitor").is_ok() {
-                        hidden = true;
-                    }
+    /// Called by the backend for every new sink, source or stream.
+    pub(crate) fn add_node(&self, backend: Rc<dyn NodeBackend>) {
+        let imp = self.imp();
 
-                    pwvucontrol_info!("Got node: {} bound id {}", node.name().unwrap_or_default(), node.bound_id());
-                    let pwobj = PwNodeObject::new(node);
-                    pwobj.set_hidden(hidden);
-                    imp.node_model.append(&pwobj);
-                } else if let Some(device) = object.downcast_ref::<wp::pw::Device>() {
-                    pwvucontrol_info!("Got device: {} bound id {}", device.pw_property::<String>("device.name").unwrap_or_default(), device.bound_id());
-                    imp.device_model.append(&PwDeviceObject::new(device));
-                } else {
-                    unreachable!("Object must be one of the above, but is {:?} instead", object.type_());
-                }
-            }));
-
-            wp_om.connect_object_removed(clone!(@weak self as imp => move |_, object| {
-                if let Some(node) = object.downcast_ref::<wp::pw::Node>() {
-                    pwvucontrol_info!("removed: {} id: {}", node.name().unwrap_or_default(), node.bound_id());
-                    imp.obj().remove_node_by_id(node.bound_id());
-                } else if let Some(device) = object.downcast_ref::<wp::pw::Device>() {
-                    imp.obj().remove_device_by_id(device.bound_id());
-                } else {
-                    pwvucontrol_info!("Object must be one of the above, but is {:?} instead", object.type_());
-                }
-            }));
-
-            glib::MainContext::default().spawn_local(clone!(@weak self as manager, @weak wp_core as core, @weak wp_om as om => async move {
-                let plugin_names = vec![("mixer-api", &manager.mixer_api), ("default-nodes-api", &manager.default_nodes_api)];
-
-                let mut count = 0;
-                for (plugin_name, plugin_cell) in plugin_names.iter() {
-                    if let Some(plugin) = Plugin::find(&core, plugin_name) {
-                        let result = plugin.activate_future(PluginFeatures::ENABLED).await;
-                        if result.is_err() {
-                            pwvucontrol_critical!("Cannot activate plugin {plugin_name}");
-                        } else {
-                            plugin_cell.set(plugin).expect("Plugin not set");
-                            pwvucontrol_info!("Activated plugin {plugin_name}");
-                            count += 1;
-                            if count == plugin_names.len() {
-                                core.install_object_manager(&om);
-                            }
-                        }
-                    } else {
-                        pwvucontrol_critical!("Cannot find plugin {plugin_name}");
-                        PwvucontrolApplication::default().quit();
-                    }
-                }
-            }));
+        // The default rules hide ourselves, pavucontrol, notification sounds and peak meter streams.
+        let hidden = NodeRule::any_matches(&imp.hide_rules.borrow(), |key| backend.property(key));
 
-            self.wp_core.set(wp_core).expect("wp_core should only be set once during application activation");
-            self.wp_object_manager.set(wp_om).expect("wp_object_manager should only be set once during application activation");
+        let device_id = backend.device_id();
+        let pwobj = PwNodeObject::new(backend);
+        pwobj.set_hidden(hidden);
+        if let Some(device) = device_id.and_then(|id| self.get_device_by_id(id)) {
+            pwobj.set_device(Some(&device));
         }
+        self.add_to_application(&pwobj);
+        imp.node_model.append(&pwobj);
+        if self.matches_device_priority(&pwobj) {
+            self.queue_apply_device_priority();
+        }
+    }
 
-        fn setup_metadata_om(&self) {
-            let metadata_om = ObjectManager::new();
-
-            let wp_core = self.wp_core.get().expect("wp_core to be set");
-
-            metadata_om.add_interest(
-                [Constraint::compare(ConstraintType::PwGlobalProperty, "metadata.name", "default", true)]
-                    .iter()
-                    .collect::<Interest<wp::pw::Metadata>>(),
-            );
-
-            metadata_om.request_object_features(wp::pw::GlobalProxy::static_type(), wp::core::ObjectFeatures::ALL);
+    /// Called by the backend for every new device.
+    pub(crate) fn add_device(&self, backend: Rc<dyn DeviceBackend>) {
+        let imp = self.imp();
+        let device = PwDeviceObject::new(backend);
+        imp.device_model.append(&device);
 
-            metadata_om.connect_object_added(
-                clone!(@weak self as manager, @weak wp_core as core => move |_, object| manager.metadata_object_added(object)),
-            );
-
-            wp_core.install_object_manager(&metadata_om);
-            self.metadata_om.set(metadata_om).expect("metadata object manager set already");
-        }
-
-        fn metadata_changed(&self, _subject: u32, key: Option<&str>, type_: Option<&str>, value: Option<&str>) {
-            if let (Some(key), Some(json_str), Some("Spa:String:JSON")) = (key, value, type_) {
-                if let Some(node_name) = json_str.split(r#"{"name":""#).nth(1).and_then(|x| x.split('"').next()) {
-                    match key {
-                        "default.audio.sink" => {
-                            pwvucontrol_info!("New default sink: {node_name}")
-                        }
-                        "default.audio.source" => {
-                            pwvucontrol_info!("New default source: {node_name}")
-                        }
-                        _ => {}
-                    }
-                }
+        // Devices can show up after their nodes.
+        for node in imp.node_model.iter::<PwNodeObject>().map_while(Result::ok) {
+            if node.device().is_none() && node.backend().device_id() == Some(device.id()) {
+                node.set_device(Some(&device));
             }
         }
+    }
 
-        fn metadata_object_added(&self, object: &glib::Object) {
-            if let Some(metadataobj) = object.dynamic_cast_ref::<wp::pw::Metadata>() {
-                self.metadata.replace(Some(metadataobj.clone()));
-
-                for a in metadataobj.new_iterator(u32::MAX).expect("iterator") {
-                    let (s, k, t, v) = wp::pw::Metadata::iterator_item_extract(&a);
-                    self.metadata_changed(s, Some(&k), Some(&t), Some(&v));
-                }
-
-                metadataobj.connect_changed(clone!(@weak self as manager => move |_,s,k,t,v| manager.metadata_changed(s, k, t, v)));
-            } else {
-                unreachable!("Object must be one of the above, but is {:?} instead", object.type_());
-            }
+    /// Drops every node and device, for when the connection to the sound server is lost.
+    pub(crate) fn clear(&self) {
+        let ids: Vec<u32> = self.imp().node_model.iter::<PwNodeObject>().map_while(Result::ok).map(|node| node.boundid()).collect();
+        for id in ids {
+            self.remove_node_by_id(id);
         }
+        self.imp().device_model.remove_all();
     }
-}
 
-glib::wrapper! {
-    pub struct PwvucontrolManager(ObjectSubclass<imp::PwvucontrolManager>);
-}
+    pub(crate) fn default_nodes_changed(&self) {
+        self.emit_by_name::<()>("default-nodes-changed", &[]);
+    }
 
-impl PwvucontrolManager {
-    pub fn new() -> Self {
-        glib::Object::builder().build()
+    pub(crate) fn metadata_changed(&self, subject: u32, key: &str) {
+        self.emit_by_name::<()>("metadata-changed", &[&subject, &key]);
+    }
+
+    pub(crate) fn settings_changed(&self) {
+        self.emit_by_name::<()>("settings-changed", &[]);
     }
 
     pub fn get_device_by_id(&self, id: u32) -> Option<PwDeviceObject> {
         let devicemodel = &self.imp().device_model;
         for device in devicemodel.iter::<PwDeviceObject>() {
             if let Ok(device) = device {
-                if device.wpdevice().bound_id() == id {
+                if device.id() == id {
                     return Some(device);
                 }
             } else {
@@ -306,7 +249,7 @@ impl PwvucontrolManager {
 
         for (i, item) in (0..).zip(devicemodel.iter::<PwDeviceObject>()) {
             if let Ok(item) = item {
-                if item.wpdevice().bound_id() == id {
+                if item.id() == id {
                     devicemodel.remove(i);
                     break;
                 }
@@ -320,7 +263,7 @@ impl PwvucontrolManager {
         let nodemodel = &self.imp().node_model;
         for node in nodemodel.iter::<PwNodeObject>() {
             if let Ok(node) = node {
-                if node.wpnode().bound_id() == id {
+                if node.boundid() == id {
                     return Some(node);
                 }
             } else {
@@ -335,8 +278,14 @@ impl PwvucontrolManager {
 
         for (i, item) in (0..).zip(nodemodel.iter::<PwNodeObject>()) {
             if let Ok(item) = item {
-                if item.wpnode().bound_id() == id {
+                if item.boundid() == id {
+                    if let Some(application) = self.get_application_for_node(&item) {
+                        application.remove_member(&item);
+                    }
                     nodemodel.remove(i);
+                    if self.matches_device_priority(&item) || self.is_configured_default(&item) {
+                        self.queue_apply_device_priority();
+                    }
                     break;
                 }
             } else {
@@ -345,6 +294,263 @@ impl PwvucontrolManager {
         }
     }
 
+    /// The application group of a stream, one per application and direction.
+    pub(crate) fn get_application_for_node(&self, node: &PwNodeObject) -> Option<PwNodeGroupObject> {
+        let key = PwNodeGroupObject::application_key(node);
+        self.imp()
+            .application_model
+            .iter::<PwNodeGroupObject>()
+            .map_while(Result::ok)
+            .find(|application| application.key() == key && application.nodetype() == node.nodetype())
+    }
+
+    /// Adds a stream to its application, applying the application volume if one was set.
+    ///
+    /// Applications are kept after their last stream goes away so short-lived streams
+    /// still pick up the volume chosen for their application.
+    fn add_to_application(&self, node: &PwNodeObject) {
+        if !matches!(node.nodetype(), NodeType::StreamInput | NodeType::StreamOutput) {
+            return;
+        }
+        // Our own sound tests, listening streams and peak meters are not applications the user wants to control.
+        if node.node_property::<String>("application.id").as_deref() == Some(APP_ID) || node.node_property::<bool>("stream.monitor") == Some(true) {
+            return;
+        }
+
+        let application = self.get_application_for_node(node).unwrap_or_else(|| {
+            let key = PwNodeGroupObject::application_key(node);
+            let application = PwNodeGroupObject::new(&key, &node.application_name(), node.nodetype(), true);
+            self.imp().application_model.append(&application);
+            application
+        });
+        application.add_member(node);
+    }
+
+    fn reapply_hide_rules(&self) {
+        let imp = self.imp();
+        let rules = imp.hide_rules.borrow();
+
+        for node in imp.node_model.iter::<PwNodeObject>().map_while(Result::ok) {
+            node.set_hidden(NodeRule::any_matches(&rules, |key| node.backend().property(key)));
+        }
+
+        for model in [&imp.stream_output_model, &imp.stream_input_model, &imp.source_model, &imp.sink_model] {
+            model.refilter();
+        }
+    }
+
+    /// Applies the default device priority once the current batch of node changes is processed,
+    /// so devices appearing together at startup don't switch the default back and forth.
+    fn queue_apply_device_priority(&self) {
+        let imp = self.imp();
+        if imp.device_priority_pending.replace(true) {
+            return;
+        }
+
+        glib::idle_add_local_once(clone!(@weak self as manager => move || {
+            manager.imp().device_priority_pending.set(false);
+            manager.apply_device_priority();
+        }));
+    }
+
+    /// Makes the highest priority sink and source that is present the configured default.
+    ///
+    /// Nothing changes for a media class when no rule matches a present node, so the session
+    /// manager's own choice stays in effect.
+    fn apply_device_priority(&self) {
+        let imp = self.imp();
+        if !imp.settings.boolean("auto-switch-default-device") {
+            return;
+        }
+        let rules = imp.device_priority.borrow();
+        for nodetype in [NodeType::Sink, NodeType::Source] {
+            let nodes: Vec<PwNodeObject> =
+                imp.node_model.iter::<PwNodeObject>().map_while(Result::ok).filter(|node| node.nodetype() == nodetype).collect();
+            let Some(node) = rules.iter().find_map(|rule| nodes.iter().find(|node| Self::device_priority_matches(rule, node))) else {
+                continue;
+            };
+
+            let (Some(media_class), Some(node_name)) = (Self::default_media_class(node), node.node_property::<String>("node.name")) else {
+                continue;
+            };
+            let configured = self.backend().default_configured_node_name(media_class);
+            if configured.as_deref() == Some(node_name.as_str()) {
+                continue;
+            }
+
+            pwvucontrol_info!("Switching default {media_class} to {node_name} by priority");
+            self.set_default_configured_node_name(media_class, &node_name);
+        }
+    }
+
+    /// Sets the node the session manager should use as default for `media_class`, an empty name clears it.
+    pub(crate) fn set_default_configured_node_name(&self, media_class: &str, node_name: &str) -> bool {
+        let result = self.backend().set_default_configured_node_name(media_class, node_name);
+        if !result {
+            pwvucontrol_warning!("Unable to set default {media_class} to {node_name}");
+        }
+        result
+    }
+
+    /// Media class the default-nodes-api keeps the default of `node` under.
+    pub(crate) fn default_media_class(node: &PwNodeObject) -> Option<&'static str> {
+        match node.nodetype() {
+            NodeType::Sink => Some("Audio/Sink"),
+            NodeType::Source if node.is_virtual() => Some("Audio/Source/Virtual"),
+            NodeType::Source => Some("Audio/Source"),
+            _ => None,
+        }
+    }
+
+    /// Makes `node` the default sink or source, optionally moving the existing streams over to it.
+    pub(crate) fn set_default_node(&self, node: &PwNodeObject, move_streams: bool) {
+        let (Some(media_class), Some(node_name)) = (Self::default_media_class(node), node.node_property::<String>("node.name")) else {
+            return;
+        };
+        if !self.set_default_configured_node_name(media_class, &node_name) || !move_streams {
+            return;
+        }
+
+        let stream_type = match node.nodetype() {
+            NodeType::Sink => NodeType::StreamOutput,
+            _ => NodeType::StreamInput,
+        };
+        // Streams without a target follow the default, so clearing it moves them along and keeps them following.
+        // Only running streams are moved, paused ones keep the target they may have been given. Hidden streams,
+        // like event sounds and peak meters, and monitor streams stay where they are.
+        let streams = self.imp().node_model.iter::<PwNodeObject>().map_while(Result::ok).filter(|stream| {
+            stream.nodetype() == stream_type && stream.running() && !stream.hidden() && stream.node_property::<bool>("stream.monitor") != Some(true)
+        });
+        for stream in streams {
+            stream.unset_default_target();
+        }
+    }
+
+    /// Whether `node` is a sink or source a priority rule matches, so it coming or going can change the default.
+    /// Other nodes leave the default alone, which may have been picked by hand.
+    fn matches_device_priority(&self, node: &PwNodeObject) -> bool {
+        matches!(node.nodetype(), NodeType::Sink | NodeType::Source)
+            && self.imp().device_priority.borrow().iter().any(|rule| Self::device_priority_matches(rule, node))
+    }
+
+    /// Whether `node` is the configured default of its media class.
+    fn is_configured_default(&self, node: &PwNodeObject) -> bool {
+        let (Some(media_class), Some(node_name)) = (Self::default_media_class(node), node.node_property::<String>("node.name")) else {
+            return false;
+        };
+        self.backend().default_configured_node_name(media_class).as_deref() == Some(node_name.as_str())
+    }
+
+    /// Priority rules match node properties as well as those of the node's device, like `device.bus`.
+    fn device_priority_matches(rule: &NodeRule, node: &PwNodeObject) -> bool {
+        rule.matches(|key| node.backend().property(key)) || node.device().is_some_and(|device| rule.matches(|key| device.backend().property(key)))
+    }
+
+    /// Adds a hide rule matching the sink or source `node`, so it stays hidden across restarts.
+    pub(crate) fn hide_node(&self, node: &PwNodeObject) {
+        self.add_hide_rule(NodeRule::new("node.name", &node.node_property::<String>("node.name").unwrap_or_default()));
+    }
+
+    /// Adds a hide rule matching every stream of the application `node` belongs to.
+    ///
+    /// Streams carry nothing that tells them apart across restarts, applications reuse their
+    /// `node.name` and `media.name` for all of them, so streams are only hidden by application.
+    pub(crate) fn hide_application(&self, node: &PwNodeObject) {
+        if let Some(application_name) = node.node_property::<String>("application.name") {
+            self.add_hide_rule(NodeRule::new("application.name", &application_name));
+        }
+    }
+
+    fn add_hide_rule(&self, rule: NodeRule) {
+        let settings = &self.imp().settings;
+        let mut rules = NodeRule::load(settings, "hide-rules");
+        if rules.contains(&rule) {
+            return;
+        }
+        rules.push(rule);
+        if NodeRule::store(settings, "hide-rules", &rules).is_err() {
+            pwvucontrol_warning!("Unable to save hide rules to gsettings");
+        }
+    }
+
+    /// How long volume ramps take in microseconds, `None` when ramping is turned off.
+    pub(crate) fn volume_ramp_duration(&self) -> Option<i64> {
+        let duration = self.imp().settings.uint("volume-ramp-duration");
+        (duration > 0).then_some(duration as i64 * 1000)
+    }
+
+    pub(crate) fn mute_on_unplug(&self) -> bool {
+        self.imp().settings.boolean("mute-on-unplug")
+    }
+
+    /// Channel volumes last used on the route with `key`, if route volumes are remembered.
+    pub(crate) fn remembered_route_volumes(&self, key: &str) -> Option<Vec<f32>> {
+        let settings = &self.imp().settings;
+        if !settings.boolean("remember-route-volumes") {
+            return None;
+        }
+        let volumes: HashMap<String, Vec<f64>> = settings.get("route-volumes");
+        volumes.get(key).map(|volumes| volumes.iter().map(|volume| *volume as f32).collect())
+    }
+
+    pub(crate) fn remember_route_volumes(&self, key: &str, channel_volumes: &[f32]) {
+        let settings = &self.imp().settings;
+        if !settings.boolean("remember-route-volumes") {
+            return;
+        }
+        let mut volumes: HashMap<String, Vec<f64>> = settings.get("route-volumes");
+        let channel_volumes: Vec<f64> = channel_volumes.iter().map(|volume| *volume as f64).collect();
+        if volumes.get(key) == Some(&channel_volumes) {
+            return;
+        }
+        volumes.insert(key.to_string(), channel_volumes);
+        if settings.set("route-volumes", volumes).is_err() {
+            pwvucontrol_warning!("Unable to save route volumes to gsettings");
+        }
+    }
+
+    /// Scheduling info of the node with `id` from the PipeWire profiler, which is started on the first call.
+    pub(crate) fn node_profile(&self, id: u32) -> Option<NodeProfile> {
+        self.backend().node_profile(id)
+    }
+
+    pub(crate) fn node_profiles(&self) -> Vec<NodeProfile> {
+        self.backend().node_profiles()
+    }
+
+    pub(crate) fn profiler_available(&self) -> bool {
+        self.backend().profiler_available()
+    }
+
+    /// Whether the `settings` metadata holding the global clock settings is there.
+    pub(crate) fn has_settings(&self) -> bool {
+        self.backend().has_settings()
+    }
+
+    /// Reads a global setting such as `clock.force-rate` from the `settings` metadata.
+    pub(crate) fn metadata_setting(&self, key: &str) -> Option<String> {
+        self.backend().setting(key)
+    }
+
+    /// Writes a global setting to the `settings` metadata, `None` resets it to the configured default.
+    pub(crate) fn set_metadata_setting(&self, key: &str, value: Option<&str>) {
+        self.backend().set_setting(key, value);
+    }
+
+    /// Reads `key` of `subject` from the `default` metadata, like `target.object` of a stream.
+    pub(crate) fn metadata_value(&self, subject: u32, key: &str) -> Option<String> {
+        self.backend().metadata_value(subject, key)
+    }
+
+    pub(crate) fn set_metadata_value(&self, subject: u32, key: &str, type_: Option<&str>, value: Option<&str>) {
+        self.backend().set_metadata_value(subject, key, type_, value);
+    }
+
+    /// Id of the node the session manager currently uses as default for `media_class`.
+    pub(crate) fn default_node_id(&self, media_class: &str) -> Option<u32> {
+        self.backend().default_node(media_class)
+    }
+
     pub fn get_model_for_nodetype(&self, nodetype: NodeType) -> PwNodeFilterModel {
         match nodetype {
             NodeType::Sink => self.sink_model(),
@@ -356,20 +562,21 @@ impl PwvucontrolManager {
     }
 
     pub fn default_configured_sink_node(&self) -> Option<PwNodeObject> {
-        let api = self.imp().default_nodes_api.get().expect("default_nodes_api");
-        let id = api.emit_by_name("get-default-node", &[&"Audio/Sink"]);
-        self.get_node_by_id(id)
+        self.get_node_by_id(self.default_node_id("Audio/Sink")?)
     }
 
     pub fn default_configured_source_node(&self) -> Option<PwNodeObject> {
-        let api = self.imp().default_nodes_api.get().expect("default_nodes_api");
-        let id = api.emit_by_name("get-default-node", &[&"Audio/Source"]);
-        self.get_node_by_id(id)
+        self.get_node_by_id(self.default_node_id("Audio/Source")?)
     }
 }
 
 impl Default for PwvucontrolManager {
     fn default() -> Self {
+        // Tests run the backend without an application.
+        #[cfg(test)]
+        if let Some(manager) = super::tests::current_manager() {
+            return manager;
+        }
         PwvucontrolApplication::default().manager()
     }
 }
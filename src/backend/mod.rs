@@ -1,21 +1,46 @@
+mod audiobackend;
 mod manager;
+mod noderules;
+mod objectdump;
 mod paramavailability;
 mod pwchannelobject;
 mod pwdeviceobject;
 mod pwnodefiltermodel;
+mod pwnodegroupobject;
 mod pwnodeobject;
+mod pwnodesearchfilter;
+mod pwnodesorter;
+mod pwperformanceobject;
 mod pwprofileobject;
+mod pwprofiler;
 mod pwroutefiltermodel;
 mod pwrouteobject;
 mod routedirection;
+mod spajson;
+mod spaparams;
+#[cfg(test)]
+mod tests;
 
+pub use audiobackend::{
+    ActiveRoute, AudioBackend, DeviceBackend, DeviceChange, MockBackend, MockRoute, NodeBackend, NodeChange, NodeProps, NodeVolume, ProfileInfo,
+    RouteInfo, RouteProps, WpBackend,
+};
 pub use manager::PwvucontrolManager;
+pub use noderules::NodeRule;
+pub use objectdump::{dump_object, sections_to_string, DumpSection};
 pub use paramavailability::ParamAvailability;
 pub use pwchannelobject::PwChannelObject;
-pub use pwdeviceobject::PwDeviceObject;
+pub use pwdeviceobject::{codec_display_name, BluetoothCodecProfile, BluetoothMode, PwDeviceObject};
 pub use pwnodefiltermodel::PwNodeFilterModel;
-pub use pwnodeobject::{NodeType, PwNodeObject};
+pub use pwnodegroupobject::PwNodeGroupObject;
+pub use pwnodeobject::{NodeType, PropsParamValue, PwNodeObject, SupportedFormats};
+pub use pwnodesearchfilter::PwNodeSearchFilter;
+pub use pwnodesorter::{NodeSortMode, PwNodeSorter};
+pub use pwperformanceobject::PwPerformanceObject;
 pub use pwprofileobject::PwProfileObject;
+pub use pwprofiler::{NodeProfile, PwProfiler};
 pub use pwroutefiltermodel::PwRouteFilterModel;
 pub use pwrouteobject::PwRouteObject;
 pub use routedirection::RouteDirection;
+pub use spajson::{json_object_string, json_string};
+pub use spaparams::{parse_active_route, parse_format, parse_profile, parse_props, parse_route, ParamError, ParamResult};
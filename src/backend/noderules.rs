@@ -0,0 +1,43 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use gtk::{gio, prelude::*};
+
+/// A property match used by the hide rules and the default device priority.
+///
+/// The pattern is a glob as understood by [`glib::PatternSpec`], so `*` matches
+/// any node that has the property set at all.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeRule {
+    pub key: String,
+    pub pattern: String,
+}
+
+impl NodeRule {
+    pub fn new(key: &str, pattern: &str) -> Self {
+        Self { key: key.to_string(), pattern: pattern.to_string() }
+    }
+
+    /// Checks the rule against an object whose properties are looked up with `property`.
+    pub fn matches(&self, property: impl Fn(&str) -> Option<String>) -> bool {
+        let Some(value) = property(&self.key) else {
+            return false;
+        };
+
+        glib::PatternSpec::new(&self.pattern).matches_string(&value)
+    }
+
+    /// Loads a rule list stored as `a(ss)` in `settings`.
+    pub fn load(settings: &gio::Settings, key: &str) -> Vec<NodeRule> {
+        let rules: Vec<(String, String)> = settings.get(key);
+        rules.iter().map(|(k, p)| NodeRule::new(k, p)).collect()
+    }
+
+    pub fn store(settings: &gio::Settings, key: &str, rules: &[NodeRule]) -> Result<(), glib::BoolError> {
+        let rules: Vec<(String, String)> = rules.iter().map(|r| (r.key.clone(), r.pattern.clone())).collect();
+        settings.set(key, rules.to_variant())
+    }
+
+    pub fn any_matches(rules: &[NodeRule], property: impl Fn(&str) -> Option<String>) -> bool {
+        rules.iter().any(|rule| rule.matches(&property))
+    }
+}
@@ -0,0 +1,115 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use gtk::prelude::*;
+use wireplumber as wp;
+use wp::pw::{GlobalProxyExt, PipewireObjectExt};
+
+/// A titled list of key/value pairs describing part of a PipeWire object.
+#[derive(Debug, Clone)]
+pub struct DumpSection {
+    pub title: String,
+    pub entries: Vec<(String, String)>,
+}
+
+/// Collects the properties and the given params of `object`, roughly what `pw-dump` shows for it.
+pub fn dump_object<O: IsA<wp::pw::GlobalProxy> + IsA<wp::pw::PipewireObject>>(object: &O, param_ids: &[&str]) -> Vec<DumpSection> {
+    let mut sections = Vec::new();
+
+    if let Some(props) = object.global_properties() {
+        sections.push(DumpSection { title: "Global Properties".into(), entries: properties_entries(&props) });
+    }
+    if let Some(props) = object.properties() {
+        sections.push(DumpSection { title: "Properties".into(), entries: properties_entries(&props) });
+    }
+
+    for id in param_ids {
+        let Some(params) = object.enum_params_sync(id, None) else {
+            continue;
+        };
+
+        let entries = params
+            .into_iter()
+            .filter_map(|value| value.get::<wp::spa::SpaPod>().ok())
+            .enumerate()
+            .map(|(index, pod)| (index.to_string(), format_pod(&pod, 0)))
+            .collect();
+        sections.push(DumpSection { title: id.to_string(), entries });
+    }
+
+    sections
+}
+
+/// Formats sections as text, for copying to the clipboard.
+pub fn sections_to_string(sections: &[DumpSection]) -> String {
+    let mut out = String::new();
+    for section in sections {
+        out.push_str(&format!("[{}]\n", section.title));
+        for (key, value) in section.entries.iter() {
+            out.push_str(&format!("{key} = {value}\n"));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+fn properties_entries(props: &wp::pw::Properties) -> Vec<(String, String)> {
+    let mut entries: Vec<(String, String)> = props
+        .new_iterator()
+        .into_iter()
+        .filter_map(|value| value.get::<wp::pw::PropertiesItem>().ok())
+        .map(|item| (item.key().to_string(), item.value().to_string()))
+        .collect();
+    entries.sort();
+    entries
+}
+
+/// Pretty-prints a pod, recursing into objects and structs.
+pub fn format_pod(pod: &wp::spa::SpaPod, indent: usize) -> String {
+    let pad = "  ".repeat(indent + 1);
+
+    if pod.is_none() {
+        "null".into()
+    } else if pod.is_boolean() {
+        pod.boolean().map(|v| v.to_string()).unwrap_or_default()
+    } else if pod.is_id() {
+        pod.id().map(|v| format!("Id {v}")).unwrap_or_default()
+    } else if pod.is_int() {
+        pod.int().map(|v| v.to_string()).unwrap_or_default()
+    } else if pod.is_long() {
+        pod.long().map(|v| v.to_string()).unwrap_or_default()
+    } else if pod.is_float() {
+        pod.float().map(|v| v.to_string()).unwrap_or_default()
+    } else if pod.is_double() {
+        pod.double().map(|v| v.to_string()).unwrap_or_default()
+    } else if pod.is_string() {
+        pod.string().map(|v| format!("\"{v}\"")).unwrap_or_default()
+    } else if pod.is_fd() {
+        pod.fd().map(|v| format!("Fd {v}")).unwrap_or_default()
+    } else if pod.is_rectangle() {
+        pod.rectangle().map(|(w, h)| format!("{w}x{h}")).unwrap_or_default()
+    } else if pod.is_fraction() {
+        pod.fraction().map(|(num, denom)| format!("{num}/{denom}")).unwrap_or_default()
+    } else if pod.is_choice() {
+        pod.choice_child().map(|child| format!("Choice {}", format_pod(&child, indent))).unwrap_or_default()
+    } else if pod.is_array() {
+        let values: Vec<String> = match pod.array_child() {
+            Some(child) if child.is_float() => pod.array_iterator::<f32>().map(|v| v.to_string()).collect(),
+            Some(child) if child.is_id() || child.is_int() => pod.array_iterator::<i32>().map(|v| v.to_string()).collect(),
+            _ => vec!["…".into()],
+        };
+        format!("[ {} ]", values.join(", "))
+    } else if pod.is_struct() {
+        let fields: Vec<String> = pod.iterator().into_iter().map(|field| format!("{pad}{}", format_pod(&field, indent + 1))).collect();
+        format!("{{\n{}\n{}}}", fields.join(",\n"), "  ".repeat(indent))
+    } else if pod.is_object() {
+        let fields: Vec<String> = pod
+            .iterator()
+            .into_iter()
+            .filter_map(|prop| prop.property())
+            .map(|(key, value)| format!("{pad}{key}: {}", format_pod(&value, indent + 1)))
+            .collect();
+        format!("{{\n{}\n{}}}", fields.join(",\n"), "  ".repeat(indent))
+    } else {
+        "<unsupported>".into()
+    }
+}
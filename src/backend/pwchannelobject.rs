@@ -60,9 +60,15 @@ glib::wrapper! {
 impl PwChannelObject {
     pub(crate) fn new(index: u32, volume: f32, node_object: &PwNodeObject) -> Self {
         let t_audiochannel = wp::spa::SpaIdTable::from_name("Spa:Enum:AudioChannel").expect("audio channel type");
-        let channel = node_object.format().unwrap().positions[index as usize];
 
-        let channelname = t_audiochannel.find_value(channel).expect("channel short name").short_name();
+        // The volumes can arrive before the format, or the format may name no position for this channel.
+        let channelname = node_object
+            .format()
+            .and_then(|format| format.positions.get(index as usize).copied())
+            .and_then(|channel| t_audiochannel.find_value(channel))
+            .and_then(|value| value.short_name())
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| format!("AUX{index}"));
 
         glib::Object::builder()
             .property("index", index)
@@ -2,23 +2,25 @@
 
 use crate::backend::{ParamAvailability, PwProfileObject};
 use gtk::glib::{
-    self, clone,
+    self,
     subclass::{prelude::*, Signal},
     ParamSpec, Properties, Value,
 };
 use gtk::{gio, prelude::*};
-use wireplumber as wp;
-use wp::{
-    pw::{PipewireObjectExt, PipewireObjectExt2},
-    spa::SpaPodBuilder,
-};
 
-use super::{PwRouteFilterModel, PwRouteObject, RouteDirection};
+use super::{ActiveRoute, DeviceBackend, DeviceChange, PwRouteFilterModel, PwRouteObject, RouteDirection, RouteProps};
 use crate::macros::*;
 use std::cell::OnceCell;
 use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
 use std::sync::OnceLock;
 
+mod bluetooth;
+mod routememory;
+
+pub use bluetooth::{codec_display_name, BluetoothCodecProfile, BluetoothMode};
+
 pub mod imp {
     use super::*;
 
@@ -40,8 +42,7 @@ pub mod imp {
         #[property(get, set)]
         pub(super) route_index_output: Cell<u32>,
 
-        #[property(get, set, construct_only)]
-        pub(super) wpdevice: OnceCell<wp::pw::Device>,
+        pub(super) backend: OnceCell<Rc<dyn DeviceBackend>>,
 
         #[property(get)]
         pub(super) profilemodel: gio::ListStore,
@@ -53,6 +54,14 @@ pub mod imp {
         pub(super) routemodel_output: PwRouteFilterModel,
 
         pub(super) routemodel: gio::ListStore,
+
+        /// Active route of each card device, by route `device` index.
+        pub(super) active_routes: RefCell<HashMap<i32, ActiveRoute>>,
+        /// Route volumes waiting to be remembered, by card device, with the key to remember them under.
+        pub(super) pending_route_volumes: RefCell<HashMap<i32, (String, Vec<f32>)>>,
+        pub(super) route_volumes_timeout: RefCell<Option<glib::SourceId>>,
+        /// Card devices whose output was muted after an unplug and is kept muted until unmuted by hand.
+        pub(super) unplug_muted: RefCell<HashSet<i32>>,
     }
 
     impl Default for PwDeviceObject {
@@ -63,11 +72,15 @@ pub mod imp {
                 profile_index: Default::default(),
                 route_index_input: Default::default(),
                 route_index_output: Default::default(),
-                wpdevice: Default::default(),
+                backend: Default::default(),
                 profilemodel: gio::ListStore::new::<PwProfileObject>(),
                 routemodel_input: PwRouteFilterModel::new(RouteDirection::Input, gio::ListModel::NONE),
                 routemodel_output: PwRouteFilterModel::new(RouteDirection::Output, gio::ListModel::NONE),
                 routemodel: gio::ListStore::new::<PwRouteObject>(),
+                active_routes: Default::default(),
+                pending_route_volumes: Default::default(),
+                route_volumes_timeout: Default::default(),
+                unplug_muted: Default::default(),
             }
         }
     }
@@ -100,6 +113,8 @@ pub mod imp {
                     Signal::builder("post-update-profile").build(),
                     Signal::builder("pre-update-route").build(),
                     Signal::builder("post-update-route").build(),
+                    Signal::builder("properties-changed").build(),
+                    Signal::builder("battery-changed").build(),
                 ]
             })
         }
@@ -109,43 +124,11 @@ pub mod imp {
 
             self.routemodel_input.set_model(Some(self.routemodel.as_ref()));
             self.routemodel_output.set_model(Some(self.routemodel.as_ref()));
+        }
 
-            let obj = self.obj();
-
-            obj.label_set_name();
-            obj.update_icon_name();
-            obj.update_profiles();
-
-            obj.update_current_profile_index();
-
-            obj.update_routes();
-
-            obj.wpdevice().connect_properties_notify(clone!(@weak obj => move |device| {
-                pwvucontrol_debug!("properties changed! id: {}", device.object_id().unwrap());
-
-                obj.label_set_name();
-            }));
-
-            obj.wpdevice().connect_params_changed(clone!(@weak obj => move |device, what| {
-                pwvucontrol_debug!("params-changed! {what} id: {}", device.object_id().unwrap());
-
-                match what {
-                    "EnumProfile" => {
-                        obj.update_profiles();
-                    },
-                    "Profile" => {
-                        obj.update_current_profile_index();
-                    },
-                    "EnumRoute" => {
-                        obj.update_routes();
-                    },
-                    "Route" => {
-                        obj.update_current_route_index();
-                    },
-                    _ => {},
-                }
-
-            }));
+        fn dispose(&self) {
+            // The device is gone, most likely unplugged, so remember what is still pending now.
+            self.obj().save_route_volumes();
         }
     }
 
@@ -157,66 +140,80 @@ glib::wrapper! {
 }
 
 impl PwDeviceObject {
-    pub(crate) fn new(node: &wp::pw::Device) -> Self {
-        glib::Object::builder().property("wpdevice", node).build()
+    pub(crate) fn new(backend: Rc<dyn DeviceBackend>) -> Self {
+        let obj: Self = glib::Object::new();
+        obj.imp().backend.set(backend).unwrap_or_else(|_| unreachable!("backend is only set here"));
+        obj.setup();
+        obj
     }
 
-    pub(crate) fn update_profiles(&self) {
-        let device = self.wpdevice();
-
-        device.enum_params(
-            Some("EnumProfile"),
-            None,
-            gtk::gio::Cancellable::NONE,
-            clone!(@weak self as widget => move |res| {
+    fn setup(&self) {
+        self.label_set_name();
+        self.update_icon_name();
+        self.update_profiles();
 
-                if let Ok(Some(iter)) = res {
-                    let mut profiles: Vec<PwProfileObject> = Vec::new();
+        self.update_current_profile_index();
 
-                    for a in iter {
-                        let pod: wp::spa::SpaPod = a.get().unwrap();
-                        if !pod.is_object() {
-                            continue;
-                        }
+        self.update_routes();
+        self.update_active_routes();
 
-                        let index: i32 = pod.spa_property(&wp::spa::ffi::SPA_PARAM_PROFILE_index).expect("Profile index");
-                        let description: String = pod.spa_property(&wp::spa::ffi::SPA_PARAM_PROFILE_description).expect("Profile description");
-                        let available = pod.find_spa_property(&wp::spa::ffi::SPA_PARAM_PROFILE_available).expect("Profile availability").id().expect("Id");
+        let obj = self.downgrade();
+        self.backend().connect_changed(Rc::new(move |change| {
+            let Some(obj) = obj.upgrade() else {
+                return;
+            };
 
-                        profiles.push(PwProfileObject::new(index as u32, &description, available));
-                    }
-                    widget.emit_by_name::<()>("pre-update-profile", &[]);
-                    widget.profilemodel().splice(0, widget.profilemodel().n_items(), &profiles);
-                    widget.update_current_profile_index();
-                    widget.emit_by_name::<()>("post-update-profile", &[]);
-                } else if let Err(e) = res {
-                    dbg!(e);
+            match change {
+                DeviceChange::Properties => {
+                    obj.label_set_name();
+                    obj.emit_by_name::<()>("properties-changed", &[]);
                 }
-            }),
-        );
+                DeviceChange::Profiles => obj.update_profiles(),
+                DeviceChange::Profile => obj.update_current_profile_index(),
+                DeviceChange::Routes => obj.update_routes(),
+                DeviceChange::Route => {
+                    obj.update_current_route_index();
+                    obj.update_active_routes();
+                }
+                DeviceChange::Battery => obj.emit_by_name::<()>("battery-changed", &[]),
+            }
+        }));
     }
 
-    pub(crate) fn update_current_profile_index(&self) {
-        let device = self.wpdevice();
+    pub(crate) fn backend(&self) -> &dyn DeviceBackend {
+        self.imp().backend.get().expect("Backend set on PwDeviceObject").as_ref()
+    }
 
-        if let Some(params) = device.enum_params_sync("Profile", None) {
-            for a in params {
-                let pod: wp::spa::SpaPod = a.get().unwrap();
-                if !pod.is_object() {
-                    continue;
-                }
+    pub(crate) fn id(&self) -> u32 {
+        self.backend().id()
+    }
 
-                let index: i32 = pod.spa_property(&wp::spa::ffi::SPA_PARAM_PROFILE_index).expect("Profile index");
-                let description: String = pod
-                    .spa_property(&wp::spa::ffi::SPA_PARAM_PROFILE_description)
-                    .expect("Profile description");
-                pwvucontrol_info!("Current profile #{} {}", index, description);
+    pub(crate) fn update_profiles(&self) {
+        let widget = self.downgrade();
+        self.backend().profiles(Box::new(move |profiles| {
+            let Some(widget) = widget.upgrade() else {
+                return;
+            };
+
+            let profiles: Vec<PwProfileObject> = profiles
+                .iter()
+                .map(|profile| PwProfileObject::new(profile.index, &profile.name, &profile.description, profile.availability))
+                .collect();
+            widget.emit_by_name::<()>("pre-update-profile", &[]);
+            widget.profilemodel().splice(0, widget.profilemodel().n_items(), &profiles);
+            widget.update_current_profile_index();
+            widget.emit_by_name::<()>("post-update-profile", &[]);
+        }));
+    }
 
-                if let Some(index) = self.get_model_index_from_profile_index(index as u32) {
-                    self.set_profile_index(index);
-                } else {
-                    pwvucontrol_critical!("Unable to get model index from profile index.");
-                }
+    pub(crate) fn update_current_profile_index(&self) {
+        if let Some(profile) = self.backend().current_profile() {
+            pwvucontrol_info!("Current profile #{} {}", profile.index, profile.description);
+
+            if let Some(index) = self.get_model_index_from_profile_index(profile.index) {
+                self.set_profile_index(index);
+            } else {
+                pwvucontrol_critical!("Unable to get model index from profile index.");
             }
         }
     }
@@ -234,79 +231,28 @@ impl PwDeviceObject {
     }
 
     pub(crate) fn set_profile(&self, index: i32) {
-        let device = self.wpdevice();
-
-        let podbuilder = SpaPodBuilder::new_object("Spa:Pod:Object:Param:Profile", "Profile");
-
-        podbuilder.add_property("index");
-        podbuilder.add_int(index);
-
-        if let Some(pod) = podbuilder.end() {
-            device.set_param("Profile", 0, pod);
-        }
+        self.backend().set_profile(index);
     }
 
     pub(crate) fn update_routes(&self) {
-        let device = self.wpdevice();
-
-        device.enum_params(
-            Some("EnumRoute"),
-            None,
-            gtk::gio::Cancellable::NONE,
-            clone!(@weak self as widget => move |res| {
-
-                if let Ok(Some(iter)) = res {
-                    let removed = widget.imp().routemodel.n_items();
-
-                    let mut routes: Vec<PwRouteObject> = Vec::new();
-
-                    for a in iter {
-                        let pod: wp::spa::SpaPod = a.get().unwrap();
-                        if !pod.is_object() {
-                            continue;
-                        }
-
-                        let index: i32 = pod.spa_property(&wp::spa::ffi::SPA_PARAM_ROUTE_index).expect("Route index");
-                        let description: String = pod.spa_property(&wp::spa::ffi::SPA_PARAM_ROUTE_description).expect("Route description");
-                        let direction: RouteDirection = pod.spa_property(&wp::spa::ffi::SPA_PARAM_ROUTE_direction).expect("Route direction");
-                        let available: ParamAvailability = pod.spa_property(&wp::spa::ffi::SPA_PARAM_ROUTE_available).expect("Route available");
-                        let profiles = pod.find_spa_property(&wp::spa::ffi::SPA_PARAM_ROUTE_profiles).expect("Profiles!");
-                        assert!(profiles.is_array());
-                        let profiles_vec: Vec<u32> =  profiles.array_iterator::<i32>().map(|x| x as u32).collect();
-
-                        let info: wireplumber::spa::SpaPod = pod.spa_property(&wp::spa::ffi::SPA_PARAM_ROUTE_info).expect("Route info");
-
-                        let productname = Self::find_struct_key(&info, "device.product.name");
-                        let desc = match productname {
-                            Some(x) => format!("{description} [{x}]"),
-                            None => description
-                        };
-
-                        routes.push(PwRouteObject::new(index as u32, &desc, available, direction, &profiles_vec));
-                    }
-                    // Notify update of list model
-                    widget.emit_by_name::<()>("pre-update-route", &[]);
-                    widget.imp().routemodel.splice(0, removed as u32, &routes);
-                    widget.update_current_route_index();
-                    widget.emit_by_name::<()>("post-update-route", &[]);
-                } else {
-                    if let Err(e) = res {
-                        dbg!(e);
-                    }
-                }
-            }),
-        );
-    }
-
-    fn find_struct_key(input: &wireplumber::spa::SpaPod, key: &str) -> Option<String> {
-        let mut iter = input.iterator().into_iter();
-
-        while let Some(k) = iter.next() {
-            if k.string() == Some(key.into()) {
-                return iter.next()?.string().map(|gs| gs.to_string());
-            }
-        }
-        None
+        let widget = self.downgrade();
+        self.backend().routes(Box::new(move |routes| {
+            let Some(widget) = widget.upgrade() else {
+                return;
+            };
+            let removed = widget.imp().routemodel.n_items();
+
+            let routes: Vec<PwRouteObject> = routes
+                .iter()
+                .map(|route| PwRouteObject::new(route.index, &route.description, route.availability, route.direction, &route.profiles))
+                .collect();
+
+            // Notify update of list model
+            widget.emit_by_name::<()>("pre-update-route", &[]);
+            widget.imp().routemodel.splice(0, removed, &routes);
+            widget.update_current_route_index();
+            widget.emit_by_name::<()>("post-update-route", &[]);
+        }));
     }
 
     pub(crate) fn update_current_route_index(&self) {
@@ -315,53 +261,29 @@ impl PwDeviceObject {
     }
 
     pub(crate) fn update_current_route_index_for_direction_sync(&self, direction: RouteDirection) {
-        let device = self.wpdevice();
-
-        let keys = wp::spa::SpaIdTable::from_name("Spa:Pod:Object:Param:Route").expect("id table");
-        let index_key = keys.find_value_from_short_name("index").expect("index key");
-        let description_key = keys.find_value_from_short_name("description").expect("decription key");
-        let direction_key = keys.find_value_from_short_name("direction").expect("direction key");
-
-        if let Some(params) = device.enum_params_sync("Route", None) {
-            for a in params {
-                let pod: wp::spa::SpaPod = a.get().unwrap();
-                if !pod.is_object() {
-                    continue;
-                }
-
-                let index = pod.find_spa_property(&index_key).expect("Index").int().expect("Int");
-                let description = pod
-                    .find_spa_property(&description_key)
-                    .expect("Description key")
-                    .string()
-                    .expect("String");
-                let direction_from_pod = pod.find_spa_property(&direction_key).expect("direction key").id().expect("Id");
+        for route in self.backend().active_routes() {
+            if route.direction != direction {
+                continue;
+            }
+            pwvucontrol_debug!("Current route #{} {}", route.index, route.description);
 
-                if direction_from_pod != direction as u32 {
-                    continue;
-                }
-                pwvucontrol_debug!("Current route #{} {}", index, description);
-
-                if let Some(modelindex) = self.get_model_index_from_route_index(direction, index) {
-                    match direction {
-                        RouteDirection::Input => {
-                            if self.route_index_input() != modelindex {
-                                self.set_route_index_input(modelindex)
-                            }
+            if let Some(modelindex) = self.get_model_index_from_route_index(direction, route.index) {
+                match direction {
+                    RouteDirection::Input => {
+                        if self.route_index_input() != modelindex {
+                            self.set_route_index_input(modelindex)
                         }
-                        RouteDirection::Output => {
-                            if self.route_index_output() != modelindex {
-                                self.set_route_index_output(modelindex)
-                            }
+                    }
+                    RouteDirection::Output => {
+                        if self.route_index_output() != modelindex {
+                            self.set_route_index_output(modelindex)
                         }
-                        _ => unreachable!(),
                     }
-                } else {
-                    pwvucontrol_critical!(
-                        "{direction:?} Unable to get model index from route index in update_current_route_index_for_direction_sync"
-                    );
-                };
-            }
+                    _ => unreachable!(),
+                }
+            } else {
+                pwvucontrol_critical!("{direction:?} Unable to get model index from route index in update_current_route_index_for_direction_sync");
+            };
         }
     }
 
@@ -381,20 +303,7 @@ impl PwDeviceObject {
     }
 
     pub(crate) fn set_route(&self, index: u32, device_index: i32) {
-        let device = self.wpdevice();
-
-        let podbuilder = SpaPodBuilder::new_object("Spa:Pod:Object:Param:Route", "Route");
-
-        podbuilder.add_property("index");
-        podbuilder.add_int(index as i32);
-        podbuilder.add_property("device");
-        podbuilder.add_int(device_index);
-        podbuilder.add_property("save");
-        podbuilder.add_boolean(true);
-
-        if let Some(pod) = podbuilder.end() {
-            device.set_param("Route", 0, pod);
-        }
+        self.backend().set_route(index as i32, device_index, &RouteProps::default());
     }
 
     fn get_route_model_for_direction(&self, direction: RouteDirection) -> PwRouteFilterModel {
@@ -406,15 +315,16 @@ impl PwDeviceObject {
     }
 
     fn label_set_name(&self) {
-        let description: String = self.wpdevice().pw_property("device.description").expect("device description");
+        let backend = self.backend();
+        let description = backend
+            .property("device.description")
+            .or_else(|| backend.property("device.name"))
+            .unwrap_or_else(|| format!("Device {}", backend.id()));
         self.set_name(description);
     }
 
     fn update_icon_name(&self) {
-        let icon_name: String = self
-            .wpdevice()
-            .pw_property("device.icon-name")
-            .unwrap_or("soundcard-symbolic".to_string());
+        let icon_name = self.backend().property("device.icon-name").unwrap_or("soundcard-symbolic".to_string());
         self.set_icon_name(icon_name);
     }
 }
@@ -0,0 +1,154 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use super::*;
+use crate::backend::{PwNodeObject, PwvucontrolManager};
+
+/// The kind of connection a Bluetooth profile sets up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BluetoothMode {
+    /// A2DP or LE audio playback, one way and high quality.
+    HighFidelity,
+    /// HSP/HFP, two way with a low quality microphone.
+    Headset,
+    Off,
+}
+
+impl BluetoothMode {
+    fn from_profile_name(name: &str) -> Self {
+        if name.starts_with("a2dp") || name.starts_with("bap") {
+            BluetoothMode::HighFidelity
+        } else if name.starts_with("headset") || name.starts_with("audio-gateway") {
+            BluetoothMode::Headset
+        } else {
+            BluetoothMode::Off
+        }
+    }
+}
+
+/// A profile of a Bluetooth device that selects a codec, like `a2dp-sink-ldac`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BluetoothCodecProfile {
+    pub profile_index: u32,
+    /// Codec part of the profile name, like `ldac`.
+    pub codec: String,
+}
+
+/// Human readable name of a codec as it appears in `api.bluez5.codec` and profile names.
+pub fn codec_display_name(codec: &str) -> String {
+    match codec {
+        "sbc" => "SBC".to_string(),
+        "sbc_xq" => "SBC-XQ".to_string(),
+        "aac" => "AAC".to_string(),
+        "aptx" => "aptX".to_string(),
+        "aptx_hd" => "aptX HD".to_string(),
+        "aptx_ll" | "aptx_ll_duplex" => "aptX LL".to_string(),
+        "ldac" => "LDAC".to_string(),
+        "msbc" => "mSBC".to_string(),
+        "cvsd" => "CVSD".to_string(),
+        "lc3" => "LC3".to_string(),
+        "lc3plus_h3" => "LC3plus".to_string(),
+        "opus_05" | "opus_g" => "Opus".to_string(),
+        other => other.to_uppercase(),
+    }
+}
+
+impl PwDeviceObject {
+    pub(crate) fn is_bluetooth(&self) -> bool {
+        self.backend().property("device.api").as_deref() == Some("bluez5")
+    }
+
+    pub(crate) fn battery_level(&self) -> Option<u32> {
+        self.backend().battery_level()
+    }
+
+    pub(crate) fn current_profile(&self) -> Option<PwProfileObject> {
+        self.profilemodel().item(self.profile_index()).and_downcast()
+    }
+
+    pub(crate) fn bluetooth_mode(&self) -> BluetoothMode {
+        self.current_profile().map_or(BluetoothMode::Off, |profile| BluetoothMode::from_profile_name(&profile.name()))
+    }
+
+    /// Codec in use, as reported by the device's nodes, or taken from the profile name.
+    pub(crate) fn bluetooth_codec(&self) -> Option<String> {
+        let from_nodes = self.nodes().iter().find_map(|node| node.node_property::<String>("api.bluez5.codec"));
+        from_nodes.or_else(|| {
+            let profile = self.current_profile()?;
+            self.codec_profiles(self.bluetooth_mode()).into_iter().find(|x| x.profile_index == profile.index()).map(|x| x.codec)
+        })
+    }
+
+    /// Available profiles of `mode` that pick a specific codec.
+    pub(crate) fn codec_profiles(&self, mode: BluetoothMode) -> Vec<BluetoothCodecProfile> {
+        let Some(family) = self.current_profile_family(mode) else {
+            return Vec::new();
+        };
+
+        self.profilemodel()
+            .iter::<PwProfileObject>()
+            .map_while(Result::ok)
+            .filter(|profile| profile.availability() != ParamAvailability::No)
+            .filter_map(|profile| {
+                let codec = profile.name().strip_prefix(&format!("{family}-"))?.to_string();
+                Some(BluetoothCodecProfile { profile_index: profile.index(), codec })
+            })
+            .collect()
+    }
+
+    fn mode_profiles(&self, mode: BluetoothMode) -> Vec<PwProfileObject> {
+        self.profilemodel()
+            .iter::<PwProfileObject>()
+            .map_while(Result::ok)
+            .filter(|profile| profile.availability() != ParamAvailability::No)
+            .filter(|profile| BluetoothMode::from_profile_name(&profile.name()) == mode)
+            .collect()
+    }
+
+    pub(crate) fn has_bluetooth_mode(&self, mode: BluetoothMode) -> bool {
+        !self.mode_profiles(mode).is_empty()
+    }
+
+    /// Switches to the first available profile of `mode`, preferring the codec-less default profile.
+    pub(crate) fn set_bluetooth_mode(&self, mode: BluetoothMode) {
+        if self.bluetooth_mode() == mode {
+            return;
+        }
+
+        let profiles = self.mode_profiles(mode);
+        let profile = profiles.iter().min_by_key(|profile| profile.name().len());
+        if let Some(profile) = profile {
+            self.set_profile(profile.index() as i32);
+        } else {
+            pwvucontrol_warning!("No available {mode:?} profile on {}", self.name().unwrap_or_default());
+        }
+    }
+
+    /// Base name of the current profile when it is of `mode`, like `a2dp-sink` or `headset-head-unit`.
+    fn current_profile_family(&self, mode: BluetoothMode) -> Option<String> {
+        let current = self.current_profile()?.name();
+        if BluetoothMode::from_profile_name(&current) != mode {
+            return None;
+        }
+
+        let names: Vec<String> = self.profilemodel().iter::<PwProfileObject>().map_while(Result::ok).map(|profile| profile.name()).collect();
+        let extended = |name: &String| names.iter().any(|other| other.starts_with(&format!("{name}-")));
+
+        // The family is the current profile name, or the longest one it extends with a codec suffix.
+        names
+            .iter()
+            .filter(|name| **name == current || current.starts_with(&format!("{name}-")))
+            .filter(|name| extended(name))
+            .max_by_key(|name| name.len())
+            .cloned()
+    }
+
+    /// Nodes the device currently provides.
+    pub(crate) fn nodes(&self) -> Vec<PwNodeObject> {
+        PwvucontrolManager::default()
+            .node_model()
+            .iter::<PwNodeObject>()
+            .map_while(Result::ok)
+            .filter(|node| node.device().as_ref() == Some(self))
+            .collect()
+    }
+}
@@ -0,0 +1,120 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::{collections::HashMap, time::Duration};
+
+use glib::clone;
+
+use super::*;
+use crate::backend::PwvucontrolManager;
+
+/// How long the volume of a route has to stay put before it is written to the settings.
+const ROUTE_VOLUMES_DELAY: Duration = Duration::from_secs(2);
+
+impl PwDeviceObject {
+    /// Reacts to the active routes changing: restores the volume remembered for a newly selected
+    /// route and mutes the outputs that took over from a route that was unplugged.
+    pub(super) fn update_active_routes(&self) {
+        let routes = self.backend().active_routes();
+
+        let manager = PwvucontrolManager::default();
+        let mut availability: Option<HashMap<i32, ParamAvailability>> = None;
+
+        for route in routes {
+            let previous = self.imp().active_routes.borrow_mut().insert(route.device, route.clone());
+
+            match previous {
+                Some(previous) if previous.index != route.index => {
+                    pwvucontrol_info!("Device {} switched from route {} to {}", route.device, previous.name, route.name);
+
+                    // What was set on the previous route belongs to it, not to the one switched to.
+                    self.save_route_volumes_of(route.device);
+                    self.imp().unplug_muted.borrow_mut().remove(&route.device);
+
+                    let volumes = manager.remembered_route_volumes(&self.route_key(&route.name));
+
+                    let unplugged = route.direction == RouteDirection::Output && manager.mute_on_unplug() && {
+                        let availability = availability.get_or_insert_with(|| self.route_availability());
+                        availability.get(&previous.index) == Some(&ParamAvailability::No)
+                    };
+                    if unplugged {
+                        self.imp().unplug_muted.borrow_mut().insert(route.device);
+                    }
+
+                    if volumes.is_some() || unplugged {
+                        self.set_route_props(&route, volumes.as_deref(), unplugged.then_some(true));
+                    }
+                }
+                _ => {
+                    // The session manager restores the saved mute state of a route after switching, so mute again
+                    // until it is unmuted by hand.
+                    if !route.mute && self.imp().unplug_muted.borrow().contains(&route.device) {
+                        pwvucontrol_info!("Keeping route {} of device {} muted after unplug", route.name, route.device);
+                        self.set_route_props(&route, None, Some(true));
+                    }
+                    if !route.channel_volumes.is_empty() {
+                        self.queue_route_volumes(&route);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Lets the output of `card_device` be unmuted again after it was muted on unplug.
+    pub(crate) fn release_unplug_mute(&self, card_device: i32) {
+        self.imp().unplug_muted.borrow_mut().remove(&card_device);
+    }
+
+    /// Remembers the volumes of a route once they stopped changing, as every step of a volume
+    /// slider drag changes the route.
+    fn queue_route_volumes(&self, route: &ActiveRoute) {
+        let imp = self.imp();
+        imp.pending_route_volumes.borrow_mut().insert(route.device, (self.route_key(&route.name), route.channel_volumes.clone()));
+
+        if let Some(source) = imp.route_volumes_timeout.take() {
+            source.remove();
+        }
+        let source = glib::timeout_add_local_once(
+            ROUTE_VOLUMES_DELAY,
+            clone!(@weak self as device => move || {
+                device.imp().route_volumes_timeout.take();
+                device.save_route_volumes();
+            }),
+        );
+        imp.route_volumes_timeout.replace(Some(source));
+    }
+
+    fn save_route_volumes_of(&self, card_device: i32) {
+        let pending = self.imp().pending_route_volumes.borrow_mut().remove(&card_device);
+        if let Some((key, channel_volumes)) = pending {
+            PwvucontrolManager::default().remember_route_volumes(&key, &channel_volumes);
+        }
+    }
+
+    /// Remembers all pending route volumes right away.
+    pub(super) fn save_route_volumes(&self) {
+        if let Some(source) = self.imp().route_volumes_timeout.take() {
+            source.remove();
+        }
+        let pending: Vec<(String, Vec<f32>)> = self.imp().pending_route_volumes.borrow_mut().drain().map(|(_, pending)| pending).collect();
+        let manager = PwvucontrolManager::default();
+        for (key, channel_volumes) in pending {
+            manager.remember_route_volumes(&key, &channel_volumes);
+        }
+    }
+
+    /// Availability of every route, read fresh so it is current even if `EnumRoute` changes are
+    /// reported after the `Route` change.
+    fn route_availability(&self) -> HashMap<i32, ParamAvailability> {
+        self.backend().route_availability().into_iter().collect()
+    }
+
+    fn route_key(&self, route_name: &str) -> String {
+        let device_name = self.backend().property("device.name").unwrap_or_default();
+        format!("{device_name}:{route_name}")
+    }
+
+    fn set_route_props(&self, route: &ActiveRoute, channel_volumes: Option<&[f32]>, mute: Option<bool>) {
+        let props = RouteProps { channel_volumes: channel_volumes.map(<[f32]>::to_vec), mute };
+        self.backend().set_route(route.index, route.device, &props);
+    }
+}
@@ -17,6 +17,12 @@ mod imp {
         #[property(get, set, construct_only, builder(NodeType::Undefined))]
         pub(super) nodetype: Cell<NodeType>,
 
+        /// Also pass through nodes that are hidden by the hide rules.
+        #[property(get, set = Self::set_show_hidden)]
+        pub(super) show_hidden: Cell<bool>,
+
+        pub(super) filter: OnceCell<gtk::CustomFilter>,
+
         /// The model we are filtering.
         #[property(get, set = Self::set_model, nullable)]
         pub(super) model: RefCell<Option<gio::ListModel>>,
@@ -38,14 +44,17 @@ mod imp {
 
             let nodetype = self.nodetype.get();
 
+            let widget = self.obj().downgrade();
             let filter = gtk::CustomFilter::new(move |obj| {
                 let node: &PwNodeObject = obj.downcast_ref().expect("PwNodeObject");
-                node.nodetype() == nodetype && !node.hidden()
+                let show_hidden = widget.upgrade().map_or(false, |widget| widget.show_hidden());
+                node.nodetype() == nodetype && (show_hidden || !node.hidden())
             });
 
             self.filtered_model
-                .set(gtk::FilterListModel::new(None::<gio::ListModel>, Some(filter)))
+                .set(gtk::FilterListModel::new(None::<gio::ListModel>, Some(filter.clone())))
                 .expect("filtered model not set");
+            self.filter.set(filter).expect("filter not set");
         }
     }
 
@@ -87,6 +96,12 @@ mod imp {
             }
         }
 
+        fn set_show_hidden(&self, value: bool) {
+            if self.show_hidden.replace(value) != value {
+                self.obj().refilter();
+            }
+        }
+
         fn disconnect(&self) {
             let filtered_model = self.filtered_model.get().expect("Filtered model");
             filtered_model.set_model(gio::ListModel::NONE);
@@ -106,6 +121,13 @@ impl PwNodeFilterModel {
         glib::Object::builder().property("model", &model).property("nodetype", nodetype).build()
     }
 
+    /// Re-evaluates the filter, needed after the hidden state of nodes changes.
+    pub(crate) fn refilter(&self) {
+        if let Some(filter) = self.imp().filter.get() {
+            filter.changed(gtk::FilterChange::Different);
+        }
+    }
+
     pub fn get_node_pos_from_id(&self, id: u32) -> Option<u32> {
         let pos: Option<usize> = self
             .iter::<PwNodeObject>()
@@ -0,0 +1,229 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use super::{NodeType, PwNodeObject};
+use glib::{clone, Properties, SignalHandlerId};
+use gtk::{prelude::*, subclass::prelude::*};
+use std::cell::{Cell, RefCell};
+
+mod imp {
+    use super::*;
+
+    #[derive(Properties)]
+    #[properties(wrapper_type = super::PwNodeGroupObject)]
+    pub struct PwNodeGroupObject {
+        /// The application name or process id, or the target name for target groups.
+        #[property(get, set, construct_only)]
+        key: RefCell<String>,
+        #[property(get, set)]
+        name: RefCell<String>,
+        #[property(get, set)]
+        iconname: RefCell<String>,
+        /// Stream direction of application groups, so playback and recording are controlled apart.
+        /// `Undefined` for target groups.
+        #[property(get, set, construct_only, builder(NodeType::Undefined))]
+        nodetype: Cell<NodeType>,
+        #[property(get, set = Self::set_volume)]
+        pub(super) volume: Cell<f32>,
+        #[property(get, set = Self::set_mute)]
+        pub(super) mute: Cell<bool>,
+        #[property(get)]
+        pub(super) n_members: Cell<u32>,
+
+        /// Apply the group volume and mute to streams joining later on.
+        #[property(get, set, construct_only)]
+        pub(super) apply_to_new_members: Cell<bool>,
+
+        /// Product of the volume changes made on the group, applied to streams joining later on.
+        pub(super) scale: Cell<f32>,
+        /// Set once the user changed the group volume or mute.
+        pub(super) volume_set: Cell<bool>,
+        pub(super) mute_set: Cell<bool>,
+
+        pub(super) members: RefCell<Vec<(PwNodeObject, Vec<SignalHandlerId>)>>,
+        pub(super) block: Cell<bool>,
+    }
+
+    impl Default for PwNodeGroupObject {
+        fn default() -> Self {
+            Self {
+                key: Default::default(),
+                name: Default::default(),
+                iconname: Default::default(),
+                nodetype: Default::default(),
+                volume: Default::default(),
+                mute: Default::default(),
+                n_members: Default::default(),
+                apply_to_new_members: Default::default(),
+                scale: Cell::new(1.0),
+                volume_set: Default::default(),
+                mute_set: Default::default(),
+                members: Default::default(),
+                block: Default::default(),
+            }
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for PwNodeGroupObject {
+        const NAME: &'static str = "PwNodeGroupObject";
+        type Type = super::PwNodeGroupObject;
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for PwNodeGroupObject {
+        fn dispose(&self) {
+            self.obj().clear_members();
+        }
+    }
+
+    impl PwNodeGroupObject {
+        /// Scales every member relative to its current volume so their balance is kept.
+        fn set_volume(&self, volume: f32) {
+            let old = self.volume.replace(volume);
+            if self.block.get() || old == volume {
+                return;
+            }
+            self.volume_set.set(true);
+            self.scale.set(if old > 0.0 { self.scale.get() * volume / old } else { volume });
+
+            self.block.set(true);
+            for (member, _) in self.members.borrow().iter() {
+                let new_volume = if old > 0.0 { member.volume() * volume / old } else { volume };
+                member.set_volume(new_volume);
+            }
+            self.block.set(false);
+        }
+
+        fn set_mute(&self, mute: bool) {
+            let old = self.mute.replace(mute);
+            if self.block.get() || old == mute {
+                return;
+            }
+            self.mute_set.set(true);
+
+            self.block.set(true);
+            for (member, _) in self.members.borrow().iter() {
+                member.set_mute(mute);
+            }
+            self.block.set(false);
+        }
+    }
+}
+
+glib::wrapper! {
+    /// A set of streams controlled together, such as all streams of one application.
+    pub struct PwNodeGroupObject(ObjectSubclass<imp::PwNodeGroupObject>);
+}
+
+impl PwNodeGroupObject {
+    pub(crate) fn new(key: &str, name: &str, nodetype: NodeType, apply_to_new_members: bool) -> Self {
+        glib::Object::builder()
+            .property("key", key)
+            .property("name", name)
+            .property("nodetype", nodetype)
+            .property("apply-to-new-members", apply_to_new_members)
+            .build()
+    }
+
+    /// Key identifying the application of a stream, for the list headers as well as the application groups.
+    pub(crate) fn application_key(node: &PwNodeObject) -> String {
+        node.application_name()
+    }
+
+    pub(crate) fn members(&self) -> Vec<PwNodeObject> {
+        self.imp().members.borrow().iter().map(|(member, _)| member.clone()).collect()
+    }
+
+    pub(crate) fn set_members(&self, members: &[PwNodeObject]) {
+        self.clear_members();
+        for member in members {
+            self.add_member(member);
+        }
+        self.update_from_members();
+    }
+
+    pub(crate) fn add_member(&self, node: &PwNodeObject) {
+        let imp = self.imp();
+        if imp.members.borrow().iter().any(|(member, _)| member == node) {
+            return;
+        }
+
+        if imp.apply_to_new_members.get() {
+            // Relative, so the stream keeps its own level scaled like the rest of the application,
+            // and never louder than it asked for unless it asked for more than 100%.
+            // A volume of zero is one not reported yet, scaling it would silence the stream.
+            let volume = node.volume();
+            if imp.volume_set.get() && volume > 0.0 {
+                node.set_volume((volume * imp.scale.get()).min(volume.max(1.0)));
+            }
+            if imp.mute_set.get() {
+                node.set_mute(imp.mute.get());
+            }
+        }
+        if self.iconname().is_empty() {
+            self.set_iconname(node.iconname());
+        }
+
+        let handlers = vec![
+            node.connect_volume_notify(clone!(@weak self as group => move |_| group.update_from_members())),
+            node.connect_mute_notify(clone!(@weak self as group => move |_| group.update_from_members())),
+        ];
+        imp.members.borrow_mut().push((node.clone(), handlers));
+        self.update_n_members();
+        self.update_from_members();
+    }
+
+    pub(crate) fn remove_member(&self, node: &PwNodeObject) {
+        let removed = {
+            let mut members = self.imp().members.borrow_mut();
+            members.iter().position(|(member, _)| member == node).map(|pos| members.remove(pos))
+        };
+
+        if let Some((member, handlers)) = removed {
+            for handler in handlers {
+                member.disconnect(handler);
+            }
+            self.update_n_members();
+            self.update_from_members();
+        }
+    }
+
+    fn clear_members(&self) {
+        for (member, handlers) in self.imp().members.take() {
+            for handler in handlers {
+                member.disconnect(handler);
+            }
+        }
+        self.update_n_members();
+    }
+
+    fn update_n_members(&self) {
+        let imp = self.imp();
+        let n_members = imp.members.borrow().len() as u32;
+        if imp.n_members.replace(n_members) != n_members {
+            self.notify_n_members();
+        }
+    }
+
+    fn update_from_members(&self) {
+        let imp = self.imp();
+        if imp.block.get() {
+            return;
+        }
+
+        let (volume, mute) = {
+            let members = imp.members.borrow();
+            if members.is_empty() {
+                return;
+            }
+            let volume = members.iter().map(|(member, _)| member.volume()).max_by(f32::total_cmp).unwrap_or_default();
+            let mute = members.iter().all(|(member, _)| member.mute());
+            (volume, mute)
+        };
+
+        imp.block.set(true);
+        self.set_volume(volume);
+        self.set_mute(mute);
+        imp.block.set(false);
+    }
+}
@@ -1,22 +1,33 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use super::{PwChannelObject, PwDeviceObject, PwRouteObject, PwvucontrolManager};
+use super::{NodeBackend, NodeChange, PwChannelObject, PwDeviceObject, PwRouteObject, PwvucontrolManager, RouteDirection};
 use glib::{clone, subclass::Signal, ParamSpec, Properties, Value};
 use gtk::{gio, prelude::*, subclass::prelude::*};
 use std::cell::OnceCell;
 use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::str::FromStr;
 use std::sync::OnceLock;
 use wireplumber as wp;
-use wp::registry::ObjectManager;
-use wp::{
-    pw::{FromPipewirePropertyString, GlobalProxyExt, MetadataExt, PipewireObjectExt, PipewireObjectExt2, ProxyExt},
-    registry::{Constraint, ConstraintType, Interest},
-    spa::SpaPodBuilder,
-};
 
 use crate::macros::*;
 
-mod mixerapi;
+mod balance;
+mod channelmix;
+mod format;
+mod ramp;
+mod volume;
+
+pub use format::SupportedFormats;
+
+/// A value for [`PwNodeObject::set_props_params`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropsParamValue {
+    Int(i32),
+    Float(f32),
+    Bool(bool),
+    String(String),
+}
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Default, glib::Enum)]
 #[enum_type(name = "NodeType")]
@@ -31,6 +42,10 @@ pub enum NodeType {
 
 #[derive(Copy, Clone, Debug)]
 pub struct AudioFormat {
+    /// An id from `Spa:Enum:AudioFormat`.
+    pub format: u32,
+    pub channels: u32,
+    pub rate: u32,
     pub positions: [u32; 64],
 }
 
@@ -66,6 +81,11 @@ pub mod imp {
         iconname: RefCell<String>,
         #[property(get, set)]
         hidden: Cell<bool>,
+        #[property(get)]
+        pub(super) running: Cell<bool>,
+        /// Monotonic time in microseconds of when the node last started running.
+        #[property(get)]
+        pub(super) last_active: Cell<i64>,
 
         pub(super) channel_volumes: RefCell<Vec<f32>>,
         #[property(get, builder(NodeType::Undefined))]
@@ -82,12 +102,32 @@ pub mod imp {
         #[property(get, set)]
         pub(super) channellock: Cell<bool>,
 
-        #[property(get, set, construct_only)]
-        pub(super) wpnode: OnceCell<wp::pw::Node>,
+        /// Left/right balance from -1.0 to 1.0, derived from the channel volumes.
+        #[property(get, set)]
+        pub(super) balance: Cell<f32>,
+        /// Rear/front fade from -1.0 to 1.0, derived from the channel volumes.
+        #[property(get, set)]
+        pub(super) fade: Cell<f32>,
+        #[property(get)]
+        pub(super) has_balance: Cell<bool>,
+        #[property(get)]
+        pub(super) has_fade: Cell<bool>,
+
+        /// Adapter channelmix settings, read from and written to the Props `params`.
+        #[property(get, set)]
+        channelmix_upmix: Cell<bool>,
+        #[property(get, set)]
+        channelmix_normalize: Cell<bool>,
+        #[property(get, set)]
+        channelmix_lfe_cutoff: Cell<f32>,
+
+        pub(super) backend: OnceCell<Rc<dyn NodeBackend>>,
 
         pub(super) block: Cell<bool>,
 
-        pub(super) om: RefCell<ObjectManager>,
+        /// Channel volumes last set through mixer-api, where a volume ramp starts from.
+        pub(super) sent_volumes: RefCell<Vec<f32>>,
+        pub(super) ramp: RefCell<Option<super::ramp::VolumeRamp>>,
 
         #[property(get)]
         pub(super) device: RefCell<Option<PwDeviceObject>>,
@@ -110,10 +150,20 @@ pub mod imp {
                 channelmodel: RefCell::new(gio::ListStore::new::<PwChannelObject>()),
                 format: Default::default(),
                 channellock: Default::default(),
-                wpnode: OnceCell::default(),
+                balance: Default::default(),
+                fade: Default::default(),
+                has_balance: Default::default(),
+                has_fade: Default::default(),
+                channelmix_upmix: Default::default(),
+                channelmix_normalize: Default::default(),
+                channelmix_lfe_cutoff: Default::default(),
+                backend: OnceCell::default(),
                 block: Default::default(),
-                om: Default::default(),
+                sent_volumes: Default::default(),
+                ramp: Default::default(),
                 hidden: Default::default(),
+                running: Default::default(),
+                last_active: Default::default(),
                 device: Default::default(),
                 is_virtual: Default::default(),
             }
@@ -136,12 +186,12 @@ pub mod imp {
             match pspec.name() {
                 "volume" => {
                     if !self.block.get() {
-                        self.obj().send_volume_using_mixerapi(PropertyChanged::Volume);
+                        self.obj().send_volume(PropertyChanged::Volume);
                     }
                 }
                 "mute" => {
                     if !self.block.get() {
-                        self.obj().send_volume_using_mixerapi(PropertyChanged::Mute);
+                        self.obj().send_volume(PropertyChanged::Mute);
                     }
                 }
                 "mainvolume" => {
@@ -154,6 +204,21 @@ pub mod imp {
                         self.obj().send_monitorvolume();
                     }
                 }
+                "balance" => {
+                    if !self.block.get() {
+                        self.obj().send_balance();
+                    }
+                }
+                "fade" => {
+                    if !self.block.get() {
+                        self.obj().send_fade();
+                    }
+                }
+                name @ ("channelmix-upmix" | "channelmix-normalize" | "channelmix-lfe-cutoff") => {
+                    if !self.block.get() {
+                        self.obj().send_channelmix(name);
+                    }
+                }
                 _ => {}
             }
         }
@@ -166,99 +231,6 @@ pub mod imp {
             static SIGNALS: OnceLock<Vec<Signal>> = OnceLock::new();
             SIGNALS.get_or_init(|| vec![Signal::builder("format").build()])
         }
-
-        fn constructed(&self) {
-            self.parent_constructed();
-
-            let obj = self.obj();
-
-            let node = self.wpnode.get().expect("Node set on PwNodeObject");
-
-            self.nodetype.set(get_node_type_for_node(node));
-            self.boundid.set(node.bound_id());
-
-            if node.get_pw_property("media.class").as_deref() == Some("Audio/Source/Virtual") {
-                self.is_virtual.set(true);
-            }
-
-            node.connect_notify_local(
-                Some("global-properties"),
-                clone!(@weak obj => move  |_, _| {
-                    obj.label_set_name()
-                }),
-            );
-
-            node.connect_notify_local(
-                Some("properties"),
-                clone!(@weak obj => move  |_, _| {
-                    obj.label_set_description();
-                }),
-            );
-
-            node.connect_params_changed(clone!(@weak obj => move |node,what| {
-                pwvucontrol_debug!("params-changed! {what} id: {}", node.bound_id());
-                obj.imp().block.set(true);
-                match what {
-                    "Props" => obj.update_props(),
-                    "Format" => obj.update_format(),
-                    _ => {},
-                }
-                obj.imp().block.set(false);
-            }));
-
-            obj.label_set_description();
-            obj.update_props();
-            obj.update_format();
-            obj.label_set_name();
-
-            obj.get_mixer_api();
-            obj.update_volume_using_mixerapi();
-            obj.update_icon_name();
-
-            let om = self.om.borrow();
-
-            om.add_interest(
-                [Constraint::compare(ConstraintType::PwProperty, "link.output.node", node.bound_id(), true)]
-                    .iter()
-                    .collect::<Interest<wp::pw::Link>>(),
-            );
-
-            if let Ok(Some(device_id)) = node.device_id() {
-                om.add_interest(
-                    [Constraint::compare(ConstraintType::GProperty, "bound-id", device_id, true)]
-                        .iter()
-                        .collect::<Interest<wp::pw::Device>>(),
-                );
-            }
-
-            om.connect_object_added(clone!(@weak self as nodeobject => move |_om, obj| {
-                if let Some(link) = obj.downcast_ref::<wp::pw::Link>() {
-                    let linked_node_id: u32 = link.pw_property("link.input.node").expect("link.input.node property");
-                    let linked_node = PwvucontrolManager::default().get_node_by_id(linked_node_id);
-                    pwvucontrol_info!("Node {} linked to node id {linked_node_id} ({:?})", nodeobject.obj().name(), linked_node.map(|x|x.name()));
-                } else if let Some(device) = obj.downcast_ref::<wp::pw::Device>() {
-                    let device_name: String = device.pw_property("device.description").unwrap();
-                    let manager = PwvucontrolManager::default();
-                    nodeobject.device.set(manager.get_device_by_id(device.bound_id()));
-                    nodeobject.obj().notify_device();
-                    pwvucontrol_info!("Node {} linked to device {device_name}", nodeobject.obj().name());
-                }
-            }));
-
-            PwvucontrolManager::default().wp_core().install_object_manager(&om);
-
-            // let manager = PwvucontrolManager::default();
-            // manager.device_model().connect_items_changed(clone!(@weak self as nodeobject => move |liststore, _position, _removed, _added| {
-            //     for device in liststore.iter::<PwDeviceObject>().map_while(Result::ok) {
-            //         let node = nodeobject.wpnode.get().expect("node");
-            //         if let Ok(Some(id)) = node.device_id() {
-            //             if id == device.wpdevice().bound_id() {
-            //                 nodeobject.device.set(Some(device.clone()));
-            //             }
-            //         }
-            //     }
-            // }));
-        }
     }
 
     impl PwNodeObject {}
@@ -268,8 +240,8 @@ glib::wrapper! {
     pub struct PwNodeObject(ObjectSubclass<imp::PwNodeObject>);
 }
 
-pub(crate) fn get_node_type_for_node(node: &wp::pw::Node) -> NodeType {
-    match node.get_pw_property("media.class").as_deref() {
+fn node_type_for_media_class(media_class: Option<&str>) -> NodeType {
+    match media_class {
         Some("Stream/Output/Audio") => NodeType::StreamOutput,
         Some("Stream/Input/Audio") => NodeType::StreamInput,
         Some("Audio/Source") => NodeType::Source,
@@ -280,38 +252,103 @@ pub(crate) fn get_node_type_for_node(node: &wp::pw::Node) -> NodeType {
 }
 
 impl PwNodeObject {
-    pub(crate) fn new(node: &wp::pw::Node) -> Self {
-        glib::Object::builder().property("wpnode", node).build()
+    pub(crate) fn new(backend: Rc<dyn NodeBackend>) -> Self {
+        let obj: Self = glib::Object::new();
+        obj.imp().backend.set(backend).unwrap_or_else(|_| unreachable!("backend is only set here"));
+        obj.setup();
+        obj
     }
 
-    fn label_set_name(&self) {
-        let wp_node = self.imp().wpnode.get().expect("Node widget should always have a wp_node");
-        let props = wp_node.global_properties().expect("Node has no properties");
-
-        let name_gstr = match self.nodetype() {
-            NodeType::Sink | NodeType::Source => props
-                .get("node.description")
-                .or_else(|| props.get("node.nick"))
-                .or_else(|| props.get("node.name")),
-            _ => props
-                .get("node.nick")
-                .or_else(|| props.get("node.description"))
-                .or_else(|| props.get("node.name")),
-        };
+    fn setup(&self) {
+        let imp = self.imp();
+        let backend = self.backend();
+
+        let media_class = backend.property("media.class");
+        imp.nodetype.set(node_type_for_media_class(media_class.as_deref()));
+        imp.boundid.set(backend.id());
 
-        let name = name_gstr.as_ref().map(|name| name.as_str()).unwrap_or_default();
+        if media_class.as_deref() == Some("Audio/Source/Virtual") {
+            imp.is_virtual.set(true);
+        }
 
-        self.set_name(name);
+        let obj = self.downgrade();
+        backend.connect_changed(Rc::new(move |change| {
+            let Some(obj) = obj.upgrade() else {
+                return;
+            };
+            match change {
+                NodeChange::Properties => {
+                    obj.label_set_name();
+                    obj.label_set_description();
+                }
+                NodeChange::Props => {
+                    obj.imp().block.set(true);
+                    obj.update_props();
+                    obj.imp().block.set(false);
+                }
+                NodeChange::Format => obj.update_format(),
+                NodeChange::State => obj.set_running(obj.backend().is_running()),
+                NodeChange::Volume => {
+                    obj.imp().block.set(true);
+                    obj.update_volume();
+                    obj.imp().block.set(false);
+                }
+            }
+        }));
+
+        self.label_set_description();
+        self.update_props();
+        self.update_format();
+        self.label_set_name();
+
+        imp.block.set(true);
+        self.update_volume();
+        imp.block.set(false);
+        self.update_icon_name();
+        self.set_running(backend.is_running());
     }
 
-    fn label_set_description(&self) {
-        let wp_node = self.imp().wpnode.get().expect("Node widget should always have a wp_node");
-        let props = wp_node.properties().expect("Node has no properties");
-        let name_gstr = props.get("media.name");
+    pub(crate) fn backend(&self) -> &dyn NodeBackend {
+        self.imp().backend.get().expect("Backend set on PwNodeObject").as_ref()
+    }
 
-        let name = name_gstr.as_ref().map(|name| name.as_str()).unwrap_or_default();
+    pub(crate) fn set_device(&self, device: Option<&PwDeviceObject>) {
+        if let Some(device) = device {
+            pwvucontrol_info!("Node {} linked to device {}", self.name(), device.name());
+        }
+        self.imp().device.replace(device.cloned());
+        self.notify_device();
+    }
 
-        self.set_description(name);
+    fn set_running(&self, running: bool) {
+        if running {
+            self.imp().last_active.set(glib::monotonic_time());
+            self.notify_last_active();
+        }
+        if self.imp().running.replace(running) != running {
+            self.notify_running();
+        }
+    }
+
+    fn label_set_name(&self) {
+        let backend = self.backend();
+
+        let name = match self.nodetype() {
+            NodeType::Sink | NodeType::Source => backend
+                .property("node.description")
+                .or_else(|| backend.property("node.nick"))
+                .or_else(|| backend.property("node.name")),
+            _ => backend
+                .property("node.nick")
+                .or_else(|| backend.property("node.description"))
+                .or_else(|| backend.property("node.name")),
+        };
+
+        self.set_name(name.unwrap_or_default());
+    }
+
+    fn label_set_description(&self) {
+        self.set_description(self.backend().property("media.name").unwrap_or_default());
     }
 
     fn update_icon_name(&self) {
@@ -320,7 +357,7 @@ impl PwNodeObject {
                 self.set_iconname("library-music-symbolic");
                 let icon_props = ["media.icon-name", "window.icon-name", "application.icon-name"];
                 for prop in icon_props {
-                    if let Ok(appid) = self.wpnode().pw_property::<String>(prop) {
+                    if let Some(appid) = self.node_property::<String>(prop) {
                         self.set_iconname(appid);
                     }
                 }
@@ -335,121 +372,55 @@ impl PwNodeObject {
     }
 
     fn update_format(&self) {
-        let node = self.imp().wpnode.get().expect("node");
+        let widget = self.downgrade();
+        self.backend().format(Box::new(move |format| {
+            let (Some(widget), Some(format)) = (widget.upgrade(), format) else {
+                return;
+            };
 
-        node.enum_params(Some("Format"), None, gtk::gio::Cancellable::NONE, clone!(@weak self as widget, @weak node => move |res| {
-            if let Ok(Some(iter)) = res {
+            let t_format = wp::spa::SpaIdTable::from_name("Spa:Enum:AudioFormat").expect("audio format type");
+            let formatname = t_format.values().into_iter().find(|x| x.number() == format.format).and_then(|x| x.short_name()).unwrap_or_default();
 
-                for a in iter {
-                    let pod: wp::spa::SpaPod = a.get().unwrap();
-                    if !pod.is_object() {
-                        continue;
-                    }
+            widget.set_formatstr(format!("{}ch {}Hz {}", format.channels, format.rate, formatname));
 
-                    fn get_pod_maybe_choice(pod: wp::spa::SpaPod) -> wp::spa::SpaPod {
-                        if pod.is_choice() {
-                            pod.choice_child().unwrap()
-                        } else {
-                            pod
-                        }
-                    }
+            widget.set_format(format);
 
-                    let choice = pod.find_spa_property(&wp::spa::ffi::SPA_FORMAT_AUDIO_format).expect("Format!");
-                    let format = get_pod_maybe_choice(choice).id().expect("Format id");
-                    if format == 0 {
-                        pwvucontrol_warning!("Format is 0, ignoring...");
-                        return;
-                    }
-
-                    let channels = match pod.find_spa_property(&wp::spa::ffi::SPA_FORMAT_AUDIO_channels) {
-                        Some(pod) => get_pod_maybe_choice(pod).int().expect("Channels int"),
-                        None => 0
-                    };
-
-                    let rate = match pod.find_spa_property(&wp::spa::ffi::SPA_FORMAT_AUDIO_rate) {
-                        Some(pod) => get_pod_maybe_choice(pod).int().expect("Rate int"),
-                        None => 0
-                    };
-
-                    let choice = pod.find_spa_property(&wp::spa::ffi::SPA_FORMAT_AUDIO_position).expect("Position!");
-                    let positionpod = get_pod_maybe_choice(choice);
-                    let vec: Vec<u32> = positionpod.array_iterator().map(|x: i32| x as u32).collect();
-                    let mut a = [0u32;64];
-                    for (i,v) in (0..).zip(vec.iter()) {
-                        a[i] = *v;
-                    }
-
-                    pwvucontrol_info!("For {} bound id {}, Got rate {rate}, format {format}, channels {channels}", node.name().unwrap_or_default(), node.bound_id());
-
-                    let t_format = wp::spa::SpaIdTable::from_name("Spa:Enum:AudioFormat").expect("audio format type");
-                    let formatname = t_format.values().into_iter().find(|x| x.number() == format).and_then(|x|x.short_name()).unwrap();
-
-                    widget.set_formatstr(format!("{}ch {}Hz {}", channels, rate, formatname));
-
-                    widget.set_format(AudioFormat { positions: a });
-
-                    // Must be done here since EnumFormat is async
-                    widget.update_channelmodel();
-                }
-            } else {
-                pwvucontrol_debug!("enum_params async call didn't return anything useful");
-            }
+            // Must be done here since the format arrives asynchronously
+            widget.update_channelmodel();
         }));
     }
 
     pub(crate) fn update_props(&self) {
-        let node = self.imp().wpnode.get().expect("node");
-
-        let params = node.enum_params_sync("Props", None).expect("getting params");
+        let Some(props) = self.backend().props() else {
+            return;
+        };
 
-        for a in params {
-            let pod: wp::spa::SpaPod = a.get().unwrap();
-            if pod.is_object() {
-                if let Some(val) = pod.find_spa_property(&wp::spa::ffi::SPA_PROP_volume) {
-                    if let Some(volume) = val.float() {
-                        self.set_mainvolume(volume);
-                    }
-                }
+        if let Some(volume) = props.volume {
+            self.set_mainvolume(volume);
+        }
 
-                if let Some(val) = pod.find_spa_property(&wp::spa::ffi::SPA_PROP_monitorVolumes) {
-                    if val.is_array() {
-                        let volume = val.array_iterator::<f32>().max_by(f32::total_cmp);
-                        self.set_monitorvolume(volume.unwrap_or_default());
-                    }
-                }
-            }
+        if !props.monitor_volumes.is_empty() {
+            let volume = props.monitor_volumes.iter().copied().max_by(f32::total_cmp);
+            self.set_monitorvolume(volume.unwrap_or_default());
         }
+
+        self.update_channelmix(&props.params);
     }
 
     fn send_mainvolume(&self) {
-        let podbuilder = SpaPodBuilder::new_object("Spa:Pod:Object:Param:Props", "Props");
-        let node = self.imp().wpnode.get().expect("WpNode set");
-
-        podbuilder.add_property("volume");
-        podbuilder.add_float(self.mainvolume());
-
-        if let Some(pod) = podbuilder.end() {
-            node.set_param("Props", 0, pod);
-        }
+        self.backend().set_main_volume(self.mainvolume());
     }
 
     fn send_monitorvolume(&self) {
-        let podbuilder = SpaPodBuilder::new_object("Spa:Pod:Object:Param:Props", "Props");
-        let node = self.imp().wpnode.get().expect("WpNode set");
-
-        let array = SpaPodBuilder::new_array();
-        let volume = self.monitorvolume();
-        for _ in 0..self.channel_volumes_vec().len() {
-            array.add_float(volume);
-        }
-        if let Some(arraypod) = array.end() {
-            podbuilder.add_property("monitorVolumes");
-            podbuilder.add_pod(&arraypod);
-        }
+        let volumes = vec![self.monitorvolume(); self.channel_volumes_vec().len()];
+        self.backend().set_monitor_volumes(&volumes);
+    }
 
-        if let Some(pod) = podbuilder.end() {
-            node.set_param("Props", 0, pod);
-        }
+    /// Updates node properties at runtime through the `params` struct of the Props param.
+    ///
+    /// Nodes that don't support changing a property while running ignore it.
+    pub(crate) fn set_props_params(&self, params: &[(&str, PropsParamValue)]) {
+        self.backend().set_props_params(params);
     }
 
     pub(crate) fn channel_volumes_vec(&self) -> Vec<f32> {
@@ -459,7 +430,7 @@ impl PwNodeObject {
     pub(crate) fn set_channel_volumes_vec(&self, values: &[f32]) {
         self.set_channel_volumes_vec_no_send(values);
         if !self.imp().block.get() {
-            self.send_volume_using_mixerapi(PropertyChanged::ChannelVolumes);
+            self.send_volume(PropertyChanged::ChannelVolumes);
         }
     }
 
@@ -467,6 +438,7 @@ impl PwNodeObject {
         *(self.imp().channel_volumes.borrow_mut()) = values.to_owned();
 
         self.update_channel_objects();
+        self.update_balance_fade();
     }
 
     pub(crate) fn set_channel_volume(&self, index: u32, volume: f32) {
@@ -475,13 +447,15 @@ impl PwNodeObject {
         }
 
         self.update_channel_objects();
+        self.update_balance_fade();
         if !self.imp().block.get() {
-            self.send_volume_using_mixerapi(PropertyChanged::ChannelVolumes);
+            self.send_volume(PropertyChanged::ChannelVolumes);
         }
     }
 
     fn set_format(&self, format: AudioFormat) {
         self.imp().format.set(Some(format));
+        self.update_balance_fade();
 
         self.emit_by_name::<()>("format", &[]);
     }
@@ -493,29 +467,18 @@ impl PwNodeObject {
     pub(crate) fn set_default_target(&self, target_node: &PwNodeObject) {
         let manager = PwvucontrolManager::default();
 
-        if let Some(metadata) = manager.metadata() {
-            metadata.set(
-                self.boundid(),
-                Some("target.node"),
-                Some("Spa:Id"),
-                Some(&target_node.boundid().to_string()),
-            );
-            metadata.set(
-                self.boundid(),
-                Some("target.object"),
-                Some("Spa:Id"),
-                Some(&target_node.serial().to_string()),
-            );
-        } else {
-            pwvucontrol_warning!("Cannot get metadata object");
-        };
+        manager.set_metadata_value(self.boundid(), "target.node", Some("Spa:Id"), Some(&target_node.boundid().to_string()));
+        match target_node.serial() {
+            Some(serial) => manager.set_metadata_value(self.boundid(), "target.object", Some("Spa:Id"), Some(&serial.to_string())),
+            None => pwvucontrol_warning!("Target {} has no object.serial, only setting target.node", target_node.boundid()),
+        }
     }
 
     pub(crate) fn set_route(&self, routeobj: &PwRouteObject) {
         let index = routeobj.index();
-        if let Ok(Some(card_profile_device)) = self.wpnode().device_index() {
+        if let Some(card_profile_device) = self.backend().card_device() {
             if let Some(device) = self.device() {
-                device.set_route(index, card_profile_device as i32);
+                device.set_route(index, card_profile_device);
 
                 let profiles = routeobj.get_profiles();
                 if !profiles.is_empty() {
@@ -529,52 +492,32 @@ impl PwNodeObject {
 
     pub(crate) fn default_target(&self) -> Option<PwNodeObject> {
         let manager = PwvucontrolManager::default();
+        let nodes = || manager.node_model().iter::<PwNodeObject>().map_while(Result::ok);
 
-        let om = manager.imp().wp_object_manager.get().unwrap();
-        if let Some(metadata) = manager.metadata() {
-            if let Some(target_serial) = metadata.find_notype(self.boundid(), "target.object") {
-                if target_serial != "-1" {
-                    if let Some(sinknode) = om.lookup(
-                        [Constraint::compare(
-                            ConstraintType::PwProperty,
-                            "object.serial",
-                            target_serial.as_str(),
-                            true,
-                        )]
-                        .iter()
-                        .collect::<Interest<wp::pw::Node>>(),
-                    ) {
-                        return manager.get_node_by_id(sinknode.bound_id());
-                    };
+        if let Some(target_serial) = manager.metadata_value(self.boundid(), "target.object") {
+            if target_serial != "-1" {
+                // Nodes without a serial yet can't be the target.
+                if let Some(node) = nodes().find(|node| node.serial().is_some_and(|serial| serial.to_string() == target_serial)) {
+                    return Some(node);
                 }
             }
+        }
 
-            if let Some(target_node) = metadata.find_notype(self.boundid(), "target.node") {
-                if target_node != "-1" {
-                    if let Some(sinknode) = om.lookup(
-                        [Constraint::compare(ConstraintType::PwProperty, "object.id", target_node.as_str(), true)]
-                            .iter()
-                            .collect::<Interest<wp::pw::Node>>(),
-                    ) {
-                        return manager.get_node_by_id(sinknode.bound_id());
-                    };
+        if let Some(target_node) = manager.metadata_value(self.boundid(), "target.node") {
+            if target_node != "-1" {
+                if let Some(node) = nodes().find(|node| node.boundid().to_string() == target_node) {
+                    return Some(node);
                 }
             }
-        } else {
-            pwvucontrol_warning!("Cannot get metadata object");
-        };
+        }
         None
     }
 
     pub(crate) fn unset_default_target(&self) {
         let manager = PwvucontrolManager::default();
 
-        if let Some(metadata) = manager.metadata() {
-            metadata.set(self.boundid(), Some("target.node"), Some("Spa:Id"), Some("-1"));
-            metadata.set(self.boundid(), Some("target.object"), Some("Spa:Id"), Some("-1"));
-        } else {
-            pwvucontrol_warning!("Cannot get metadata object");
-        };
+        manager.set_metadata_value(self.boundid(), "target.node", Some("Spa:Id"), Some("-1"));
+        manager.set_metadata_value(self.boundid(), "target.object", Some("Spa:Id"), Some("-1"));
     }
 
     fn update_channelmodel(&self) {
@@ -595,34 +538,22 @@ impl PwNodeObject {
         }
     }
 
-    pub(crate) fn serial(&self) -> u32 {
-        let node = self.imp().wpnode.get().expect("node");
-        let serial: i32 = node.pw_property("object.serial").expect("object.serial");
-
-        serial as u32
+    /// `None` with servers from before serials and until the properties have arrived.
+    pub(crate) fn serial(&self) -> Option<u32> {
+        self.node_property::<u32>("object.serial")
     }
 
-    pub(crate) fn node_property<T: FromPipewirePropertyString>(&self, property: &str) -> Option<T> {
-        let node = self.imp().wpnode.get().expect("node");
-        node.pw_property(property).ok()
+    /// Name of the application owning this stream, falling back to the node name.
+    pub(crate) fn application_name(&self) -> String {
+        self.node_property::<String>("application.name").unwrap_or_else(|| self.name())
     }
-}
 
-trait MetadataExtFix: 'static {
-    fn find_notype(&self, subject: u32, key: &str) -> Option<glib::GString>;
-}
+    /// Name of the node this stream is explicitly routed to, or an empty string when following the default.
+    pub(crate) fn target_name(&self) -> String {
+        self.default_target().map(|target| target.name()).unwrap_or_default()
+    }
 
-impl<O: IsA<wp::pw::Metadata>> MetadataExtFix for O {
-    fn find_notype(&self, subject: u32, key: &str) -> Option<glib::GString> {
-        use glib::translate::ToGlibPtr;
-        unsafe {
-            let mut type_ = std::ptr::null();
-            glib::translate::from_glib_none(wp::ffi::wp_metadata_find(
-                self.as_ref().to_glib_none().0,
-                subject,
-                ToGlibPtr::to_glib_none(&key).0,
-                &mut type_,
-            ))
-        }
+    pub(crate) fn node_property<T: FromStr>(&self, property: &str) -> Option<T> {
+        self.backend().property(property)?.parse().ok()
     }
 }
@@ -0,0 +1,118 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use wireplumber as wp;
+
+use super::*;
+
+const LEFT: &[&str] = &["FL", "RL", "SL", "FLC", "RLC", "TFL", "TRL", "TSL", "FLW", "FLH"];
+const RIGHT: &[&str] = &["FR", "RR", "SR", "FRC", "RRC", "TFR", "TRR", "TSR", "FRW", "FRH"];
+const FRONT: &[&str] = &["FL", "FR", "FC", "FLC", "FRC", "TFL", "TFR", "TFC", "FLW", "FRW", "FLH", "FCH", "FRH"];
+const REAR: &[&str] = &["RL", "RR", "RC", "RLC", "RRC", "TRL", "TRR", "TRC"];
+
+/// Which side of an axis a channel is on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Side {
+    First,
+    Second,
+    Neither,
+}
+
+impl PwNodeObject {
+    fn channel_sides(&self, first: &[&str], second: &[&str]) -> Vec<Side> {
+        let t_audiochannel = wp::spa::SpaIdTable::from_name("Spa:Enum:AudioChannel").expect("audio channel type");
+
+        self.channel_positions()
+            .iter()
+            .map(|position| {
+                let name = t_audiochannel.find_value(*position).and_then(|value| value.short_name());
+                match name.as_deref() {
+                    Some(name) if first.contains(&name) => Side::First,
+                    Some(name) if second.contains(&name) => Side::Second,
+                    _ => Side::Neither,
+                }
+            })
+            .collect()
+    }
+
+    /// Position between -1.0 (only the first side) and 1.0 (only the second side), as pulseaudio computes it.
+    fn axis_position(volumes: &[f32], sides: &[Side]) -> f32 {
+        let side_max = |side| volumes.iter().zip(sides).filter(|(_, s)| **s == side).map(|(v, _)| *v).fold(0.0, f32::max);
+        let (first, second) = (side_max(Side::First), side_max(Side::Second));
+
+        if first == second {
+            0.0
+        } else if first > second {
+            -1.0 + second / first
+        } else {
+            1.0 - first / second
+        }
+    }
+
+    /// Scales the channels on each side so the louder side keeps its level.
+    fn set_axis_position(volumes: &[f32], sides: &[Side], position: f32) -> Vec<f32> {
+        let side_max = |side| volumes.iter().zip(sides).filter(|(_, s)| **s == side).map(|(v, _)| *v).fold(0.0, f32::max);
+        let (first, second) = (side_max(Side::First), side_max(Side::Second));
+        let max = first.max(second);
+
+        let position = position.clamp(-1.0, 1.0);
+        let (new_first, new_second) = if position <= 0.0 { (max, max * (1.0 + position)) } else { (max * (1.0 - position), max) };
+
+        volumes
+            .iter()
+            .zip(sides)
+            .map(|(volume, side)| {
+                let (old, new) = match side {
+                    Side::First => (first, new_first),
+                    Side::Second => (second, new_second),
+                    Side::Neither => return *volume,
+                };
+                if old > 0.0 {
+                    volume * new / old
+                } else {
+                    new
+                }
+            })
+            .collect()
+    }
+
+    fn has_both_sides(sides: &[Side]) -> bool {
+        sides.contains(&Side::First) && sides.contains(&Side::Second)
+    }
+
+    /// Recomputes the balance and fade from the channel volumes after they or the channel map changed.
+    pub(super) fn update_balance_fade(&self) {
+        let imp = self.imp();
+        let volumes = self.channel_volumes_vec();
+
+        let balance_sides = self.channel_sides(LEFT, RIGHT);
+        let fade_sides = self.channel_sides(REAR, FRONT);
+
+        let has_balance = Self::has_both_sides(&balance_sides);
+        if imp.has_balance.replace(has_balance) != has_balance {
+            self.notify_has_balance();
+        }
+        let has_fade = Self::has_both_sides(&fade_sides);
+        if imp.has_fade.replace(has_fade) != has_fade {
+            self.notify_has_fade();
+        }
+
+        let balance = Self::axis_position(&volumes, &balance_sides);
+        if (imp.balance.replace(balance) - balance).abs() > f32::EPSILON {
+            self.notify_balance();
+        }
+        let fade = Self::axis_position(&volumes, &fade_sides);
+        if (imp.fade.replace(fade) - fade).abs() > f32::EPSILON {
+            self.notify_fade();
+        }
+    }
+
+    pub(super) fn send_balance(&self) {
+        let volumes = Self::set_axis_position(&self.channel_volumes_vec(), &self.channel_sides(LEFT, RIGHT), self.balance());
+        self.set_channel_volumes_vec(&volumes);
+    }
+
+    pub(super) fn send_fade(&self) {
+        let volumes = Self::set_axis_position(&self.channel_volumes_vec(), &self.channel_sides(REAR, FRONT), self.fade());
+        self.set_channel_volumes_vec(&volumes);
+    }
+}
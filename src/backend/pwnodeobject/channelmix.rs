@@ -0,0 +1,60 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use super::*;
+
+impl PwNodeObject {
+    /// Reads the adapter's channelmix settings from the `params` struct of the Props param.
+    pub(super) fn update_channelmix(&self, params: &[(String, PropsParamValue)]) {
+        let imp = self.imp();
+        let was_blocked = imp.block.replace(true);
+
+        for (key, value) in params {
+            match (key.as_str(), value) {
+                ("channelmix.upmix", PropsParamValue::Bool(upmix)) => self.set_channelmix_upmix(*upmix),
+                ("channelmix.normalize", PropsParamValue::Bool(normalize)) => self.set_channelmix_normalize(*normalize),
+                ("channelmix.lfe-cutoff", PropsParamValue::Float(cutoff)) => self.set_channelmix_lfe_cutoff(*cutoff),
+                _ => {}
+            }
+        }
+
+        imp.block.set(was_blocked);
+    }
+
+    /// Sends the setting behind the GObject property `property` alone, so the others keep what
+    /// was configured elsewhere in the meantime.
+    pub(super) fn send_channelmix(&self, property: &str) {
+        let param = match property {
+            "channelmix-upmix" => ("channelmix.upmix", PropsParamValue::Bool(self.channelmix_upmix())),
+            "channelmix-normalize" => ("channelmix.normalize", PropsParamValue::Bool(self.channelmix_normalize())),
+            "channelmix-lfe-cutoff" => ("channelmix.lfe-cutoff", PropsParamValue::Float(self.channelmix_lfe_cutoff())),
+            _ => return,
+        };
+        self.set_props_params(&[param]);
+    }
+
+    /// Channel positions of the negotiated format, as `Spa:Enum:AudioChannel` ids.
+    pub(crate) fn channel_positions(&self) -> Vec<u32> {
+        let Some(format) = self.format() else {
+            return Vec::new();
+        };
+        format.positions.iter().take(format.channels as usize).copied().collect()
+    }
+
+    /// Reconfigures the adapter ports of a sink or source with a new channel map.
+    ///
+    /// The ports are recreated, so the session manager relinks the streams afterwards.
+    pub(crate) fn set_channel_positions(&self, positions: &[u32]) {
+        // The ports of a sink take input, the ones of a source produce output.
+        let direction = match self.nodetype() {
+            NodeType::Sink => RouteDirection::Input,
+            NodeType::Source => RouteDirection::Output,
+            _ => {
+                pwvucontrol_warning!("Only sinks and sources have a configurable channel map");
+                return;
+            }
+        };
+        let rate = self.format().map(|format| format.rate).unwrap_or(48000);
+
+        self.backend().set_port_config(direction, rate, positions);
+    }
+}
@@ -0,0 +1,40 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use super::*;
+
+/// Sample rates and formats a node accepts, according to its EnumFormat params.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SupportedFormats {
+    pub rates: Vec<u32>,
+    /// Short names from `Spa:Enum:AudioFormat`, such as `S16LE` or `F32LE`.
+    pub formats: Vec<String>,
+}
+
+impl PwNodeObject {
+    /// Whether this node is an ALSA PCM, the only kind of device whose format can be picked.
+    pub(crate) fn is_alsa(&self) -> bool {
+        self.node_property::<String>("device.api").as_deref() == Some("alsa") || self.node_property::<String>("api.alsa.path").is_some()
+    }
+
+    pub(crate) fn supported_formats(&self) -> SupportedFormats {
+        self.backend().supported_formats()
+    }
+
+    /// Asks the node to use `rate` and `format` and suspends it so the new format is negotiated
+    /// the next time it starts. `None` leaves the current choice alone.
+    pub(crate) fn set_preferred_format(&self, rate: Option<u32>, format: Option<&str>) {
+        let mut params = Vec::new();
+        if let Some(rate) = rate {
+            params.push(("audio.rate", PropsParamValue::Int(rate as i32)));
+        }
+        if let Some(format) = format {
+            params.push(("audio.format", PropsParamValue::String(format.to_string())));
+        }
+        if params.is_empty() {
+            return;
+        }
+
+        self.set_props_params(&params);
+        self.backend().suspend();
+    }
+}
@@ -0,0 +1,156 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::time::Duration;
+
+use glib::{ControlFlow, SourceId};
+
+use super::*;
+
+const RAMP_STEP: Duration = Duration::from_millis(10);
+
+/// Changes of at least this much on any channel are ramped, smaller ones are applied at once.
+const RAMP_THRESHOLD: f32 = 0.05;
+
+/// Reported volumes this close to a step we sent are taken to be that step.
+const STEP_TOLERANCE: f32 = 0.001;
+
+/// An in-progress volume fade issued as a sequence of mixer-api `set-volume` calls.
+pub(crate) struct VolumeRamp {
+    source: SourceId,
+    from: Vec<f32>,
+    to: Vec<f32>,
+    start: i64,
+    duration: i64,
+    /// Mute state to send together with the last step.
+    mute_after: Option<bool>,
+}
+
+impl VolumeRamp {
+    /// Interpolates in the cubic domain so the fade sounds even rather than rushing at the low end.
+    fn volumes_at(&self, now: i64) -> Vec<f32> {
+        let t = ((now - self.start) as f32 / self.duration as f32).clamp(0.0, 1.0);
+        self.from
+            .iter()
+            .zip(self.to.iter())
+            .map(|(from, to)| {
+                let (from, to) = (from.cbrt(), to.cbrt());
+                (from + (to - from) * t).powi(3)
+            })
+            .collect()
+    }
+}
+
+impl PwNodeObject {
+    /// Starts ramping to `target` if ramping is enabled and the change is large enough.
+    ///
+    /// Returns false when the caller should set the volume directly.
+    pub(super) fn ramp_volumes_to(&self, target: &[f32]) -> bool {
+        let Some(duration) = PwvucontrolManager::default().volume_ramp_duration() else {
+            return false;
+        };
+
+        let from = self.current_sent_volumes();
+        let large = from.len() == target.len() && from.iter().zip(target).any(|(a, b)| (a - b).abs() >= RAMP_THRESHOLD);
+        if !large {
+            self.stop_ramp();
+            return false;
+        }
+
+        self.start_ramp(from, target.to_vec(), duration, None);
+        true
+    }
+
+    /// Fades out before muting or in after unmuting. Returns false when ramping is off.
+    pub(super) fn ramp_mute(&self, mute: bool) -> bool {
+        let Some(duration) = PwvucontrolManager::default().volume_ramp_duration() else {
+            return false;
+        };
+
+        let volumes = self.channel_volumes_vec();
+        let current = self.current_sent_volumes();
+        if current.len() != volumes.len() {
+            return false;
+        }
+
+        let silence = vec![0.0; volumes.len()];
+        if mute {
+            self.start_ramp(current, silence, duration, Some(true));
+        } else if self.imp().ramp.borrow().is_some() {
+            // Still fading out, so the node is not muted yet and can turn around from where it is.
+            self.start_ramp(current, volumes, duration, None);
+        } else {
+            self.send_volumes(&silence, Some(false));
+            self.start_ramp(silence, volumes, duration, None);
+        }
+        true
+    }
+
+    fn start_ramp(&self, from: Vec<f32>, to: Vec<f32>, duration: i64, mute_after: Option<bool>) {
+        self.stop_ramp();
+
+        let source = glib::timeout_add_local(
+            RAMP_STEP,
+            clone!(@weak self as nodeobject => @default-return ControlFlow::Break, move || nodeobject.ramp_step()),
+        );
+
+        self.imp().ramp.replace(Some(VolumeRamp { source, from, to, start: glib::monotonic_time(), duration, mute_after }));
+    }
+
+    fn ramp_step(&self) -> ControlFlow {
+        let now = glib::monotonic_time();
+
+        let (volumes, finished, mute_after) = {
+            let ramp = self.imp().ramp.borrow();
+            let Some(ramp) = ramp.as_ref() else {
+                return ControlFlow::Break;
+            };
+            let finished = now - ramp.start >= ramp.duration;
+            (ramp.volumes_at(now), finished, ramp.mute_after)
+        };
+
+        if !finished {
+            self.send_volumes(&volumes, None);
+            return ControlFlow::Continue;
+        }
+
+        // The source is removed by returning Break, so just forget the ramp.
+        self.imp().ramp.take();
+        match mute_after {
+            // Restore the volume while muted so unmuting brings it back.
+            Some(true) => self.send_volumes(&self.channel_volumes_vec(), Some(true)),
+            _ => self.send_volumes(&volumes, None),
+        }
+        ControlFlow::Break
+    }
+
+    /// Whether `volumes` reported by the server are one of the steps of the running ramp, that is
+    /// between where it started and the step sent last, as reports can lag behind a step or two.
+    pub(super) fn is_ramp_step(&self, volumes: &[f32]) -> bool {
+        let imp = self.imp();
+        let ramp = imp.ramp.borrow();
+        let Some(ramp) = ramp.as_ref() else {
+            return false;
+        };
+        let sent = imp.sent_volumes.borrow();
+        volumes.len() == sent.len()
+            && ramp.from.len() == sent.len()
+            && volumes
+                .iter()
+                .zip(ramp.from.iter().zip(sent.iter()))
+                .all(|(volume, (from, sent))| (from.min(*sent) - STEP_TOLERANCE..=from.max(*sent) + STEP_TOLERANCE).contains(volume))
+    }
+
+    pub(super) fn stop_ramp(&self) {
+        if let Some(ramp) = self.imp().ramp.take() {
+            ramp.source.remove();
+        }
+    }
+
+    /// Volumes last sent to the server, or the point a running ramp has reached.
+    fn current_sent_volumes(&self) -> Vec<f32> {
+        if let Some(ramp) = self.imp().ramp.borrow().as_ref() {
+            return ramp.volumes_at(glib::monotonic_time());
+        }
+        self.imp().sent_volumes.borrow().clone()
+    }
+}
@@ -0,0 +1,99 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use super::*;
+
+impl PwNodeObject {
+    pub(crate) fn send_volume(&self, what: PropertyChanged) {
+        let backend = self.backend();
+        if backend.volume().is_none() {
+            pwvucontrol_warning!("Node {} does not support volume", backend.id());
+            return;
+        }
+
+        let positions = self.channel_positions();
+        match what {
+            PropertyChanged::Mute => {
+                if !self.mute() {
+                    self.release_unplug_mute();
+                }
+                if self.ramp_mute(self.mute()) {
+                    return;
+                }
+                backend.set_volume(None, &positions, Some(self.mute()));
+            }
+            PropertyChanged::Volume => {
+                let mut channel_volumes = self.channel_volumes_vec();
+                let max = self.volume();
+                let t = *channel_volumes.iter().max_by(|a, b| a.total_cmp(b)).expect("Max");
+                if t > 0.0 {
+                    for v in channel_volumes.iter_mut() {
+                        *v = *v * max / t;
+                    }
+                } else {
+                    for v in channel_volumes.iter_mut() {
+                        *v = max;
+                    }
+                }
+                self.set_channel_volumes_vec_no_send(&channel_volumes);
+                if self.ramp_volumes_to(&channel_volumes) {
+                    return;
+                }
+                backend.set_volume(Some(&channel_volumes), &positions, None);
+                self.imp().sent_volumes.replace(channel_volumes);
+            }
+            PropertyChanged::ChannelVolumes => {
+                let channel_volumes = self.channel_volumes_vec();
+                if self.ramp_volumes_to(&channel_volumes) {
+                    return;
+                }
+                backend.set_volume(Some(&channel_volumes), &positions, None);
+                self.imp().sent_volumes.replace(channel_volumes);
+            }
+        }
+    }
+
+    /// Unmuting by hand ends the mute kept on the route after headphones were unplugged.
+    fn release_unplug_mute(&self) {
+        if let (Some(card_device), Some(device)) = (self.backend().card_device(), self.device()) {
+            device.release_unplug_mute(card_device);
+        }
+    }
+
+    /// Sets the channel volumes, and optionally the mute state, without touching the properties.
+    pub(super) fn send_volumes(&self, channel_volumes: &[f32], mute: Option<bool>) {
+        self.imp().sent_volumes.replace(channel_volumes.to_vec());
+        self.backend().set_volume(Some(channel_volumes), &self.channel_positions(), mute);
+    }
+
+    pub(crate) fn update_volume(&self) {
+        let Some(volume) = self.backend().volume() else {
+            return;
+        };
+
+        // The changes reported while ramping are mostly our own steps, the properties already hold the target.
+        // Anything else was set elsewhere and wins over the ramp.
+        if self.imp().ramp.borrow().is_some() {
+            if self.is_ramp_step(&volume.channel_volumes) {
+                return;
+            }
+            pwvucontrol_debug!("Volume of {} changed elsewhere, stopping the ramp", self.boundid());
+            self.stop_ramp();
+        }
+
+        if !volume.channel_volumes.is_empty() {
+            self.imp().sent_volumes.replace(volume.channel_volumes.clone());
+            self.set_channel_volumes_vec(&volume.channel_volumes);
+        }
+
+        // Wireplumber's mixerapi always sets volume to first channel volume
+        // instead we will use the max channel
+        let maxvol: Option<f32> = self.channel_volumes_vec().iter().max_by(|a, b| a.total_cmp(b)).copied();
+
+        if let Some(maxvol) = maxvol {
+            self.set_volume(maxvol);
+        }
+
+        self.set_mute(volume.mute);
+        pwvucontrol_debug!("Setting mute to {:?}", volume.mute);
+    }
+}
@@ -0,0 +1,162 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use super::{PwDeviceObject, PwNodeObject};
+use glib::{clone, Properties};
+use gtk::{gio, prelude::*, subclass::prelude::*};
+use std::cell::{Cell, RefCell};
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, Properties, Default)]
+    #[properties(wrapper_type = super::PwNodeSearchFilter)]
+    pub struct PwNodeSearchFilter {
+        /// Text matched against name, description, application.name and media.name.
+        #[property(get, set = Self::set_search_text)]
+        pub(super) search_text: RefCell<String>,
+
+        /// Only let through nodes that are running or not muted, hiding the idle muted ones.
+        #[property(get, set = Self::set_active_only)]
+        pub(super) active_only: Cell<bool>,
+
+        /// The model watched for running and mute changes, with its `items-changed` handler.
+        pub(super) watched_model: RefCell<Option<(gio::ListModel, glib::SignalHandlerId)>>,
+        /// Running and mute handlers of every node of the watched model, in model order.
+        pub(super) node_handlers: RefCell<Vec<(PwNodeObject, [glib::SignalHandlerId; 2])>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for PwNodeSearchFilter {
+        const NAME: &'static str = "PwNodeSearchFilter";
+        type Type = super::PwNodeSearchFilter;
+        type ParentType = gtk::Filter;
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for PwNodeSearchFilter {
+        fn dispose(&self) {
+            self.obj().unwatch_model();
+        }
+    }
+
+    impl FilterImpl for PwNodeSearchFilter {
+        fn strictness(&self) -> gtk::FilterMatch {
+            if self.search_text.borrow().is_empty() && !self.active_only.get() {
+                gtk::FilterMatch::All
+            } else {
+                gtk::FilterMatch::Some
+            }
+        }
+
+        fn match_(&self, item: &glib::Object) -> bool {
+            if let Some(node) = item.downcast_ref::<PwNodeObject>() {
+                if self.active_only.get() && !node.running() && node.mute() {
+                    return false;
+                }
+
+                let search_text = self.search_text.borrow().to_lowercase();
+                if search_text.is_empty() {
+                    return true;
+                }
+
+                let haystack = [
+                    Some(node.name()),
+                    Some(node.description()),
+                    node.node_property::<String>("application.name"),
+                    node.node_property::<String>("media.name"),
+                ];
+                haystack.iter().flatten().any(|x| x.to_lowercase().contains(&search_text))
+            } else if let Some(device) = item.downcast_ref::<PwDeviceObject>() {
+                let search_text = self.search_text.borrow().to_lowercase();
+                device.name().unwrap_or_default().to_lowercase().contains(&search_text)
+            } else {
+                true
+            }
+        }
+    }
+
+    impl PwNodeSearchFilter {
+        fn set_search_text(&self, text: String) {
+            let old = self.search_text.replace(text.clone());
+            if old == text {
+                return;
+            }
+
+            let change = if text.contains(old.as_str()) {
+                gtk::FilterChange::MoreStrict
+            } else if old.contains(text.as_str()) {
+                gtk::FilterChange::LessStrict
+            } else {
+                gtk::FilterChange::Different
+            };
+            self.obj().changed(change);
+        }
+
+        fn set_active_only(&self, value: bool) {
+            if self.active_only.replace(value) == value {
+                return;
+            }
+
+            self.obj().changed(if value { gtk::FilterChange::MoreStrict } else { gtk::FilterChange::LessStrict });
+        }
+    }
+}
+
+glib::wrapper! {
+    /// Filter layered on top of [`super::PwNodeFilterModel`] for the search bar.
+    pub struct PwNodeSearchFilter(ObjectSubclass<imp::PwNodeSearchFilter>) @extends gtk::Filter;
+}
+
+impl PwNodeSearchFilter {
+    pub(crate) fn new() -> Self {
+        glib::Object::builder().build()
+    }
+
+    /// Re-run the filter whenever the running or mute state of a node in `model` changes,
+    /// since the filter list model does not watch item properties on its own.
+    pub(crate) fn watch_model(&self, model: &impl IsA<gio::ListModel>) {
+        self.unwatch_model();
+
+        let nodes: Vec<PwNodeObject> = model.iter::<PwNodeObject>().map_while(Result::ok).collect();
+        self.imp().node_handlers.replace(nodes.iter().map(|node| self.watch_node(node)).collect());
+
+        let handler = model.connect_items_changed(clone!(@weak self as filter => move |model, position, removed, added| {
+            let added: Vec<_> = (position..position + added)
+                .filter_map(|i| model.item(i).and_downcast::<PwNodeObject>())
+                .map(|node| filter.watch_node(&node))
+                .collect();
+            let mut handlers = filter.imp().node_handlers.borrow_mut();
+            let start = (position as usize).min(handlers.len());
+            let end = (start + removed as usize).min(handlers.len());
+            for (node, ids) in handlers.splice(start..end, added) {
+                ids.into_iter().for_each(|id| node.disconnect(id));
+            }
+        }));
+        self.imp().watched_model.replace(Some((model.clone().upcast(), handler)));
+    }
+
+    fn unwatch_model(&self) {
+        let imp = self.imp();
+        if let Some((model, handler)) = imp.watched_model.take() {
+            model.disconnect(handler);
+        }
+        for (node, ids) in imp.node_handlers.take() {
+            ids.into_iter().for_each(|id| node.disconnect(id));
+        }
+    }
+
+    fn watch_node(&self, node: &PwNodeObject) -> (PwNodeObject, [glib::SignalHandlerId; 2]) {
+        let handler = clone!(@weak self as filter => move |_: &PwNodeObject| {
+            if filter.active_only() {
+                filter.changed(gtk::FilterChange::Different);
+            }
+        });
+        (node.clone(), [node.connect_running_notify(handler.clone()), node.connect_mute_notify(handler)])
+    }
+}
+
+impl Default for PwNodeSearchFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
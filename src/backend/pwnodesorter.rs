@@ -0,0 +1,152 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use super::{PwNodeGroupObject, PwNodeObject};
+use glib::{clone, Properties};
+use gtk::{gio, prelude::*, subclass::prelude::*};
+use std::cell::Cell;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default, glib::Enum)]
+#[enum_type(name = "NodeSortMode")]
+pub enum NodeSortMode {
+    /// Keep the order nodes were added in.
+    #[default]
+    #[enum_value(nick = "none")]
+    None,
+    #[enum_value(nick = "name")]
+    Name,
+    #[enum_value(nick = "application")]
+    Application,
+    #[enum_value(nick = "target")]
+    Target,
+    #[enum_value(nick = "activity")]
+    Activity,
+}
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, Properties, Default)]
+    #[properties(wrapper_type = super::PwNodeSorter)]
+    pub struct PwNodeSorter {
+        #[property(get, set = Self::set_sort_mode, builder(NodeSortMode::None))]
+        pub(super) sort_mode: Cell<NodeSortMode>,
+
+        /// Keep nodes with the same group key together, ahead of the sort mode.
+        #[property(get, set = Self::set_grouped)]
+        pub(super) grouped: Cell<bool>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for PwNodeSorter {
+        const NAME: &'static str = "PwNodeSorter";
+        type Type = super::PwNodeSorter;
+        type ParentType = gtk::Sorter;
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for PwNodeSorter {}
+
+    impl SorterImpl for PwNodeSorter {
+        fn order(&self) -> gtk::SorterOrder {
+            match self.sort_mode.get() {
+                NodeSortMode::None if !self.grouped.get() => gtk::SorterOrder::None,
+                _ => gtk::SorterOrder::Partial,
+            }
+        }
+
+        fn compare(&self, item1: &glib::Object, item2: &glib::Object) -> gtk::Ordering {
+            let (Some(node1), Some(node2)) = (item1.downcast_ref::<PwNodeObject>(), item2.downcast_ref::<PwNodeObject>()) else {
+                return gtk::Ordering::Equal;
+            };
+
+            let obj = self.obj();
+            if self.grouped.get() {
+                let ordering = compare_strings(&obj.group_key(node1), &obj.group_key(node2));
+                if ordering.is_ne() {
+                    return ordering.into();
+                }
+            }
+
+            let ordering = match self.sort_mode.get() {
+                NodeSortMode::None => std::cmp::Ordering::Equal,
+                NodeSortMode::Name => compare_strings(&node1.name(), &node2.name()),
+                NodeSortMode::Application => compare_strings(&node1.application_name(), &node2.application_name())
+                    .then_with(|| compare_strings(&node1.name(), &node2.name())),
+                NodeSortMode::Target => compare_strings(&node1.target_name(), &node2.target_name())
+                    .then_with(|| compare_strings(&node1.name(), &node2.name())),
+                // Running nodes first, then the ones that were running most recently.
+                NodeSortMode::Activity => {
+                    node2.running().cmp(&node1.running()).then_with(|| node2.last_active().cmp(&node1.last_active()))
+                }
+            };
+
+            ordering.into()
+        }
+    }
+
+    impl PwNodeSorter {
+        fn set_grouped(&self, grouped: bool) {
+            if self.grouped.replace(grouped) != grouped {
+                self.obj().changed(gtk::SorterChange::Different);
+            }
+        }
+
+        fn set_sort_mode(&self, mode: NodeSortMode) {
+            if self.sort_mode.replace(mode) != mode {
+                self.obj().changed(gtk::SorterChange::Different);
+            }
+        }
+    }
+
+    fn compare_strings(a: &str, b: &str) -> std::cmp::Ordering {
+        a.to_lowercase().cmp(&b.to_lowercase())
+    }
+}
+
+glib::wrapper! {
+    /// Sorter for the stream lists, switchable between the [`NodeSortMode`]s.
+    pub struct PwNodeSorter(ObjectSubclass<imp::PwNodeSorter>) @extends gtk::Sorter;
+}
+
+impl PwNodeSorter {
+    pub(crate) fn new() -> Self {
+        glib::Object::builder().build()
+    }
+
+    /// Key used to group streams, the target when sorting by target and the application otherwise.
+    pub(crate) fn group_key(&self, node: &PwNodeObject) -> String {
+        match self.sort_mode() {
+            NodeSortMode::Target => node.target_name(),
+            _ => PwNodeGroupObject::application_key(node),
+        }
+    }
+
+    /// Re-sort when a node in `model` starts or stops running while sorting by activity.
+    pub(crate) fn watch_model(&self, model: &impl IsA<gio::ListModel>) {
+        let watch_node = clone!(@weak self as sorter => move |node: &PwNodeObject| {
+            node.connect_running_notify(clone!(@weak sorter => move |_| {
+                if sorter.sort_mode() == NodeSortMode::Activity {
+                    sorter.changed(gtk::SorterChange::Different);
+                }
+            }));
+        });
+
+        for node in model.iter::<PwNodeObject>().map_while(Result::ok) {
+            watch_node(&node);
+        }
+
+        model.connect_items_changed(move |model, position, _removed, added| {
+            for i in position..position + added {
+                if let Some(node) = model.item(i).and_downcast::<PwNodeObject>() {
+                    watch_node(&node);
+                }
+            }
+        });
+    }
+}
+
+impl Default for PwNodeSorter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,86 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use super::{NodeProfile, PwNodeObject};
+use gtk::{
+    glib::{self, Properties},
+    prelude::*,
+    subclass::prelude::*,
+};
+use std::cell::{Cell, RefCell};
+
+mod imp {
+    use super::*;
+
+    /// One node of the Performance tab, updated in place from the profiler every refresh.
+    #[derive(Default, Properties)]
+    #[properties(wrapper_type = super::PwPerformanceObject)]
+    pub struct PwPerformanceObject {
+        #[property(get, set)]
+        id: Cell<u32>,
+        /// Name of the matching node row, or the node name the profiler reports for nodes not listed.
+        #[property(get, set)]
+        name: RefCell<String>,
+        #[property(get, set)]
+        driver_name: RefCell<String>,
+        #[property(get, set)]
+        is_driver: Cell<bool>,
+        #[property(get, set)]
+        quantum: Cell<i64>,
+        #[property(get, set)]
+        rate: Cell<u32>,
+        /// DSP load of the driver this node follows, from 0 to 1.
+        #[property(get, set)]
+        dsp_load: Cell<f32>,
+        /// Time waiting for and spent processing the last cycle, in nanoseconds.
+        #[property(get, set)]
+        wait: Cell<i64>,
+        #[property(get, set)]
+        busy: Cell<i64>,
+        /// Wait and busy time as a share of the driver's period, from 0 to 1.
+        #[property(get, set)]
+        wait_quantum: Cell<f32>,
+        #[property(get, set)]
+        busy_quantum: Cell<f32>,
+        #[property(get, set)]
+        xruns: Cell<i32>,
+        /// The sink, source or stream this is about, `None` for nodes the models don't list.
+        #[property(get, set, nullable)]
+        node: RefCell<Option<PwNodeObject>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for PwPerformanceObject {
+        const NAME: &'static str = "PwPerformanceObject";
+        type Type = super::PwPerformanceObject;
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for PwPerformanceObject {}
+}
+
+glib::wrapper! {
+    pub struct PwPerformanceObject(ObjectSubclass<imp::PwPerformanceObject>);
+}
+
+impl PwPerformanceObject {
+    pub(crate) fn new(profile: &NodeProfile, node: Option<&PwNodeObject>) -> Self {
+        let obj: Self = glib::Object::builder().property("id", profile.id).build();
+        obj.update(profile, node);
+        obj
+    }
+
+    pub(crate) fn update(&self, profile: &NodeProfile, node: Option<&PwNodeObject>) {
+        self.set_name(node.map(PwNodeObject::name).unwrap_or_else(|| profile.name.clone()));
+        self.set_driver_name(profile.driver_name.as_str());
+        self.set_is_driver(profile.is_driver());
+        self.set_quantum(profile.quantum);
+        self.set_rate(profile.rate);
+        self.set_dsp_load(profile.dsp_load);
+        self.set_wait(profile.wait);
+        self.set_busy(profile.busy);
+        self.set_wait_quantum(profile.wait_quantum);
+        self.set_busy_quantum(profile.busy_quantum);
+        self.set_xruns(profile.xruns);
+        self.set_node(node);
+    }
+}
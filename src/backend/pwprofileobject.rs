@@ -18,6 +18,9 @@ mod imp {
     pub struct PwProfileObject {
         #[property(get, set)]
         index: Cell<u32>,
+        /// Short name such as `a2dp-sink-aac`, empty if the device doesn't report one.
+        #[property(get, set)]
+        name: RefCell<String>,
         #[property(get, set)]
         description: RefCell<String>,
         #[property(get, set, builder(ParamAvailability::Unknown))]
@@ -41,11 +44,12 @@ glib::wrapper! {
 }
 
 impl PwProfileObject {
-    pub(crate) fn new(index: u32, description: &str, availability: u32) -> Self {
+    pub(crate) fn new(index: u32, name: &str, description: &str, availability: ParamAvailability) -> Self {
         glib::Object::builder()
             .property("index", index)
+            .property("name", name)
             .property("description", description)
-            .property("availability", ParamAvailability::from(availability))
+            .property("availability", availability)
             .build()
     }
 }
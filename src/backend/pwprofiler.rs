@@ -0,0 +1,322 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::macros::*;
+use glib::{ControlFlow, SourceId};
+use pipewire::{
+    context::Context,
+    core::Core,
+    loop_::Loop,
+    registry::{Listener, Registry},
+    spa::{
+        self,
+        pod::{deserialize::PodDeserializer, Value},
+    },
+    sys as pw_sys,
+    types::ObjectType,
+};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    ffi::{c_void, CString},
+    os::fd::AsRawFd,
+    rc::Rc,
+    time::Duration,
+};
+
+// From spa/param/profiler.h
+const SPA_PROFILER_INFO: u32 = 0x10001;
+const SPA_PROFILER_CLOCK: u32 = 0x10002;
+const SPA_PROFILER_DRIVER_BLOCK: u32 = 0x10003;
+const SPA_PROFILER_FOLLOWER_BLOCK: u32 = 0x20001;
+
+// From pipewire/extensions/profiler.h
+const PW_VERSION_PROFILER: u32 = 3;
+const PW_TYPE_INTERFACE_PROFILER: &str = "PipeWire:Interface:Profiler";
+
+#[repr(C)]
+struct ProfilerEvents {
+    version: u32,
+    profile: Option<unsafe extern "C" fn(data: *mut c_void, pod: *const spa::sys::spa_pod)>,
+}
+
+static PROFILER_EVENTS: ProfilerEvents = ProfilerEvents { version: 0, profile: Some(profile_event) };
+
+/// Scheduling information about a node, as reported by the profiler.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NodeProfile {
+    pub id: u32,
+    pub name: String,
+    /// Id of the driver node this node follows, its own id for drivers.
+    pub driver_id: u32,
+    pub driver_name: String,
+    /// Samples per cycle of the driver.
+    pub quantum: i64,
+    pub rate: u32,
+    /// Latency the node asked for, as a fraction.
+    pub latency: (u32, u32),
+    pub xruns: i32,
+    /// Nanoseconds from the node being signalled until it woke up, and from then until it finished.
+    pub wait: i64,
+    pub busy: i64,
+    /// `wait` and `busy` as a share of the driver's period, like pw-top's W/Q and B/Q.
+    pub wait_quantum: f32,
+    pub busy_quantum: f32,
+    /// Share of the driver's period spent processing the graph, from 0 to 1.
+    pub dsp_load: f32,
+}
+
+impl NodeProfile {
+    pub fn is_driver(&self) -> bool {
+        self.id == self.driver_id
+    }
+
+    /// Share of the driver's period, `quantum / rate`, that `nsec` nanoseconds take.
+    pub(crate) fn share_of_period(&self, nsec: i64) -> f32 {
+        if self.quantum <= 0 || self.rate == 0 {
+            return 0.0;
+        }
+        let period = self.quantum as f64 * 1e9 / self.rate as f64;
+        (nsec as f64 / period) as f32
+    }
+}
+
+type ProfileMap = RefCell<HashMap<u32, NodeProfile>>;
+
+struct BoundProfiler {
+    proxy: *mut pw_sys::pw_proxy,
+    _hook: Box<spa::sys::spa_hook>,
+}
+
+impl Drop for BoundProfiler {
+    fn drop(&mut self) {
+        unsafe { pw_sys::pw_proxy_destroy(self.proxy) };
+    }
+}
+
+/// Client of the PipeWire profiler module, the same source `pw-top` uses.
+pub struct PwProfiler {
+    _loop: Loop,
+    _context: Context,
+    _core: Core,
+    _registry: Rc<Registry>,
+    _listener: Listener,
+    bound: Rc<RefCell<Option<BoundProfiler>>>,
+    profiles: Rc<ProfileMap>,
+    sig: Option<SourceId>,
+}
+
+impl PwProfiler {
+    pub fn new() -> Result<Self, anyhow::Error> {
+        let loop_ = Loop::new(None)?;
+        let context = Context::new(&loop_)?;
+        let core = context.connect(None)?;
+        let registry = Rc::new(core.get_registry()?);
+
+        let sig = glib::source::unix_fd_add_local(loop_.fd().as_raw_fd(), glib::IOCondition::all(), {
+            let loop_ = loop_.clone();
+            move |_, _| {
+                loop_.iterate(Duration::ZERO);
+                ControlFlow::Continue
+            }
+        });
+
+        let profiles: Rc<ProfileMap> = Default::default();
+        let bound: Rc<RefCell<Option<BoundProfiler>>> = Default::default();
+
+        let listener = registry
+            .add_listener_local()
+            .global({
+                let registry = Rc::downgrade(&registry);
+                let profiles = profiles.clone();
+                let bound = bound.clone();
+                move |global| {
+                    if global.type_ != ObjectType::Profiler || bound.borrow().is_some() {
+                        return;
+                    }
+                    let Some(registry) = registry.upgrade() else {
+                        return;
+                    };
+                    if let Some(profiler) = bind_profiler(&registry, global.id, &profiles) {
+                        bound.replace(Some(profiler));
+                    } else {
+                        pwvucontrol_warning!("Unable to bind the PipeWire profiler");
+                    }
+                }
+            })
+            .register();
+
+        Ok(Self {
+            _loop: loop_,
+            _context: context,
+            _core: core,
+            _registry: registry,
+            _listener: listener,
+            bound,
+            profiles,
+            sig: Some(sig),
+        })
+    }
+
+    /// Latest profile of the node with `id`, if the profiler is available and the node is scheduled.
+    pub fn node_profile(&self, id: u32) -> Option<NodeProfile> {
+        self.profiles.borrow().get(&id).cloned()
+    }
+
+    /// Latest profiles of all nodes scheduled in the last cycle of each driver.
+    pub fn profiles(&self) -> Vec<NodeProfile> {
+        self.profiles.borrow().values().cloned().collect()
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.bound.borrow().is_some()
+    }
+}
+
+impl Drop for PwProfiler {
+    fn drop(&mut self) {
+        self.bound.take();
+        if let Some(sig) = self.sig.take() {
+            sig.remove();
+        }
+    }
+}
+
+fn bind_profiler(registry: &Registry, id: u32, profiles: &Rc<ProfileMap>) -> Option<BoundProfiler> {
+    let type_ = CString::new(PW_TYPE_INTERFACE_PROFILER).ok()?;
+
+    unsafe {
+        let iface = registry.as_raw_ptr().cast::<spa::sys::spa_interface>();
+        let methods = (*iface).cb.funcs.cast::<pw_sys::pw_registry_methods>();
+        let bind = (*methods).bind?;
+        let proxy = bind((*iface).cb.data, id, type_.as_ptr(), PW_VERSION_PROFILER, 0).cast::<pw_sys::pw_proxy>();
+        if proxy.is_null() {
+            return None;
+        }
+
+        let mut hook: Box<spa::sys::spa_hook> = Box::new(std::mem::zeroed());
+        pw_sys::pw_proxy_add_object_listener(
+            proxy,
+            hook.as_mut(),
+            (&PROFILER_EVENTS as *const ProfilerEvents).cast(),
+            Rc::as_ptr(profiles) as *mut c_void,
+        );
+
+        Some(BoundProfiler { proxy, _hook: hook })
+    }
+}
+
+unsafe extern "C" fn profile_event(data: *mut c_void, pod: *const spa::sys::spa_pod) {
+    if data.is_null() || pod.is_null() {
+        return;
+    }
+    let profiles = &*(data as *const ProfileMap);
+    let bytes = std::slice::from_raw_parts(pod.cast::<u8>(), (*pod).size as usize + std::mem::size_of::<spa::sys::spa_pod>());
+
+    match PodDeserializer::deserialize_any_from(bytes) {
+        Ok((_, Value::Struct(drivers))) => {
+            let mut profiles = profiles.borrow_mut();
+            profiles.clear();
+            for driver in drivers.iter() {
+                parse_driver(driver, &mut profiles);
+            }
+        }
+        Ok(_) => pwvucontrol_debug!("Unexpected profiler pod"),
+        Err(_) => pwvucontrol_debug!("Unable to parse profiler pod"),
+    }
+}
+
+/// Parses one Profiler object, which describes a driver and all its followers for the last cycle.
+fn parse_driver(driver: &Value, profiles: &mut HashMap<u32, NodeProfile>) {
+    let Value::Object(object) = driver else {
+        return;
+    };
+
+    let mut clock = NodeProfile::default();
+    let mut driver_block = None;
+    let mut blocks = Vec::new();
+    let mut xruns = 0;
+
+    for property in object.properties.iter() {
+        let Value::Struct(fields) = &property.value else {
+            continue;
+        };
+        match property.key {
+            // The loads here are the server's CPU load, not the graph's, so only the xruns are used.
+            SPA_PROFILER_INFO => xruns = int_field(fields, 4).unwrap_or_default(),
+            SPA_PROFILER_CLOCK => {
+                clock.driver_id = int_field(fields, 1).unwrap_or_default() as u32;
+                clock.driver_name = string_field(fields, 2).unwrap_or_default();
+                if let Some(Value::Fraction(rate)) = fields.get(4) {
+                    clock.rate = rate.denom;
+                }
+                clock.quantum = long_field(fields, 6).unwrap_or_default();
+            }
+            SPA_PROFILER_DRIVER_BLOCK => {
+                driver_block = Some(fields);
+                blocks.push(fields);
+            }
+            SPA_PROFILER_FOLLOWER_BLOCK => blocks.push(fields),
+            _ => {}
+        }
+    }
+
+    // Like pw-top, the DSP load is the time from the driver being signalled until the graph finished, per period.
+    if let Some(fields) = driver_block {
+        let elapsed = long_field(fields, 5).zip(long_field(fields, 3)).map(|(finish, signal)| (finish - signal).max(0));
+        clock.dsp_load = clock.share_of_period(elapsed.unwrap_or_default());
+    }
+
+    for fields in blocks {
+        let Some(id) = int_field(fields, 0) else {
+            continue;
+        };
+        let latency = match fields.get(7) {
+            Some(Value::Fraction(latency)) => (latency.num, latency.denom),
+            _ => (0, 0),
+        };
+        let (signal, awake, finish) = (long_field(fields, 3), long_field(fields, 4), long_field(fields, 5));
+        let wait = awake.zip(signal).map(|(awake, signal)| (awake - signal).max(0)).unwrap_or_default();
+        let busy = finish.zip(awake).map(|(finish, awake)| (finish - awake).max(0)).unwrap_or_default();
+        let profile = NodeProfile {
+            id: id as u32,
+            name: string_field(fields, 1).unwrap_or_default(),
+            latency,
+            // Older servers only report xruns for the whole graph.
+            xruns: int_field(fields, 8).unwrap_or(xruns),
+            wait,
+            busy,
+            wait_quantum: clock.share_of_period(wait),
+            busy_quantum: clock.share_of_period(busy),
+            ..clock.clone()
+        };
+        profiles.insert(id as u32, profile);
+    }
+}
+
+fn int_field(fields: &[Value], index: usize) -> Option<i32> {
+    match fields.get(index) {
+        Some(Value::Int(value)) => Some(*value),
+        _ => None,
+    }
+}
+
+fn long_field(fields: &[Value], index: usize) -> Option<i64> {
+    match fields.get(index) {
+        Some(Value::Long(value)) => Some(*value),
+        _ => None,
+    }
+}
+
+fn float_field(fields: &[Value], index: usize) -> Option<f32> {
+    match fields.get(index) {
+        Some(Value::Float(value)) => Some(*value),
+        _ => None,
+    }
+}
+
+fn string_field(fields: &[Value], index: usize) -> Option<String> {
+    match fields.get(index) {
+        Some(Value::String(value)) => Some(value.clone()),
+        _ => None,
+    }
+}
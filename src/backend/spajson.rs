@@ -0,0 +1,48 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Reading and writing the JSON values of the `default` metadata.
+//!
+//! WirePlumber writes these as SPA JSON, where keys may be unquoted, `=` may stand for `:` and
+//! commas are optional, so they are read with WirePlumber's own parser rather than split by hand.
+
+use std::fmt::Write;
+use wireplumber as wp;
+use wp::spa::SpaJson;
+
+/// A JSON string literal holding `value`.
+pub fn json_string(value: &str) -> String {
+    let mut result = String::with_capacity(value.len() + 2);
+    result.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\t' => result.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(result, "\\u{:04x}", c as u32);
+            }
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+/// The string member `key` of a JSON object, like the node name in `{ "name": "alsa_output.pci" }`
+/// values of the `default` metadata.
+pub fn json_object_string(value: &str, key: &str) -> Option<String> {
+    let json = SpaJson::from_string(value);
+    if !json.is_object() {
+        return None;
+    }
+
+    // Iterating an object yields its keys and values in turn.
+    let mut members = json.new_iterator().into_iter().filter_map(|item| item.get::<SpaJson>().ok());
+    while let (Some(name), Some(member)) = (members.next(), members.next()) {
+        if name.parse_string() == key {
+            return (!member.is_container() && !member.is_null()).then(|| member.parse_string().to_string());
+        }
+    }
+    None
+}
@@ -0,0 +1,258 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Typed reading of the params PipeWire reports for nodes and devices.
+//!
+//! Drivers disagree on which fields they fill in, so every parser says which field was missing or
+//! had the wrong type instead of panicking. Callers log the error and skip that one param, leaving
+//! the rest of the node or device usable.
+
+use super::{pwnodeobject::AudioFormat, ActiveRoute, NodeProps, ParamAvailability, ProfileInfo, PropsParamValue, RouteDirection, RouteInfo};
+use std::fmt;
+use wireplumber as wp;
+use wp::spa::{ffi, SpaPod};
+
+// From spa/param/audio/raw.h
+const SPA_AUDIO_CHANNEL_MONO: u32 = 2;
+const SPA_AUDIO_CHANNEL_FL: u32 = 3;
+const SPA_AUDIO_CHANNEL_FR: u32 = 4;
+const SPA_AUDIO_CHANNEL_AUX0: u32 = 0x1000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParamError {
+    NotAnObject,
+    Missing(&'static str),
+    WrongType(&'static str),
+    Invalid(&'static str),
+}
+
+impl fmt::Display for ParamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParamError::NotAnObject => write!(f, "param is not an object"),
+            ParamError::Missing(field) => write!(f, "{field} is missing"),
+            ParamError::WrongType(field) => write!(f, "{field} has the wrong type"),
+            ParamError::Invalid(field) => write!(f, "{field} has an invalid value"),
+        }
+    }
+}
+
+impl std::error::Error for ParamError {}
+
+pub type ParamResult<T> = Result<T, ParamError>;
+
+/// A param object, with typed access to its properties.
+struct ParamObject<'a>(&'a SpaPod);
+
+impl<'a> ParamObject<'a> {
+    fn new(pod: &'a SpaPod) -> ParamResult<Self> {
+        if pod.is_object() {
+            Ok(Self(pod))
+        } else {
+            Err(ParamError::NotAnObject)
+        }
+    }
+
+    /// The property `key`, or the default value when it is a choice.
+    fn get(&self, key: u32) -> Option<SpaPod> {
+        let pod = self.0.find_spa_property(&key)?;
+        if pod.is_choice() {
+            pod.choice_child()
+        } else {
+            Some(pod)
+        }
+    }
+
+    fn require(&self, key: u32, field: &'static str) -> ParamResult<SpaPod> {
+        self.get(key).ok_or(ParamError::Missing(field))
+    }
+
+    fn int(&self, key: u32, field: &'static str) -> ParamResult<i32> {
+        self.require(key, field)?.int().ok_or(ParamError::WrongType(field))
+    }
+
+    fn optional_int(&self, key: u32, field: &'static str) -> ParamResult<Option<i32>> {
+        self.get(key).map(|pod| pod.int().ok_or(ParamError::WrongType(field))).transpose()
+    }
+
+    fn id(&self, key: u32, field: &'static str) -> ParamResult<u32> {
+        self.require(key, field)?.id().ok_or(ParamError::WrongType(field))
+    }
+
+    fn string(&self, key: u32, field: &'static str) -> ParamResult<String> {
+        self.require(key, field)?.string().map(|s| s.to_string()).ok_or(ParamError::WrongType(field))
+    }
+
+    fn optional_string(&self, key: u32) -> Option<String> {
+        self.get(key)?.string().map(|s| s.to_string())
+    }
+
+    fn availability(&self, key: u32) -> ParamAvailability {
+        self.get(key).map(|pod| ParamAvailability::from(&pod)).unwrap_or_default()
+    }
+
+    /// An array property, empty when it is missing.
+    fn array<T: Copy + 'static>(&self, key: u32, field: &'static str) -> ParamResult<Vec<T>> {
+        match self.get(key) {
+            Some(pod) if pod.is_array() => Ok(pod.array_iterator::<T>().collect()),
+            Some(_) => Err(ParamError::WrongType(field)),
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+/// An `EnumProfile` or `Profile` param. A profile without `available` is taken as unknown.
+pub fn parse_profile(pod: &SpaPod) -> ParamResult<ProfileInfo> {
+    let object = ParamObject::new(pod)?;
+
+    let index = object.int(ffi::SPA_PARAM_PROFILE_index, "profile index")?;
+    let name = object.optional_string(ffi::SPA_PARAM_PROFILE_name).unwrap_or_default();
+    let description = object.optional_string(ffi::SPA_PARAM_PROFILE_description).unwrap_or_else(|| name.clone());
+    let availability = object.availability(ffi::SPA_PARAM_PROFILE_available);
+
+    Ok(ProfileInfo { index: u32::try_from(index).map_err(|_| ParamError::Invalid("profile index"))?, name, description, availability })
+}
+
+/// An `EnumRoute` param, with the product name from the route info added to the description.
+pub fn parse_route(pod: &SpaPod) -> ParamResult<RouteInfo> {
+    let object = ParamObject::new(pod)?;
+
+    let index = object.int(ffi::SPA_PARAM_ROUTE_index, "route index")?;
+    let direction = RouteDirection::from(object.id(ffi::SPA_PARAM_ROUTE_direction, "route direction")?);
+    if direction == RouteDirection::Unknown {
+        return Err(ParamError::Invalid("route direction"));
+    }
+    let name = object.optional_string(ffi::SPA_PARAM_ROUTE_name).unwrap_or_default();
+    let description = object.optional_string(ffi::SPA_PARAM_ROUTE_description).unwrap_or(name);
+    let availability = object.availability(ffi::SPA_PARAM_ROUTE_available);
+    let profiles = object.array::<i32>(ffi::SPA_PARAM_ROUTE_profiles, "route profiles")?.into_iter().map(|x| x as u32).collect();
+
+    let product_name = object.get(ffi::SPA_PARAM_ROUTE_info).and_then(|info| find_struct_key(&info, "device.product.name"));
+    let description = match product_name {
+        Some(product_name) => format!("{description} [{product_name}]"),
+        None => description,
+    };
+
+    Ok(RouteInfo { index: u32::try_from(index).map_err(|_| ParamError::Invalid("route index"))?, description, direction, availability, profiles })
+}
+
+/// A `Route` param, the route currently used by one card device.
+pub fn parse_active_route(pod: &SpaPod) -> ParamResult<ActiveRoute> {
+    let object = ParamObject::new(pod)?;
+
+    let index = object.int(ffi::SPA_PARAM_ROUTE_index, "route index")?;
+    let device = object.int(ffi::SPA_PARAM_ROUTE_device, "route device")?;
+    let direction = RouteDirection::from(object.id(ffi::SPA_PARAM_ROUTE_direction, "route direction")?);
+    let name = object.string(ffi::SPA_PARAM_ROUTE_name, "route name")?;
+    let description = object.optional_string(ffi::SPA_PARAM_ROUTE_description).unwrap_or_else(|| name.clone());
+
+    let (channel_volumes, mute) = match object.get(ffi::SPA_PARAM_ROUTE_props) {
+        Some(props) => {
+            let props = ParamObject::new(&props).map_err(|_| ParamError::WrongType("route props"))?;
+            let mute = props.get(ffi::SPA_PROP_mute).map(|mute| mute.boolean().ok_or(ParamError::WrongType("route mute"))).transpose()?;
+            (props.array::<f32>(ffi::SPA_PROP_channelVolumes, "route channel volumes")?, mute.unwrap_or_default())
+        }
+        None => (Vec::new(), false),
+    };
+
+    Ok(ActiveRoute { index, device, name, description, direction, channel_volumes, mute })
+}
+
+/// A `Format` param of an audio node. Missing channel positions are made up from the channel count,
+/// as some Bluetooth devices leave them out.
+pub fn parse_format(pod: &SpaPod) -> ParamResult<AudioFormat> {
+    let object = ParamObject::new(pod)?;
+
+    let format = object.id(ffi::SPA_FORMAT_AUDIO_format, "audio format")?;
+    if format == 0 {
+        return Err(ParamError::Invalid("audio format"));
+    }
+    let rate = object.optional_int(ffi::SPA_FORMAT_AUDIO_rate, "audio rate")?.unwrap_or_default();
+    let channels = object.optional_int(ffi::SPA_FORMAT_AUDIO_channels, "audio channels")?.unwrap_or_default();
+    let channels = u32::try_from(channels).map_err(|_| ParamError::Invalid("audio channels"))?;
+
+    let mut positions: Vec<u32> = object.array::<i32>(ffi::SPA_FORMAT_AUDIO_position, "audio position")?.into_iter().map(|x| x as u32).collect();
+    if positions.is_empty() {
+        positions = default_positions(channels);
+    }
+
+    let mut a = [0u32; 64];
+    for (position, value) in a.iter_mut().zip(positions) {
+        *position = value;
+    }
+
+    Ok(AudioFormat { format, channels, rate: rate.max(0) as u32, positions: a })
+}
+
+/// A `Props` param. Nodes leave out what they do not support, so only the type of what is there is checked.
+pub fn parse_props(pod: &SpaPod, props: &mut NodeProps) -> ParamResult<()> {
+    let object = ParamObject::new(pod)?;
+
+    if let Some(volume) = object.get(ffi::SPA_PROP_volume) {
+        props.volume = Some(volume.float().ok_or(ParamError::WrongType("volume"))?);
+    }
+
+    let channel_volumes = object.array::<f32>(ffi::SPA_PROP_channelVolumes, "channel volumes")?;
+    if !channel_volumes.is_empty() {
+        props.channel_volumes = channel_volumes;
+    }
+
+    if let Some(mute) = object.get(ffi::SPA_PROP_mute) {
+        props.mute = Some(mute.boolean().ok_or(ParamError::WrongType("mute"))?);
+    }
+
+    let monitor_volumes = object.array::<f32>(ffi::SPA_PROP_monitorVolumes, "monitor volumes")?;
+    if !monitor_volumes.is_empty() {
+        props.monitor_volumes = monitor_volumes;
+    }
+
+    if let Some(params) = object.get(ffi::SPA_PROP_params) {
+        props.params.extend(struct_params(&params));
+    }
+    Ok(())
+}
+
+/// Channel positions for a format that has none: mono, stereo or numbered auxiliary channels.
+fn default_positions(channels: u32) -> Vec<u32> {
+    match channels {
+        1 => vec![SPA_AUDIO_CHANNEL_MONO],
+        2 => vec![SPA_AUDIO_CHANNEL_FL, SPA_AUDIO_CHANNEL_FR],
+        _ => (0..channels.min(64)).map(|channel| SPA_AUDIO_CHANNEL_AUX0 + channel).collect(),
+    }
+}
+
+/// The string value following `key` in a struct of key and value pairs.
+fn find_struct_key(input: &SpaPod, key: &str) -> Option<String> {
+    let mut iter = input.iterator().into_iter();
+
+    while let Some(k) = iter.next() {
+        if k.string() == Some(key.into()) {
+            return iter.next()?.string().map(|gs| gs.to_string());
+        }
+    }
+    None
+}
+
+/// Key and value pairs of a Props `params` struct.
+fn struct_params(params: &SpaPod) -> Vec<(String, PropsParamValue)> {
+    let mut result = Vec::new();
+
+    let mut iter = params.iterator().into_iter();
+    while let (Some(key), Some(value)) = (iter.next(), iter.next()) {
+        let Some(key) = key.string() else {
+            continue;
+        };
+        let value = if value.is_boolean() {
+            value.boolean().map(PropsParamValue::Bool)
+        } else if value.is_float() {
+            value.float().map(PropsParamValue::Float)
+        } else if value.is_int() {
+            value.int().map(PropsParamValue::Int)
+        } else {
+            value.string().map(|value| PropsParamValue::String(value.to_string()))
+        };
+        if let Some(value) = value {
+            result.push((key.to_string(), value));
+        }
+    }
+    result
+}
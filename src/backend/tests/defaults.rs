@@ -0,0 +1,30 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use super::harness::{self, wait_for_node, wait_until, PLAYBACK_STREAM, SINK_1, SINK_2};
+
+#[test]
+fn default_sink_follows_configured_name() {
+    harness::run("default_sink_follows_configured_name", |manager| {
+        let sink_1 = wait_for_node(&manager.sink_model(), SINK_1);
+        let sink_2 = wait_for_node(&manager.sink_model(), SINK_2);
+
+        for sink in [&sink_2, &sink_1] {
+            assert!(manager.set_default_configured_node_name("Audio/Sink", &sink.node_property::<String>("node.name").expect("node.name")));
+            wait_until("the default sink to change", || manager.default_configured_sink_node().as_ref() == Some(sink));
+        }
+    });
+}
+
+#[test]
+fn stream_target_round_trip() {
+    harness::run("stream_target_round_trip", |manager| {
+        let stream = wait_for_node(&manager.stream_output_model(), PLAYBACK_STREAM);
+        let sink = wait_for_node(&manager.sink_model(), SINK_2);
+
+        stream.set_default_target(&sink);
+        wait_until("the stream target to be set", || stream.default_target().as_ref() == Some(&sink));
+
+        stream.unset_default_target();
+        wait_until("the stream target to be unset", || stream.default_target().is_none());
+    });
+}
@@ -0,0 +1,407 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Runs the backend against a private PipeWire and WirePlumber.
+//!
+//! The daemons get their own runtime, config and state directories, so they never talk to the
+//! session of the user running the tests, and the hardware monitors are turned off. Two null sinks
+//! and a null source are created from the PipeWire config, and `pw-cat` provides a playback and a
+//! capture stream. When a virtual ALSA card from `snd-dummy` or `snd-aloop` is loaded, it is added as
+//! a device through the SPA device factory.
+//!
+//! Tests run with [`run_mock`] get a fresh manager on a [`MockBackend`] instead, which needs nothing
+//! but `glib-compile-schemas`.
+//!
+//! Tests using the private session are skipped with a note on stderr when the daemons or the card are
+//! missing. Set `PWVUCONTROL_TEST_SESSION=1` to make them fail instead, so CI notices a broken setup.
+//!
+//! GLib, GTK and WirePlumber objects are bound to the thread that created them while libtest runs
+//! every test on a thread of its own, so the tests are sent to a single harness thread one at a time.
+
+use crate::{
+    backend::{MockBackend, PwDeviceObject, PwNodeObject, PwvucontrolManager, WpBackend},
+    config::{APP_ID, GETTEXT_PACKAGE},
+};
+use gtk::{gio, prelude::*};
+use std::{
+    cell::RefCell,
+    env, fs,
+    io::{self, Read, Write},
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    rc::Rc,
+    sync::{mpsc, Mutex, OnceLock},
+    thread,
+    time::{Duration, Instant},
+};
+
+pub const SINK_1: &str = "pwvucontrol-test-sink-1";
+pub const SINK_2: &str = "pwvucontrol-test-sink-2";
+pub const SOURCE: &str = "pwvucontrol-test-source";
+pub const PLAYBACK_STREAM: &str = "pwvucontrol-test-playback";
+pub const CAPTURE_STREAM: &str = "pwvucontrol-test-capture";
+pub const CARD: &str = "pwvucontrol-test-card";
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Length of the silence `pw-cat` plays, long enough to outlast the test run.
+const PLAYBACK_SECONDS: u32 = 120;
+
+const PROGRAMS: [&str; 3] = ["pipewire", "wireplumber", "pw-cat"];
+
+/// ALSA ids of the cards the `snd-dummy` and `snd-aloop` modules create.
+const VIRTUAL_CARDS: [&str; 2] = ["Dummy", "Loopback"];
+
+type Job = Box<dyn FnOnce() + Send>;
+
+static HARNESS: OnceLock<Option<Mutex<mpsc::Sender<Job>>>> = OnceLock::new();
+
+thread_local! {
+    static MANAGER: RefCell<Option<PwvucontrolManager>> = const { RefCell::new(None) };
+    static SESSION: RefCell<Option<(Instance, PwvucontrolManager)>> = const { RefCell::new(None) };
+}
+
+/// The manager tests run against, used by [`PwvucontrolManager::default`] on the harness thread.
+pub(crate) fn current_manager() -> Option<PwvucontrolManager> {
+    MANAGER.with(|manager| manager.borrow().clone())
+}
+
+/// Runs `test` on the harness thread against the private PipeWire session.
+///
+/// The manager is shared by all tests, so tests must not rely on state left by others.
+pub fn run(name: &str, test: impl FnOnce(&PwvucontrolManager) + Send + 'static) {
+    if let Some(program) = PROGRAMS.iter().find(|program| !in_path(program)) {
+        skip(name, &format!("{program} is not installed"));
+        return;
+    }
+
+    run_on_harness(name, move || {
+        let manager = SESSION.with(|session| {
+            let mut session = session.borrow_mut();
+            let (_, manager) =
+                session.get_or_insert_with(|| start_session().unwrap_or_else(|e| panic!("Unable to start PipeWire for the tests: {e}")));
+            manager.clone()
+        });
+        test(&manager);
+    });
+}
+
+/// Runs `test` against the private session with the device of the virtual ALSA card.
+pub fn run_with_card(name: &str, test: impl FnOnce(&PwvucontrolManager, &PwDeviceObject) + Send + 'static) {
+    if virtual_card().is_none() {
+        skip(name, "no snd-dummy or snd-aloop card is loaded");
+        return;
+    }
+
+    run(name, move |manager| {
+        let device = wait_for_device(&manager.device_model(), CARD);
+        test(manager, &device);
+    });
+}
+
+/// Skips a test that needs something missing, unless `PWVUCONTROL_TEST_SESSION` asks for failing.
+fn skip(name: &str, reason: &str) {
+    if env::var_os("PWVUCONTROL_TEST_SESSION").is_some_and(|value| value == "1") {
+        panic!("{name} cannot run: {reason}");
+    }
+    eprintln!("Skipping {name}: {reason}");
+}
+
+/// Runs `test` on the harness thread against a manager of its own on an empty [`MockBackend`].
+pub fn run_mock(name: &str, test: impl FnOnce(&PwvucontrolManager, &MockBackend) + Send + 'static) {
+    run_on_harness(name, move || {
+        let backend = MockBackend::new();
+        let manager = PwvucontrolManager::new();
+        with_manager(&manager, || {
+            manager.start(Rc::new(backend.clone()));
+            test(&manager, &backend);
+        });
+    });
+}
+
+fn run_on_harness(name: &str, job: impl FnOnce() + Send + 'static) {
+    let Some(jobs) = HARNESS.get_or_init(start) else {
+        panic!("{name} needs glib-compile-schemas, which is not installed");
+    };
+
+    let (done_tx, done_rx) = mpsc::channel();
+    let job: Job = Box::new(move || {
+        let result = panic::catch_unwind(AssertUnwindSafe(job));
+        let _ = done_tx.send(result);
+    });
+
+    jobs.lock().unwrap_or_else(|e| e.into_inner()).send(job).expect("harness thread running");
+    if let Err(payload) = done_rx.recv().expect("harness thread running") {
+        panic::resume_unwind(payload);
+    }
+}
+
+/// Makes `manager` the one [`PwvucontrolManager::default`] returns while `f` runs.
+fn with_manager(manager: &PwvucontrolManager, f: impl FnOnce()) {
+    let previous = MANAGER.with(|current| current.replace(Some(manager.clone())));
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    MANAGER.with(|current| current.replace(previous));
+    if let Err(payload) = result {
+        panic::resume_unwind(payload);
+    }
+}
+
+/// Runs the main loop until `condition` holds, failing the test after a while.
+pub fn wait_until(what: &str, mut condition: impl FnMut() -> bool) {
+    let context = glib::MainContext::default();
+    let start = Instant::now();
+
+    while !condition() {
+        assert!(start.elapsed() < TIMEOUT, "Timed out waiting for {what}");
+        while context.iteration(false) {}
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+/// Looks up a node by its `node.name` in one of the manager's models.
+pub fn node_by_name(model: &impl IsA<gio::ListModel>, name: &str) -> Option<PwNodeObject> {
+    model.iter::<PwNodeObject>().map_while(Result::ok).find(|node| node.node_property::<String>("node.name").as_deref() == Some(name))
+}
+
+/// Waits for a node to show up in `model` and returns it.
+pub fn wait_for_node(model: &impl IsA<gio::ListModel>, name: &str) -> PwNodeObject {
+    wait_until(name, || node_by_name(model, name).is_some());
+    node_by_name(model, name).expect("node just seen")
+}
+
+/// Looks up a device by its `device.name` in `model`.
+pub fn device_by_name(model: &impl IsA<gio::ListModel>, name: &str) -> Option<PwDeviceObject> {
+    model.iter::<PwDeviceObject>().map_while(Result::ok).find(|device| device.backend().property("device.name").as_deref() == Some(name))
+}
+
+/// Waits for a device to show up in `model` and returns it.
+pub fn wait_for_device(model: &impl IsA<gio::ListModel>, name: &str) -> PwDeviceObject {
+    wait_until(name, || device_by_name(model, name).is_some());
+    device_by_name(model, name).expect("device just seen")
+}
+
+/// Index of a loaded virtual ALSA card, from `/proc/asound/cards`.
+fn virtual_card() -> Option<u32> {
+    let cards = fs::read_to_string("/proc/asound/cards").ok()?;
+    cards.lines().find_map(|line| {
+        // Like ` 0 [Dummy          ]: Dummy - Dummy`, followed by a line with the long name.
+        let (index, rest) = line.trim_start().split_once(' ')?;
+        let id = rest.trim_start().strip_prefix('[')?.split_once(']')?.0.trim();
+        VIRTUAL_CARDS.contains(&id).then(|| index.parse().ok())?
+    })
+}
+
+fn start() -> Option<Mutex<mpsc::Sender<Job>>> {
+    if !in_path("glib-compile-schemas") {
+        return None;
+    }
+
+    let (jobs_tx, jobs_rx) = mpsc::channel::<Job>();
+    let (ready_tx, ready_rx) = mpsc::channel::<io::Result<()>>();
+
+    thread::Builder::new()
+        .name("pwvucontrol-test-harness".to_string())
+        .spawn(move || {
+            if let Err(e) = compile_schema() {
+                let _ = ready_tx.send(Err(e));
+                return;
+            }
+
+            // Only list models and filters are used from GTK, which need no display.
+            unsafe { gtk::rt::set_initialized() };
+
+            let context = glib::MainContext::default();
+            let _acquired = context.acquire().expect("acquire the default main context");
+
+            let _ = ready_tx.send(Ok(()));
+            for job in jobs_rx {
+                job();
+            }
+        })
+        .expect("spawn harness thread");
+
+    match ready_rx.recv() {
+        Ok(Ok(())) => Some(Mutex::new(jobs_tx)),
+        Ok(Err(e)) => panic!("Unable to set up the test harness: {e}"),
+        Err(_) => panic!("Harness thread failed while starting"),
+    }
+}
+
+/// Starts the private PipeWire and a manager connected to it, on the harness thread.
+fn start_session() -> io::Result<(Instance, PwvucontrolManager)> {
+    let instance = Instance::start()?;
+
+    // Stays the default between tests, as the daemons keep reporting changes.
+    let manager = PwvucontrolManager::new();
+    MANAGER.with(|current| current.replace(Some(manager.clone())));
+    manager.start(Rc::new(WpBackend::new()));
+    wait_until("the manager to connect", || manager.connected() && manager.default_node_id("Audio/Sink").is_some());
+    Ok((instance, manager))
+}
+
+/// Directory holding the private PipeWire session and the compiled settings schema.
+fn test_dir() -> PathBuf {
+    env::temp_dir().join(format!("pwvucontrol-test-{}", std::process::id()))
+}
+
+fn compile_schema() -> io::Result<()> {
+    let dir = test_dir();
+    let _ = fs::remove_dir_all(&dir);
+
+    let source = Path::new(env!("CARGO_MANIFEST_DIR")).join("data/com.saivert.pwvucontrol.gschema.xml.in");
+    let schema = fs::read_to_string(source)?.replace("@app-id@", APP_ID).replace("@gettext-package@", GETTEXT_PACKAGE);
+
+    let schema_dir = dir.join("schemas");
+    fs::create_dir_all(&schema_dir)?;
+    fs::write(schema_dir.join(format!("{APP_ID}.gschema.xml")), schema)?;
+
+    let status = Command::new("glib-compile-schemas").arg(&schema_dir).status()?;
+    if !status.success() {
+        return Err(io::Error::other("glib-compile-schemas failed"));
+    }
+
+    env::set_var("GSETTINGS_SCHEMA_DIR", schema_dir);
+    env::set_var("GSETTINGS_BACKEND", "memory");
+    Ok(())
+}
+
+fn in_path(program: &str) -> bool {
+    env::var_os("PATH").is_some_and(|path| env::split_paths(&path).any(|dir| dir.join(program).is_file()))
+}
+
+/// The private PipeWire session, torn down when dropped.
+struct Instance {
+    dir: PathBuf,
+    children: Vec<Child>,
+}
+
+impl Instance {
+    fn start() -> io::Result<Self> {
+        let mut instance = Instance { dir: test_dir(), children: Vec::new() };
+        instance.write_config()?;
+
+        env::set_var("XDG_RUNTIME_DIR", &instance.dir);
+        env::set_var("PIPEWIRE_RUNTIME_DIR", &instance.dir);
+
+        instance.spawn(&["pipewire"])?;
+        let socket = instance.dir.join("pipewire-0");
+        let start = Instant::now();
+        while !socket.exists() {
+            if start.elapsed() > TIMEOUT {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "pipewire did not create its socket"));
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+
+        instance.spawn(&["wireplumber"])?;
+
+        let silence = instance.dir.join("silence.wav");
+        write_silence(&silence)?;
+        let playback_props = format!("{{ node.name = {PLAYBACK_STREAM} application.name = pwvucontrol-test }}");
+        instance.spawn(&["pw-cat", "--playback", "-P", &playback_props, &silence.to_string_lossy()])?;
+        let capture_props = format!("{{ node.name = {CAPTURE_STREAM} application.name = pwvucontrol-test }}");
+        let capture = instance.dir.join("capture.wav");
+        instance.spawn(&["pw-cat", "--record", "-P", &capture_props, &capture.to_string_lossy()])?;
+
+        Ok(instance)
+    }
+
+    fn write_config(&self) -> io::Result<()> {
+        let null_node = |name: &str, description: &str, media_class: &str| {
+            format!(
+                "{{ factory = adapter args = {{ factory.name = support.null-audio-sink node.name = {name} \
+                 node.description = \"{description}\" media.class = {media_class} audio.position = [ FL FR ] \
+                 monitor.channel-volumes = true object.linger = true }} }}"
+            )
+        };
+        // Created by PipeWire itself rather than WirePlumber's ALSA monitor, which is off.
+        let card = virtual_card().map(|card| {
+            format!(
+                "{{ factory = spa-device-factory args = {{ factory.name = api.alsa.acp.device api.alsa.path = \"hw:{card}\" \
+                 api.alsa.card = {card} device.name = {CARD} device.description = \"Test Card\" device.api = alsa \
+                 media.class = Audio/Device }} }}"
+            )
+        });
+        let pipewire_dir = self.dir.join("config/pipewire/pipewire.conf.d");
+        fs::create_dir_all(&pipewire_dir)?;
+        fs::write(
+            pipewire_dir.join("pwvucontrol-test.conf"),
+            format!(
+                "context.objects = [\n  {}\n  {}\n  {}\n  {}\n]\n",
+                null_node(SINK_1, "Test Sink 1", "Audio/Sink"),
+                null_node(SINK_2, "Test Sink 2", "Audio/Sink"),
+                null_node(SOURCE, "Test Source", "Audio/Source/Virtual"),
+                card.unwrap_or_default(),
+            ),
+        )?;
+
+        // Keep WirePlumber away from real sound cards and Bluetooth devices, both 0.4 and 0.5 style.
+        let lua_dir = self.dir.join("config/wireplumber/main.lua.d");
+        fs::create_dir_all(&lua_dir)?;
+        fs::write(lua_dir.join("99-pwvucontrol-test.lua"), "alsa_monitor.enabled = false\nv4l2_monitor.enabled = false\n")?;
+        let bluetooth_dir = self.dir.join("config/wireplumber/bluetooth.lua.d");
+        fs::create_dir_all(&bluetooth_dir)?;
+        fs::write(bluetooth_dir.join("99-pwvucontrol-test.lua"), "bluez_monitor.enabled = false\n")?;
+        let conf_dir = self.dir.join("config/wireplumber/wireplumber.conf.d");
+        fs::create_dir_all(&conf_dir)?;
+        fs::write(
+            conf_dir.join("99-pwvucontrol-test.conf"),
+            "wireplumber.profiles = {\n  main = {\n    monitor.alsa = disabled\n    monitor.bluez = disabled\n    monitor.v4l2 = disabled\n  }\n}\n",
+        )
+    }
+
+    /// Starts `command` under a shell that kills it once our end of its stdin closes, so the
+    /// daemons go away with the test process even though statics are never dropped.
+    fn spawn(&mut self, command: &[&str]) -> io::Result<()> {
+        let log = fs::File::create(self.dir.join(format!("{}-{}.log", command[0], self.children.len())))?;
+
+        let child = Command::new("sh")
+            .args(["-c", "\"$@\" </dev/null & child=$!; read _; kill $child; wait", "sh"])
+            .args(command)
+            .env("XDG_RUNTIME_DIR", &self.dir)
+            .env("PIPEWIRE_RUNTIME_DIR", &self.dir)
+            .env("XDG_CONFIG_HOME", self.dir.join("config"))
+            .env("XDG_STATE_HOME", self.dir.join("state"))
+            .env_remove("DBUS_SESSION_BUS_ADDRESS")
+            .env_remove("PIPEWIRE_REMOTE")
+            .stdin(Stdio::piped())
+            .stdout(log.try_clone()?)
+            .stderr(log)
+            .spawn()?;
+
+        self.children.push(child);
+        Ok(())
+    }
+}
+
+impl Drop for Instance {
+    fn drop(&mut self) {
+        for child in self.children.iter_mut().rev() {
+            drop(child.stdin.take());
+            let _ = child.wait();
+        }
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Writes a mono 8 kHz 16 bit WAV file of silence.
+fn write_silence(path: &Path) -> io::Result<()> {
+    const RATE: u32 = 8000;
+    let data_len = RATE * 2 * PLAYBACK_SECONDS;
+
+    let mut file = io::BufWriter::new(fs::File::create(path)?);
+    file.write_all(b"RIFF")?;
+    file.write_all(&(36 + data_len).to_le_bytes())?;
+    file.write_all(b"WAVEfmt ")?;
+    file.write_all(&16u32.to_le_bytes())?;
+    file.write_all(&1u16.to_le_bytes())?; // PCM
+    file.write_all(&1u16.to_le_bytes())?; // channels
+    file.write_all(&RATE.to_le_bytes())?;
+    file.write_all(&(RATE * 2).to_le_bytes())?; // byte rate
+    file.write_all(&2u16.to_le_bytes())?; // block align
+    file.write_all(&16u16.to_le_bytes())?; // bits per sample
+    file.write_all(b"data")?;
+    file.write_all(&data_len.to_le_bytes())?;
+    io::copy(&mut io::repeat(0).take(data_len as u64), &mut file)?;
+    file.flush()
+}
@@ -0,0 +1,219 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use super::harness::{self, wait_for_node, wait_until};
+use crate::{
+    backend::{MockBackend, MockRoute, NodeType, PwDeviceObject, PwNodeObject, RouteDirection, RouteProps},
+    config::APP_ID,
+};
+use gtk::{prelude::*, subclass::prelude::*};
+
+const SPEAKERS: u32 = 0;
+const HEADPHONES: u32 = 1;
+
+/// Adds a sound card with speakers and unplugged headphones, and its sink.
+fn add_card(backend: &MockBackend) -> (u32, u32) {
+    let device = backend.add_device(
+        &[("device.name", "mock_card"), ("device.description", "Mock Card"), ("device.api", "alsa")],
+        &[("off", "Off"), ("output:analog-stereo", "Analog Stereo Output")],
+        1,
+        vec![
+            MockRoute::new("analog-output-speaker", "Speakers", RouteDirection::Output, &[1], 0),
+            MockRoute { available: false, ..MockRoute::new("analog-output-headphones", "Headphones", RouteDirection::Output, &[1], 0) },
+        ],
+    );
+    let sink = backend.add_node(
+        &[("node.name", "mock_sink"), ("node.description", "Mock Sink"), ("media.class", "Audio/Sink"), ("device.api", "alsa")],
+        &["FL", "FR"],
+        Some((device, 0)),
+    );
+    (device, sink)
+}
+
+fn add_sink(backend: &MockBackend, name: &str) -> u32 {
+    backend.add_node(&[("node.name", name), ("node.description", name), ("media.class", "Audio/Sink")], &["FL", "FR"], None)
+}
+
+fn mock_device(devices: &gtk::gio::ListStore, id: u32) -> Option<PwDeviceObject> {
+    devices.iter::<PwDeviceObject>().map_while(Result::ok).find(|device| device.id() == id)
+}
+
+#[test]
+fn mock_nodes_and_devices_are_listed() {
+    harness::run_mock("mock_nodes_and_devices_are_listed", |manager, backend| {
+        let (device_id, _) = add_card(backend);
+
+        let sink = wait_for_node(&manager.sink_model(), "mock_sink");
+        assert_eq!(sink.nodetype(), NodeType::Sink);
+        wait_until("sink channels", || sink.channel_volumes_vec().len() == 2);
+
+        let device = mock_device(&manager.device_model(), device_id).expect("device listed");
+        assert_eq!(device.name().as_deref(), Some("Mock Card"));
+        wait_until("the profiles", || device.profilemodel().n_items() == 2);
+        assert_eq!(sink.device().as_ref(), Some(&device));
+
+        backend.remove_node(sink.boundid());
+        assert!(harness::node_by_name(&manager.sink_model(), "mock_sink").is_none());
+    });
+}
+
+#[test]
+fn mock_volume_round_trip() {
+    harness::run_mock("mock_volume_round_trip", |manager, backend| {
+        let id = add_sink(backend, "mock_volume_sink");
+        let sink = wait_for_node(&manager.sink_model(), "mock_volume_sink");
+        wait_until("sink channels", || sink.channel_volumes_vec().len() == 2);
+
+        sink.set_volume(0.4);
+        wait_until("the backend to get the volume", || backend.node_volume(id).is_some_and(|volume| volume.channel_volumes.iter().all(|volume| (volume - 0.4).abs() < 0.001)));
+
+        sink.set_mute(true);
+        wait_until("the backend to get the mute state", || backend.node_volume(id).is_some_and(|volume| volume.mute));
+        assert!(sink.mute());
+    });
+}
+
+#[test]
+fn mock_default_sink_follows_configured_name() {
+    harness::run_mock("mock_default_sink_follows_configured_name", |manager, backend| {
+        add_sink(backend, "mock_sink_1");
+        add_sink(backend, "mock_sink_2");
+        let sink_1 = wait_for_node(&manager.sink_model(), "mock_sink_1");
+        let sink_2 = wait_for_node(&manager.sink_model(), "mock_sink_2");
+
+        for sink in [&sink_2, &sink_1] {
+            assert!(manager.set_default_configured_node_name("Audio/Sink", &sink.node_property::<String>("node.name").expect("node.name")));
+            wait_until("the default sink to change", || manager.default_configured_sink_node().as_ref() == Some(sink));
+        }
+    });
+}
+
+#[test]
+fn mock_unplugging_switches_route() {
+    harness::run_mock("mock_unplugging_switches_route", |manager, backend| {
+        let (device_id, _) = add_card(backend);
+        let device = mock_device(&manager.device_model(), device_id).expect("device listed");
+        let active_output = || device.backend().active_routes().iter().find(|route| route.direction == RouteDirection::Output).map(|route| route.index);
+
+        assert_eq!(active_output(), Some(SPEAKERS as i32));
+
+        backend.set_route_available(device_id, HEADPHONES, true);
+        assert_eq!(active_output(), Some(HEADPHONES as i32));
+        wait_until("the headphones to be selected", || device.route_index_output() == HEADPHONES);
+
+        backend.set_route_available(device_id, HEADPHONES, false);
+        assert_eq!(active_output(), Some(SPEAKERS as i32));
+        wait_until("the speakers to be selected", || device.route_index_output() == SPEAKERS);
+    });
+}
+
+#[test]
+fn mock_unplugging_keeps_output_muted() {
+    harness::run_mock("mock_unplugging_keeps_output_muted", |manager, backend| {
+        let settings = &manager.imp().settings;
+        settings.set_boolean("mute-on-unplug", true).expect("setting writable");
+
+        let (device_id, _) = add_card(backend);
+        let device = mock_device(&manager.device_model(), device_id).expect("device listed");
+        let output = || device.backend().active_routes().into_iter().find(|route| route.direction == RouteDirection::Output).expect("output route");
+
+        backend.set_route_available(device_id, HEADPHONES, true);
+        wait_until("the headphones to be selected", || device.route_index_output() == HEADPHONES);
+        backend.set_route_available(device_id, HEADPHONES, false);
+        wait_until("the speakers to be muted", || output().index == SPEAKERS as i32 && output().mute);
+
+        // Like the session manager restoring the saved state of the route.
+        let unmute = RouteProps { mute: Some(false), ..Default::default() };
+        device.backend().set_route(SPEAKERS as i32, 0, &unmute);
+        wait_until("the speakers to be muted again", || output().mute);
+
+        device.release_unplug_mute(0);
+        device.backend().set_route(SPEAKERS as i32, 0, &unmute);
+        wait_until("the speakers to be unmuted", || !output().mute);
+
+        settings.reset("mute-on-unplug");
+    });
+}
+
+#[test]
+fn mock_restart_repopulates_models() {
+    harness::run_mock("mock_restart_repopulates_models", |manager, backend| {
+        let (device_id, _) = add_card(backend);
+        let sink = wait_for_node(&manager.sink_model(), "mock_sink");
+
+        backend.restart();
+        assert!(manager.connected());
+        assert_eq!(manager.device_model().n_items(), 1);
+        assert_eq!(manager.sink_model().iter::<PwNodeObject>().map_while(Result::ok).filter(|node| node.name() == sink.name()).count(), 1);
+
+        let new_sink = wait_for_node(&manager.sink_model(), "mock_sink");
+        assert_ne!(new_sink, sink, "rows are rebuilt, not kept");
+        let device = mock_device(&manager.device_model(), device_id).expect("device listed again");
+        assert_eq!(new_sink.device().as_ref(), Some(&device));
+    });
+}
+
+#[test]
+fn mock_profiles_cover_running_nodes() {
+    harness::run_mock("mock_profiles_cover_running_nodes", |manager, backend| {
+        let idle = add_sink(backend, "mock_idle_sink");
+        let running = add_sink(backend, "mock_running_sink");
+        backend.set_node_running(running, true);
+
+        let profiles = manager.node_profiles();
+        assert!(profiles.iter().all(|profile| profile.id != idle));
+        let profile = profiles.iter().find(|profile| profile.id == running).expect("running node profiled");
+        assert_eq!(profile.name, "mock_running_sink");
+        assert!(profile.busy > 0 && profile.wait > 0);
+    });
+}
+
+fn add_stream(backend: &MockBackend, name: &str, media_class: &str) -> u32 {
+    backend.add_node(&[("node.name", name), ("application.name", "mock_app"), ("media.class", media_class)], &["FL", "FR"], None)
+}
+
+#[test]
+fn mock_application_volume_is_relative_and_per_direction() {
+    harness::run_mock("mock_application_volume_is_relative_and_per_direction", |manager, backend| {
+        let first_id = add_stream(backend, "mock_app_first", "Stream/Output/Audio");
+        add_stream(backend, "mock_app_capture", "Stream/Input/Audio");
+        let first = wait_for_node(&manager.stream_output_model(), "mock_app_first");
+        let capture = wait_for_node(&manager.stream_input_model(), "mock_app_capture");
+        wait_until("stream channels", || first.channel_volumes_vec().len() == 2 && capture.channel_volumes_vec().len() == 2);
+
+        let application = manager.get_application_for_node(&first).expect("application of the playback stream");
+        application.set_volume(0.5);
+        wait_until("the group volume to apply", || (first.volume() - 0.5).abs() < 0.001);
+        assert!((capture.volume() - 1.0).abs() < 0.001, "recording streams are a group of their own");
+
+        // The header follows the loudest stream, the scale chosen on it stays.
+        first.set_volume(0.2);
+        wait_until("the header to follow", || (application.volume() - 0.2).abs() < 0.001);
+
+        add_stream(backend, "mock_app_second", "Stream/Output/Audio");
+        let second = wait_for_node(&manager.stream_output_model(), "mock_app_second");
+        wait_until("the new stream to be scaled", || (second.volume() - 0.5).abs() < 0.001);
+
+        backend.remove_node(first_id);
+        wait_until("the header to drop the removed stream", || application.n_members() == 1);
+        assert!((application.volume() - 0.5).abs() < 0.001);
+    });
+}
+
+#[test]
+fn mock_own_streams_are_hidden_and_ungrouped() {
+    harness::run_mock("mock_own_streams_are_hidden_and_ungrouped", |manager, backend| {
+        backend.add_node(
+            &[
+                ("node.name", "pwvucontrol-sound-test"),
+                ("application.name", "pwvucontrol"),
+                ("application.id", APP_ID),
+                ("media.class", "Stream/Output/Audio"),
+            ],
+            &["FL", "FR"],
+            None,
+        );
+        let stream = wait_for_node(&manager.node_model(), "pwvucontrol-sound-test");
+        assert!(stream.hidden(), "the default hide rules match our own streams");
+        assert!(manager.get_application_for_node(&stream).is_none());
+    });
+}
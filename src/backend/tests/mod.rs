@@ -0,0 +1,13 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Backend tests against a private PipeWire instance or the mock backend, see [`harness`].
+
+mod defaults;
+mod harness;
+mod mock;
+mod models;
+mod spajson;
+mod spaparams;
+mod volume;
+
+pub(crate) use harness::current_manager;
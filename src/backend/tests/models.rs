@@ -0,0 +1,72 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use super::harness::{self, wait_for_node, wait_until, CAPTURE_STREAM, PLAYBACK_STREAM, SINK_1, SINK_2, SOURCE};
+use crate::backend::{NodeType, PwNodeGroupObject, PwProfileObject};
+use gtk::prelude::*;
+
+#[test]
+fn sinks_and_sources_are_listed() {
+    harness::run("sinks_and_sources_are_listed", |manager| {
+        for name in [SINK_1, SINK_2] {
+            let sink = wait_for_node(&manager.sink_model(), name);
+            assert_eq!(sink.nodetype(), NodeType::Sink);
+            wait_until("sink channels", || sink.channel_volumes_vec().len() == 2);
+        }
+
+        let source = wait_for_node(&manager.source_model(), SOURCE);
+        assert_eq!(source.nodetype(), NodeType::Source);
+        assert!(source.is_virtual());
+        wait_until("source channels", || source.channel_volumes_vec().len() == 2);
+    });
+}
+
+#[test]
+fn streams_are_listed() {
+    harness::run("streams_are_listed", |manager| {
+        let playback = wait_for_node(&manager.stream_output_model(), PLAYBACK_STREAM);
+        assert_eq!(playback.nodetype(), NodeType::StreamOutput);
+
+        let capture = wait_for_node(&manager.stream_input_model(), CAPTURE_STREAM);
+        assert_eq!(capture.nodetype(), NodeType::StreamInput);
+
+        // Sinks and sources are never part of the stream models.
+        assert!(harness::node_by_name(&manager.stream_output_model(), SINK_1).is_none());
+        assert!(harness::node_by_name(&manager.stream_input_model(), SOURCE).is_none());
+    });
+}
+
+#[test]
+fn streams_are_grouped_by_application() {
+    harness::run("streams_are_grouped_by_application", |manager| {
+        let playback = wait_for_node(&manager.stream_output_model(), PLAYBACK_STREAM);
+        let capture = wait_for_node(&manager.stream_input_model(), CAPTURE_STREAM);
+
+        let application = manager.get_application_for_node(&playback).expect("application for the playback stream");
+        let members = application.members();
+        assert!(members.iter().any(|member| member.name() == playback.name()));
+        assert_eq!(members.len(), 1, "playback and capture share an application name but not a group");
+
+        let capture_application = manager.get_application_for_node(&capture).expect("application for the capture stream");
+        assert_ne!(capture_application, application);
+        assert_eq!(capture_application.key(), application.key());
+
+        let applications = manager.application_model();
+        assert!(applications.iter::<PwNodeGroupObject>().map_while(Result::ok).any(|group| group == application));
+    });
+}
+
+#[test]
+fn devices_have_profiles() {
+    harness::run_with_card("devices_have_profiles", |_, device| {
+        assert_eq!(device.name().as_deref(), Some("Test Card"));
+        wait_until("the profiles to load", || device.profilemodel().n_items() > 1);
+
+        let profiles: Vec<PwProfileObject> = device.profilemodel().iter().map_while(Result::ok).collect();
+        assert!(profiles.iter().any(|profile| profile.name() == "off"), "no off profile");
+        assert!(profiles.iter().all(|profile| !profile.description().is_empty()));
+
+        wait_until("the current profile", || device.current_profile().is_some());
+        let current = device.current_profile().expect("current profile");
+        assert!(profiles.iter().any(|profile| profile.index() == current.index()));
+    });
+}
@@ -0,0 +1,31 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::backend::{json_object_string, json_string};
+use std::sync::Once;
+use wireplumber as wp;
+
+fn init() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| wp::core::Core::init_with_flags(wp::InitFlags::ALL));
+}
+
+#[test]
+fn default_node_name_is_read_with_any_spacing() {
+    init();
+    for value in [r#"{"name":"alsa_output.pci"}"#, r#"{ "name": "alsa_output.pci" }"#, "{ name = alsa_output.pci }"] {
+        assert_eq!(json_object_string(value, "name").as_deref(), Some("alsa_output.pci"), "{value}");
+    }
+    assert_eq!(json_object_string(r#"{ "name": "say \"hi\"" }"#, "name").as_deref(), Some("say \"hi\""));
+    assert!(json_object_string(r#"{ "other": "x" }"#, "name").is_none());
+    assert!(json_object_string(r#"{ "name": { "nested": "x" } }"#, "name").is_none());
+    assert!(json_object_string("", "name").is_none());
+}
+
+#[test]
+fn string_values_round_trip() {
+    init();
+    for value in ["alsa_output.pci", "say \"hi\"\\", "tab\tand\nnewline"] {
+        let json = format!("{{ \"name\": {} }}", json_string(value));
+        assert_eq!(json_object_string(&json, "name").as_deref(), Some(value), "{json}");
+    }
+}
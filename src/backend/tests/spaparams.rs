@@ -0,0 +1,138 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::backend::{parse_format, parse_profile, parse_props, parse_route, NodeProps, ParamAvailability, ParamError, RouteDirection};
+use std::sync::Once;
+use wireplumber as wp;
+use wp::spa::{SpaPod, SpaPodBuilder};
+
+// From spa/param/audio/raw.h
+const SPA_AUDIO_FORMAT_S16_LE: u32 = 0x103;
+const SPA_AUDIO_CHANNEL_FL: u32 = 3;
+const SPA_AUDIO_CHANNEL_FR: u32 = 4;
+
+fn init() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| wp::core::Core::init_with_flags(wp::InitFlags::ALL));
+}
+
+fn profile(index: i32, available: Option<u32>) -> SpaPod {
+    let builder = SpaPodBuilder::new_object("Spa:Pod:Object:Param:Profile", "EnumProfile");
+    builder.add_property("index");
+    builder.add_int(index);
+    builder.add_property("name");
+    builder.add_string("a2dp-sink");
+    builder.add_property("description");
+    builder.add_string("High Fidelity Playback");
+    if let Some(available) = available {
+        builder.add_property("available");
+        builder.add_id(available);
+    }
+    builder.end().expect("profile pod")
+}
+
+fn format(positions: Option<&[i32]>) -> SpaPod {
+    let builder = SpaPodBuilder::new_object("Spa:Pod:Object:Param:Format", "Format");
+    builder.add_property("mediaType");
+    builder.add_id(1);
+    builder.add_property("mediaSubtype");
+    builder.add_id(1);
+    builder.add_property("format");
+    builder.add_id(SPA_AUDIO_FORMAT_S16_LE);
+    builder.add_property("rate");
+    builder.add_int(48000);
+    builder.add_property("channels");
+    builder.add_int(2);
+    if let Some(positions) = positions {
+        let array = SpaPodBuilder::new_array();
+        for position in positions {
+            array.add_id(*position as u32);
+        }
+        builder.add_property("position");
+        builder.add_pod(&array.end().expect("position array"));
+    }
+    builder.end().expect("format pod")
+}
+
+#[test]
+fn profile_without_availability_is_unknown() {
+    init();
+
+    let parsed = parse_profile(&profile(1, Some(2))).expect("complete profile");
+    assert_eq!(parsed.index, 1);
+    assert_eq!(parsed.name, "a2dp-sink");
+    assert_eq!(parsed.availability, ParamAvailability::Yes);
+
+    let parsed = parse_profile(&profile(1, None)).expect("profile without availability");
+    assert_eq!(parsed.availability, ParamAvailability::Unknown);
+}
+
+#[test]
+fn profile_with_bad_index_is_an_error() {
+    init();
+
+    assert_eq!(parse_profile(&profile(-1, Some(2))).unwrap_err(), ParamError::Invalid("profile index"));
+
+    let not_an_object = SpaPod::new_int(1);
+    assert_eq!(parse_profile(&not_an_object).unwrap_err(), ParamError::NotAnObject);
+}
+
+#[test]
+fn route_without_direction_is_an_error() {
+    init();
+
+    let builder = SpaPodBuilder::new_object("Spa:Pod:Object:Param:Route", "EnumRoute");
+    builder.add_property("index");
+    builder.add_int(0);
+    builder.add_property("name");
+    builder.add_string("analog-output-headphones");
+    let pod = builder.end().expect("route pod");
+    assert_eq!(parse_route(&pod).unwrap_err(), ParamError::Missing("route direction"));
+
+    let builder = SpaPodBuilder::new_object("Spa:Pod:Object:Param:Route", "EnumRoute");
+    builder.add_property("index");
+    builder.add_int(0);
+    builder.add_property("direction");
+    builder.add_id(RouteDirection::Output.into());
+    builder.add_property("name");
+    builder.add_string("analog-output-headphones");
+    let route = parse_route(&builder.end().expect("route pod")).expect("route without description or profiles");
+    assert_eq!(route.description, "analog-output-headphones");
+    assert!(route.profiles.is_empty());
+    assert_eq!(route.availability, ParamAvailability::Unknown);
+}
+
+#[test]
+fn format_without_positions_gets_default_ones() {
+    init();
+
+    let parsed = parse_format(&format(Some(&[SPA_AUDIO_CHANNEL_FR as i32, SPA_AUDIO_CHANNEL_FL as i32]))).expect("format");
+    assert_eq!((parsed.rate, parsed.channels), (48000, 2));
+    assert_eq!(parsed.positions[..2], [SPA_AUDIO_CHANNEL_FR, SPA_AUDIO_CHANNEL_FL]);
+
+    let parsed = parse_format(&format(None)).expect("format without positions");
+    assert_eq!(parsed.positions[..2], [SPA_AUDIO_CHANNEL_FL, SPA_AUDIO_CHANNEL_FR]);
+}
+
+#[test]
+fn props_carry_channel_volumes_and_mute() {
+    init();
+
+    let volumes = SpaPodBuilder::new_array();
+    volumes.add_float(0.5);
+    volumes.add_float(0.25);
+    let builder = SpaPodBuilder::new_object("Spa:Pod:Object:Param:Props", "Props");
+    builder.add_property("channelVolumes");
+    builder.add_pod(&volumes.end().expect("volume array"));
+    builder.add_property("mute");
+    builder.add_boolean(true);
+
+    let mut props = NodeProps::default();
+    parse_props(&builder.end().expect("props pod"), &mut props).expect("props");
+    assert_eq!(props.channel_volumes, [0.5, 0.25]);
+    assert_eq!(props.mute, Some(true));
+
+    let builder = SpaPodBuilder::new_object("Spa:Pod:Object:Param:Props", "Props");
+    builder.add_property("mute");
+    builder.add_int(1);
+    assert_eq!(parse_props(&builder.end().expect("props pod"), &mut NodeProps::default()).unwrap_err(), ParamError::WrongType("mute"));
+}
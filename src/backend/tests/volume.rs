@@ -0,0 +1,60 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use super::harness::{self, wait_for_node, wait_until, PLAYBACK_STREAM, SINK_1, SOURCE};
+use crate::backend::PwNodeObject;
+
+const EPSILON: f32 = 0.001;
+
+/// What the backend reports for `node`, as a mute flag and channel volumes.
+fn backend_volume(node: &PwNodeObject) -> (bool, Vec<f32>) {
+    let volume = node.backend().volume().expect("node supports volume");
+    (volume.mute, volume.channel_volumes)
+}
+
+fn approx_eq(a: &[f32], b: &[f32]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| (a - b).abs() < EPSILON)
+}
+
+#[test]
+fn volume_round_trip() {
+    harness::run("volume_round_trip", |manager| {
+        let sink = wait_for_node(&manager.sink_model(), SINK_1);
+        wait_until("sink channels", || sink.channel_volumes_vec().len() == 2);
+
+        for volume in [0.25, 0.8] {
+            sink.set_volume(volume);
+            wait_until("the backend to report the volume", || approx_eq(&backend_volume(&sink).1, &[volume, volume]));
+            wait_until("the node to follow", || (sink.volume() - volume).abs() < EPSILON);
+        }
+    });
+}
+
+#[test]
+fn mute_round_trip() {
+    harness::run("mute_round_trip", |manager| {
+        let stream = wait_for_node(&manager.stream_output_model(), PLAYBACK_STREAM);
+
+        for mute in [true, false] {
+            stream.set_mute(mute);
+            wait_until("the backend to report the mute state", || backend_volume(&stream).0 == mute);
+            assert_eq!(stream.mute(), mute);
+        }
+    });
+}
+
+#[test]
+fn channel_volumes_round_trip() {
+    harness::run("channel_volumes_round_trip", |manager| {
+        let source = wait_for_node(&manager.source_model(), SOURCE);
+        wait_until("source channels", || source.channel_volumes_vec().len() == 2);
+
+        let volumes = [0.3, 0.6];
+        source.set_channel_volumes_vec(&volumes);
+        wait_until("the backend to report the channel volumes", || approx_eq(&backend_volume(&source).1, &volumes));
+        wait_until("the node to follow", || approx_eq(&source.channel_volumes_vec(), &volumes));
+        assert!((source.volume() - 0.6).abs() < EPSILON, "volume is the loudest channel");
+
+        source.set_channel_volumes_vec(&[1.0, 1.0]);
+        wait_until("the backend to report the reset", || approx_eq(&backend_volume(&source).1, &[1.0, 1.0]));
+    });
+}
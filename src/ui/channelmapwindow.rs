@@ -0,0 +1,149 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::backend::{NodeType, PwNodeObject};
+use adw::{prelude::*, subclass::prelude::*};
+use std::cell::RefCell;
+use wireplumber as wp;
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, Default, gtk::CompositeTemplate, glib::Properties)]
+    #[template(resource = "/com/saivert/pwvucontrol/gtk/channelmapwindow.ui")]
+    #[properties(wrapper_type = super::PwChannelMapWindow)]
+    pub struct PwChannelMapWindow {
+        #[property(get, set, construct_only)]
+        pub(super) node_object: RefCell<Option<PwNodeObject>>,
+
+        #[template_child]
+        pub window_title: TemplateChild<adw::WindowTitle>,
+        #[template_child]
+        pub channel_map_group: TemplateChild<adw::PreferencesGroup>,
+        #[template_child]
+        pub upmix_row: TemplateChild<adw::SwitchRow>,
+        #[template_child]
+        pub normalize_row: TemplateChild<adw::SwitchRow>,
+        #[template_child]
+        pub lfe_cutoff_row: TemplateChild<adw::SpinRow>,
+
+        /// `Spa:Enum:AudioChannel` ids in the order they are listed in the position rows.
+        pub(super) position_ids: RefCell<Vec<u32>>,
+        pub(super) position_rows: RefCell<Vec<adw::ComboRow>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for PwChannelMapWindow {
+        const NAME: &'static str = "PwChannelMapWindow";
+        type Type = super::PwChannelMapWindow;
+        type ParentType = adw::Window;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for PwChannelMapWindow {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            let node = self.obj().node_object().expect("node object");
+            self.window_title.set_subtitle(&node.name());
+
+            node.bind_property("channelmix-upmix", &self.upmix_row.get(), "active").sync_create().bidirectional().build();
+            node.bind_property("channelmix-normalize", &self.normalize_row.get(), "active").sync_create().bidirectional().build();
+            node.bind_property("channelmix-lfe-cutoff", &self.lfe_cutoff_row.get(), "value")
+                .transform_to(|_, cutoff: f32| Some(cutoff as f64))
+                .transform_from(|_, cutoff: f64| Some(cutoff as f32))
+                .sync_create()
+                .bidirectional()
+                .build();
+
+            self.channel_map_group.set_visible(matches!(node.nodetype(), NodeType::Sink | NodeType::Source));
+            self.setup_position_rows(&node);
+        }
+    }
+    impl WidgetImpl for PwChannelMapWindow {}
+    impl WindowImpl for PwChannelMapWindow {}
+    impl AdwWindowImpl for PwChannelMapWindow {}
+
+    #[gtk::template_callbacks]
+    impl PwChannelMapWindow {
+        #[template_callback]
+        fn swap_clicked(&self, _button: &gtk::Button) {
+            let ids = self.position_ids.borrow();
+            let index_of = |name: &str| ids.iter().position(|id| channel_name(*id).as_deref() == Some(name));
+            let (Some(left), Some(right)) = (index_of("FL"), index_of("FR")) else {
+                return;
+            };
+
+            for row in self.position_rows.borrow().iter() {
+                match row.selected() as usize {
+                    selected if selected == left => row.set_selected(right as u32),
+                    selected if selected == right => row.set_selected(left as u32),
+                    _ => {}
+                }
+            }
+        }
+
+        #[template_callback]
+        fn apply_clicked(&self, _button: &gtk::Button) {
+            let ids = self.position_ids.borrow();
+            let positions: Vec<u32> =
+                self.position_rows.borrow().iter().filter_map(|row| ids.get(row.selected() as usize).copied()).collect();
+
+            if let Some(node) = self.obj().node_object() {
+                node.set_channel_positions(&positions);
+            }
+        }
+
+        fn setup_position_rows(&self, node: &PwNodeObject) {
+            let t_audiochannel = wp::spa::SpaIdTable::from_name("Spa:Enum:AudioChannel").expect("audio channel type");
+            let (ids, names): (Vec<u32>, Vec<String>) = t_audiochannel
+                .values()
+                .into_iter()
+                .filter_map(|value| Some((value.number(), value.short_name()?.to_string())))
+                .unzip();
+            let model = gtk::StringList::new(&names.iter().map(String::as_str).collect::<Vec<_>>());
+
+            let mut rows = Vec::new();
+            for (index, position) in node.channel_positions().iter().enumerate() {
+                let row = adw::ComboRow::builder()
+                    .title(formatx::formatx!(gettextrs::gettext("Channel {}"), index + 1).unwrap_or_default())
+                    .model(&model)
+                    .build();
+                if let Some(selected) = ids.iter().position(|id| id == position) {
+                    row.set_selected(selected as u32);
+                }
+                self.channel_map_group.add(&row);
+                rows.push(row);
+            }
+
+            self.position_ids.replace(ids);
+            self.position_rows.replace(rows);
+        }
+    }
+
+    fn channel_name(id: u32) -> Option<String> {
+        let t_audiochannel = wp::spa::SpaIdTable::from_name("Spa:Enum:AudioChannel")?;
+        t_audiochannel.find_value(id)?.short_name().map(|name| name.to_string())
+    }
+}
+
+glib::wrapper! {
+    /// Edits the channel map of a sink or source and the channelmix settings of a node.
+    pub struct PwChannelMapWindow(ObjectSubclass<imp::PwChannelMapWindow>)
+        @extends gtk::Widget, gtk::Window, adw::Window,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Native, gtk::Root, gtk::ShortcutManager;
+}
+
+impl PwChannelMapWindow {
+    pub fn new(parent: Option<&gtk::Window>, node: &PwNodeObject) -> Self {
+        glib::Object::builder().property("transient-for", parent).property("node-object", node).build()
+    }
+}
@@ -1,8 +1,12 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use crate::{backend::PwDeviceObject, ui::PwProfileDropDown};
+use crate::{
+    backend::{codec_display_name, BluetoothCodecProfile, BluetoothMode, PwDeviceObject, PwvucontrolManager},
+    ui::{PwProfileDropDown, PwPropertiesWindow},
+};
+use glib::{clone, SignalHandlerId};
 use gtk::{prelude::*, subclass::prelude::*};
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 
 mod imp {
     use super::*;
@@ -17,9 +21,35 @@ mod imp {
         pub label: TemplateChild<gtk::Label>,
         #[template_child]
         pub profile_dropdown: TemplateChild<PwProfileDropDown>,
+        #[template_child]
+        pub bluetooth_panel: TemplateChild<gtk::Grid>,
+        #[template_child]
+        pub hifi_button: TemplateChild<gtk::ToggleButton>,
+        #[template_child]
+        pub headset_button: TemplateChild<gtk::ToggleButton>,
+        #[template_child]
+        pub codec_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub codec_dropdown: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub battery_title: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub battery_box: TemplateChild<gtk::Box>,
+        #[template_child]
+        pub battery_levelbar: TemplateChild<gtk::LevelBar>,
+        #[template_child]
+        pub battery_label: TemplateChild<gtk::Label>,
 
         #[property(get, set, construct_only)]
         pub deviceobject: RefCell<Option<PwDeviceObject>>,
+
+        /// Codec profiles in the order they are listed in the codec dropdown.
+        pub(super) codec_profiles: RefCell<Vec<BluetoothCodecProfile>>,
+        pub(super) block_bluetooth: Cell<bool>,
+        pub(super) node_model_handler: RefCell<Option<SignalHandlerId>>,
+        /// Bluetooth handlers on the device object, which can outlive this row.
+        pub(super) device_handlers: RefCell<Vec<SignalHandlerId>>,
+        pub(super) codec_handler: RefCell<Option<SignalHandlerId>>,
     }
 
     #[glib::object_subclass]
@@ -30,6 +60,14 @@ mod imp {
 
         fn class_init(klass: &mut Self::Class) {
             klass.bind_template();
+            klass.bind_template_callbacks();
+
+            klass.install_action("device.properties", None, |widget, _, _| {
+                if let Some(device) = widget.deviceobject() {
+                    let parent = widget.root().and_downcast::<gtk::Window>();
+                    PwPropertiesWindow::new(parent.as_ref(), &device.name().unwrap_or_default(), device.backend().dump()).present();
+                }
+            });
         }
 
         fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
@@ -54,12 +92,121 @@ mod imp {
                 .build();
 
             self.profile_dropdown.set_deviceobject(obj.deviceobject());
+
+            if deviceobject.is_bluetooth() {
+                self.setup_bluetooth(&deviceobject);
+            }
+        }
+
+        fn dispose(&self) {
+            if let Some(handler) = self.node_model_handler.take() {
+                PwvucontrolManager::default().node_model().disconnect(handler);
+            }
+            if let Some(deviceobject) = self.obj().deviceobject() {
+                for handler in self.device_handlers.take() {
+                    deviceobject.disconnect(handler);
+                }
+            }
+            if let Some(handler) = self.codec_handler.take() {
+                self.codec_dropdown.disconnect(handler);
+            }
         }
     }
     impl WidgetImpl for PwDeviceBox {}
     impl ListBoxRowImpl for PwDeviceBox {}
 
-    impl PwDeviceBox {}
+    #[gtk::template_callbacks]
+    impl PwDeviceBox {
+        #[template_callback]
+        fn mode_toggled(&self, button: &gtk::ToggleButton) {
+            if self.block_bluetooth.get() || !button.is_active() {
+                return;
+            }
+            let mode = if button == &self.hifi_button.get() { BluetoothMode::HighFidelity } else { BluetoothMode::Headset };
+            if let Some(deviceobject) = self.obj().deviceobject() {
+                deviceobject.set_bluetooth_mode(mode);
+            }
+        }
+
+        fn codec_selected(&self, dropdown: &gtk::DropDown) {
+            if self.block_bluetooth.get() {
+                return;
+            }
+            let profiles = self.codec_profiles.borrow();
+            let (Some(profile), Some(deviceobject)) = (profiles.get(dropdown.selected() as usize), self.obj().deviceobject()) else {
+                return;
+            };
+            deviceobject.set_profile(profile.profile_index as i32);
+        }
+
+        fn setup_bluetooth(&self, deviceobject: &PwDeviceObject) {
+            self.bluetooth_panel.set_visible(true);
+
+            let handler = deviceobject.connect_profile_index_notify(clone!(@weak self as widget => move |_| widget.update_bluetooth()));
+            let mut handlers = vec![handler];
+            for signal in ["post-update-profile", "properties-changed", "battery-changed"] {
+                handlers.push(deviceobject.connect_local(
+                    signal,
+                    false,
+                    clone!(@weak self as widget => @default-return None, move |_| {
+                        widget.update_bluetooth();
+                        None
+                    }),
+                ));
+            }
+            self.device_handlers.replace(handlers);
+
+            let handler =
+                self.codec_dropdown.connect_selected_notify(clone!(@weak self as widget => move |dropdown| widget.codec_selected(dropdown)));
+            self.codec_handler.replace(Some(handler));
+
+            // The codec is reported by the nodes, which are recreated after a profile switch.
+            let handler = PwvucontrolManager::default()
+                .node_model()
+                .connect_items_changed(clone!(@weak self as widget => move |_, _, _, _| widget.update_bluetooth()));
+            self.node_model_handler.replace(Some(handler));
+
+            self.update_bluetooth();
+        }
+
+        fn update_bluetooth(&self) {
+            let Some(deviceobject) = self.obj().deviceobject() else {
+                return;
+            };
+            self.block_bluetooth.set(true);
+
+            let mode = deviceobject.bluetooth_mode();
+            self.hifi_button.set_active(mode == BluetoothMode::HighFidelity);
+            self.headset_button.set_active(mode == BluetoothMode::Headset);
+            self.hifi_button.set_sensitive(deviceobject.has_bluetooth_mode(BluetoothMode::HighFidelity));
+            self.headset_button.set_sensitive(deviceobject.has_bluetooth_mode(BluetoothMode::Headset));
+
+            let codec = deviceobject.bluetooth_codec();
+            self.codec_label.set_label(&codec.as_deref().map(codec_display_name).unwrap_or_else(|| "—".to_string()));
+
+            let profiles = deviceobject.codec_profiles(mode);
+            let names: Vec<String> = profiles.iter().map(|profile| codec_display_name(&profile.codec)).collect();
+            self.codec_dropdown.set_model(Some(&gtk::StringList::new(&names.iter().map(String::as_str).collect::<Vec<_>>())));
+            let current = deviceobject.current_profile().map(|profile| profile.index());
+            if let Some(selected) = profiles.iter().position(|profile| Some(profile.profile_index) == current) {
+                self.codec_dropdown.set_selected(selected as u32);
+            } else {
+                self.codec_dropdown.set_selected(gtk::INVALID_LIST_POSITION);
+            }
+            self.codec_dropdown.set_visible(profiles.len() > 1);
+            self.codec_profiles.replace(profiles);
+
+            let battery = deviceobject.battery_level();
+            self.battery_title.set_visible(battery.is_some());
+            self.battery_box.set_visible(battery.is_some());
+            if let Some(battery) = battery {
+                self.battery_levelbar.set_value(battery.min(100) as f64);
+                self.battery_label.set_label(&format!("{battery}%"));
+            }
+
+            self.block_bluetooth.set(false);
+        }
+    }
 }
 
 glib::wrapper! {
@@ -0,0 +1,115 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! The sound played on volume changes, either built in or read from a WAV file.
+
+use std::{f32::consts::PI, path::Path};
+
+use anyhow::{anyhow, bail};
+use pipewire::spa;
+
+use crate::ui::soundtest::STEREO;
+
+const BUILTIN_RATE: u32 = 48000;
+const BUILTIN_FREQUENCY: f32 = 880.0;
+const BUILTIN_LENGTH: f32 = 0.08;
+const BUILTIN_AMPLITUDE: f32 = 0.4;
+
+/// Longer files are cut, this is meant for short blips.
+const MAX_SECONDS: u32 = 3;
+
+/// Higher rates are taken for a broken file.
+const MAX_RATE: u32 = 384000;
+
+const WAVE_FORMAT_PCM: u16 = 1;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 3;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xfffe;
+
+#[derive(Debug, Clone)]
+pub struct FeedbackSound {
+    pub rate: u32,
+    pub positions: Vec<u32>,
+    /// Interleaved samples.
+    pub samples: Vec<f32>,
+}
+
+impl FeedbackSound {
+    /// A short decaying tone.
+    pub fn builtin() -> Self {
+        let frames = (BUILTIN_RATE as f32 * BUILTIN_LENGTH) as usize;
+        let samples = (0..frames)
+            .map(|frame| {
+                let t = frame as f32 / BUILTIN_RATE as f32;
+                let envelope = (1.0 - t / BUILTIN_LENGTH).powi(3);
+                (2.0 * PI * BUILTIN_FREQUENCY * t).sin() * BUILTIN_AMPLITUDE * envelope
+            })
+            .collect();
+
+        Self { rate: BUILTIN_RATE, positions: vec![spa::sys::SPA_AUDIO_CHANNEL_MONO], samples }
+    }
+
+    /// Reads a mono or stereo WAV file, with 8 to 32 bit integer or 32 bit float samples.
+    pub fn load(path: &Path) -> Result<Self, anyhow::Error> {
+        Self::from_wav(&std::fs::read(path)?)
+    }
+
+    fn from_wav(bytes: &[u8]) -> Result<Self, anyhow::Error> {
+        if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+            bail!("not a WAV file");
+        }
+
+        let mut format = None;
+        let mut data = None;
+        let mut chunks = &bytes[12..];
+        while chunks.len() >= 8 {
+            let id = &chunks[0..4];
+            let size = u32::from_le_bytes(chunks[4..8].try_into().unwrap()) as usize;
+            let body = chunks.get(8..8 + size).unwrap_or(&chunks[8..]);
+            match id {
+                b"fmt " => format = Some(body),
+                b"data" => data = Some(body),
+                _ => {}
+            }
+            // Chunks are padded to an even size.
+            chunks = chunks.get(8 + size + size % 2..).unwrap_or_default();
+        }
+
+        let format = format.filter(|format| format.len() >= 16).ok_or_else(|| anyhow!("no format chunk"))?;
+        let data = data.ok_or_else(|| anyhow!("no data chunk"))?;
+
+        let mut tag = u16::from_le_bytes([format[0], format[1]]);
+        let channels = u16::from_le_bytes([format[2], format[3]]) as usize;
+        let rate = u32::from_le_bytes(format[4..8].try_into().unwrap());
+        let bits = u16::from_le_bytes([format[14], format[15]]);
+        if tag == WAVE_FORMAT_EXTENSIBLE && format.len() >= 26 {
+            // The sub format GUID starts with the plain format tag.
+            tag = u16::from_le_bytes([format[24], format[25]]);
+        }
+
+        let positions = match channels {
+            1 => vec![spa::sys::SPA_AUDIO_CHANNEL_MONO],
+            2 => STEREO.to_vec(),
+            _ => bail!("{channels} channels, only mono and stereo are supported"),
+        };
+        if rate == 0 {
+            bail!("no sample rate");
+        }
+        if rate > MAX_RATE {
+            bail!("sample rate {rate} is too high");
+        }
+
+        let width = (bits as usize).div_ceil(8);
+        let decode: fn(&[u8]) -> f32 = match (tag, width) {
+            (WAVE_FORMAT_PCM, 1) => |b| (b[0] as f32 - 128.0) / 128.0,
+            (WAVE_FORMAT_PCM, 2) => |b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0,
+            (WAVE_FORMAT_PCM, 3) => |b| (i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8) as f32 / 8388608.0,
+            (WAVE_FORMAT_PCM, 4) => |b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32 / 2147483648.0,
+            (WAVE_FORMAT_IEEE_FLOAT, 4) => |b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+            _ => bail!("unsupported sample format {tag} with {bits} bits"),
+        };
+
+        let max_samples = (rate * MAX_SECONDS) as usize * channels;
+        let samples = data.chunks_exact(width).take(max_samples).map(decode).collect();
+
+        Ok(Self { rate, positions, samples })
+    }
+}
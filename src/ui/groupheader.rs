@@ -0,0 +1,95 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::{backend::PwNodeGroupObject, ui::PwVolumeScale};
+use gtk::{prelude::*, subclass::prelude::*};
+use std::cell::RefCell;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, gtk::CompositeTemplate)]
+    #[template(resource = "/com/saivert/pwvucontrol/gtk/groupheader.ui")]
+    pub struct PwGroupHeader {
+        pub(super) group: RefCell<Option<PwNodeGroupObject>>,
+        pub(super) bindings: RefCell<Vec<glib::Binding>>,
+
+        #[template_child]
+        pub icon: TemplateChild<gtk::Image>,
+        #[template_child]
+        pub title_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub volume_scale: TemplateChild<PwVolumeScale>,
+        #[template_child]
+        pub mutebtn: TemplateChild<gtk::ToggleButton>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for PwGroupHeader {
+        const NAME: &'static str = "PwGroupHeader";
+        type Type = super::PwGroupHeader;
+        type ParentType = gtk::Box;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for PwGroupHeader {
+        fn dispose(&self) {
+            self.obj().unbind_group();
+        }
+    }
+    impl WidgetImpl for PwGroupHeader {}
+    impl BoxImpl for PwGroupHeader {}
+}
+
+glib::wrapper! {
+    /// List box header showing the streams of a group with a volume and mute control for all of them.
+    pub struct PwGroupHeader(ObjectSubclass<imp::PwGroupHeader>)
+        @extends gtk::Box, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Orientable;
+}
+
+impl PwGroupHeader {
+    pub(crate) fn new() -> Self {
+        glib::Object::builder().build()
+    }
+
+    pub(crate) fn group(&self) -> Option<PwNodeGroupObject> {
+        self.imp().group.borrow().clone()
+    }
+
+    pub(crate) fn set_group(&self, title: &str, group: &PwNodeGroupObject) {
+        let imp = self.imp();
+
+        imp.title_label.set_label(title);
+        if imp.group.borrow().as_ref() == Some(group) {
+            return;
+        }
+        self.unbind_group();
+
+        imp.bindings.replace(vec![
+            group.bind_property("volume", &imp.volume_scale.get(), "volume").sync_create().bidirectional().build(),
+            group.bind_property("mute", &imp.mutebtn.get(), "active").sync_create().bidirectional().build(),
+            group.bind_property("iconname", &imp.icon.get(), "icon-name").sync_create().build(),
+        ]);
+        imp.group.replace(Some(group.clone()));
+    }
+
+    fn unbind_group(&self) {
+        for binding in self.imp().bindings.take() {
+            binding.unbind();
+        }
+        self.imp().group.take();
+    }
+}
+
+impl Default for PwGroupHeader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
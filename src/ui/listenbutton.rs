@@ -0,0 +1,191 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::{
+    backend::{NodeType, PwNodeObject, PwvucontrolManager},
+    pwvucontrol_warning,
+    ui::{loopback::Loopback, soundtest::STEREO, WithDefaultListModel},
+};
+use glib::{clone, closure_local};
+use gtk::{prelude::*, subclass::prelude::*};
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+const LISTEN_RATE: u32 = 48000;
+
+mod imp {
+    use super::*;
+
+    /// Toggles listening to a source or recording stream, with the sink and volume to listen with.
+    #[derive(Default, gtk::CompositeTemplate, glib::Properties)]
+    #[properties(wrapper_type = super::PwListenButton)]
+    #[template(resource = "/com/saivert/pwvucontrol/gtk/listenbutton.ui")]
+    pub struct PwListenButton {
+        #[property(get, set, nullable)]
+        pub(super) nodeobj: RefCell<Option<PwNodeObject>>,
+
+        #[template_child]
+        pub listen_toggle: TemplateChild<gtk::ToggleButton>,
+        #[template_child]
+        pub sink_dropdown: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub volume_scale: TemplateChild<gtk::Scale>,
+
+        pub(super) sink_model: RefCell<Option<WithDefaultListModel>>,
+        /// Linear gain, shared with the loopback so volume changes apply while listening.
+        pub(super) gain: Rc<Cell<f32>>,
+        pub(super) loopback: RefCell<Option<Loopback>>,
+        /// The node the running loopback captures from.
+        pub(super) listening_to: Cell<Option<u32>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for PwListenButton {
+        const NAME: &'static str = "PwListenButton";
+        type Type = super::PwListenButton;
+        type ParentType = gtk::Widget;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for PwListenButton {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            self.sink_dropdown.set_expression(Some(gtk::ClosureExpression::new::<Option<String>>(
+                gtk::Expression::NONE,
+                closure_local!(move |item: glib::Object| {
+                    if let Some(item) = item.downcast_ref::<PwNodeObject>() {
+                        Some(item.name())
+                    } else {
+                        item.downcast_ref::<gtk::StringObject>().map(|item| item.string().to_string())
+                    }
+                }),
+            )));
+            self.sink_dropdown.connect_selected_notify(clone!(@weak self as widget => move |_| {
+                widget.restart();
+            }));
+
+            // Same curve as the volume sliders.
+            self.gain.set((self.volume_scale.value() as f32).powi(3));
+            self.volume_scale.connect_value_changed(clone!(@weak self as widget => move |scale| {
+                widget.gain.set((scale.value() as f32).powi(3));
+            }));
+
+            // Recording streams are listened to through their source, which changes with their target or the default.
+            let obj = self.obj();
+            let manager = PwvucontrolManager::default();
+            manager.connect_closure(
+                "metadata-changed",
+                false,
+                closure_local!(@watch obj => move |_: PwvucontrolManager, id: u32, key: String| {
+                    let imp = obj.imp();
+                    if key.starts_with("target.") && imp.nodeobj.borrow().as_ref().is_some_and(|node| node.boundid() == id) {
+                        imp.follow_source();
+                    }
+                }),
+            );
+            manager.connect_closure(
+                "default-nodes-changed",
+                false,
+                closure_local!(@watch obj => move |_: PwvucontrolManager| obj.imp().follow_source()),
+            );
+        }
+
+        fn dispose(&self) {
+            self.loopback.take();
+            self.dispose_template();
+        }
+    }
+
+    impl WidgetImpl for PwListenButton {
+        fn map(&self) {
+            self.parent_map();
+
+            // Most rows are never listened to, so the sink list is only set up once a button is shown.
+            if self.sink_model.borrow().is_none() {
+                let model = WithDefaultListModel::new(Some(&PwvucontrolManager::default().sink_model()));
+                self.sink_dropdown.set_model(Some(&model));
+                self.sink_model.replace(Some(model));
+            }
+        }
+    }
+
+    #[gtk::template_callbacks]
+    impl PwListenButton {
+        #[template_callback]
+        fn listen_toggled(&self, _button: &gtk::ToggleButton) {
+            self.restart();
+        }
+    }
+
+    impl PwListenButton {
+        /// Starts listening anew with the current settings, or stops when the toggle is off.
+        fn restart(&self) {
+            self.loopback.take();
+            self.listening_to.set(None);
+            if !self.listen_toggle.is_active() {
+                return;
+            }
+
+            let Some(source) = self.source() else {
+                pwvucontrol_warning!("Nothing to listen to");
+                self.listen_toggle.set_active(false);
+                return;
+            };
+            let sink = self.sink_dropdown.selected_item().and_downcast::<PwNodeObject>().map(|sink| sink.boundid());
+
+            let format = PwvucontrolManager::default().get_node_by_id(source).and_then(|node| node.format());
+            let rate = format.map(|format| format.rate).filter(|rate| *rate > 0).unwrap_or(LISTEN_RATE);
+            let positions: Vec<u32> = format
+                .map(|format| format.positions[..(format.channels as usize).min(64)].to_vec())
+                .filter(|positions| !positions.is_empty())
+                .unwrap_or_else(|| STEREO.to_vec());
+
+            match Loopback::new(source, sink, rate, &positions, self.gain.clone()) {
+                Ok(loopback) => {
+                    self.loopback.replace(Some(loopback));
+                    self.listening_to.set(Some(source));
+                }
+                Err(e) => {
+                    pwvucontrol_warning!("Cannot listen to {source}: {e}");
+                    self.listen_toggle.set_active(false);
+                }
+            }
+        }
+
+        /// Starts listening to the new source when the one being listened to is no longer the right one.
+        fn follow_source(&self) {
+            if self.loopback.borrow().is_some() && self.source() != self.listening_to.get() {
+                self.restart();
+            }
+        }
+
+        /// The node to capture from, recording streams are listened to through the source they record.
+        fn source(&self) -> Option<u32> {
+            let node = self.nodeobj.borrow().clone()?;
+            match node.nodetype() {
+                NodeType::Source => Some(node.boundid()),
+                NodeType::StreamInput => {
+                    node.default_target().map(|target| target.boundid()).or_else(|| PwvucontrolManager::default().default_node_id("Audio/Source"))
+                }
+                _ => None,
+            }
+        }
+    }
+}
+
+glib::wrapper! {
+    pub struct PwListenButton(ObjectSubclass<imp::PwListenButton>)
+        @extends gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget;
+}
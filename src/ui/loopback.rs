@@ -0,0 +1,147 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Listening to a source through a sink, like `pw-loopback` but with a volume of its own.
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::VecDeque,
+    fmt::Debug,
+    rc::Rc,
+};
+
+use pipewire::{properties::*, spa::utils::Direction, stream::*};
+
+use crate::{
+    config::APP_ID,
+    ui::soundtest::{create_audio_format_pod, StreamLoop},
+};
+
+/// Captured audio kept for playback, more than this is dropped so the delay doesn't grow.
+const MAX_DELAY_MS: usize = 200;
+
+/// Captures from one node and plays it back on another.
+pub struct Loopback {
+    capture: Option<Stream>,
+    playback: Option<Stream>,
+    _capture_listener: StreamListener<()>,
+    _playback_listener: StreamListener<()>,
+    _loop: StreamLoop,
+}
+
+impl Debug for Loopback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Loopback")
+    }
+}
+
+impl Loopback {
+    /// Plays what `source` captures on `sink`, or wherever the session manager puts it when `None`.
+    /// `gain` is read on every buffer, so the volume can change while listening.
+    pub fn new(source: u32, sink: Option<u32>, rate: u32, positions: &[u32], gain: Rc<Cell<f32>>) -> Result<Self, anyhow::Error> {
+        let stream_loop = StreamLoop::new()?;
+        let channels = positions.len();
+        let max_samples = rate as usize * MAX_DELAY_MS / 1000 * channels;
+        let queue: Rc<RefCell<VecDeque<f32>>> = Default::default();
+
+        let capture_props = properties! {
+            "media.type" => "Audio",
+            "media.category" => "Capture",
+            "media.name" => "Listen",
+            "node.name" => "pwvucontrol-listen-capture",
+            "application.id" => APP_ID,
+        };
+        let capture = Stream::new(&stream_loop.core, "listen-capture", capture_props)?;
+
+        let capture_listener = capture
+            .add_local_listener::<()>()
+            .process({
+                let queue = queue.clone();
+                move |stream, _| {
+                    let Some(mut buffer) = stream.dequeue_buffer() else {
+                        return;
+                    };
+                    let data = &mut buffer.datas_mut()[0];
+                    let offset = data.chunk().offset() as usize;
+                    let size = data.chunk().size() as usize;
+
+                    if let Some(bytes) = data.data().and_then(|slice| slice.get(offset..offset + size)) {
+                        let gain = gain.get();
+                        let mut queue = queue.borrow_mut();
+                        queue.extend(bytes.chunks_exact(std::mem::size_of::<f32>()).map(|b| f32::from_le_bytes(b.try_into().unwrap()) * gain));
+                        // Whole frames only, so the channels stay in place.
+                        let excess = queue.len().saturating_sub(max_samples);
+                        queue.drain(..excess - excess % channels);
+                    }
+                }
+            })
+            .register()?;
+
+        let playback_props = properties! {
+            "media.type" => "Audio",
+            "media.category" => "Playback",
+            "media.name" => "Listen",
+            "node.name" => "pwvucontrol-listen-playback",
+            "application.id" => APP_ID,
+        };
+        let playback = Stream::new(&stream_loop.core, "listen-playback", playback_props)?;
+
+        let playback_listener = playback
+            .add_local_listener::<()>()
+            .process(move |stream, _| {
+                let Some(mut buffer) = stream.dequeue_buffer() else {
+                    return;
+                };
+                let stride = channels * std::mem::size_of::<f32>();
+                let data = &mut buffer.datas_mut()[0];
+
+                // Only what was captured, silence fills in when the graph asks for more.
+                let mut frames = 0;
+                if let Some(slice) = data.data() {
+                    let mut queue = queue.borrow_mut();
+                    frames = (slice.len() / stride).min(queue.len() / channels);
+                    let samples = queue.drain(..frames * channels);
+                    for (bytes, sample) in slice[..frames * stride].chunks_exact_mut(std::mem::size_of::<f32>()).zip(samples) {
+                        bytes.copy_from_slice(&sample.to_le_bytes());
+                    }
+                }
+
+                let chunk = data.chunk_mut();
+                *chunk.offset_mut() = 0;
+                *chunk.stride_mut() = stride as _;
+                *chunk.size_mut() = (frames * stride) as _;
+            })
+            .register()?;
+
+        let mut capture_buffer: Vec<u8> = Vec::new();
+        capture.connect(
+            Direction::Input,
+            Some(source),
+            StreamFlags::AUTOCONNECT | StreamFlags::MAP_BUFFERS | StreamFlags::DONT_RECONNECT,
+            &mut [create_audio_format_pod(&mut capture_buffer, rate, positions)],
+        )?;
+
+        let mut playback_buffer: Vec<u8> = Vec::new();
+        playback.connect(
+            Direction::Output,
+            sink,
+            StreamFlags::AUTOCONNECT | StreamFlags::MAP_BUFFERS | StreamFlags::DONT_RECONNECT,
+            &mut [create_audio_format_pod(&mut playback_buffer, rate, positions)],
+        )?;
+
+        Ok(Self {
+            capture: Some(capture),
+            playback: Some(playback),
+            _capture_listener: capture_listener,
+            _playback_listener: playback_listener,
+            _loop: stream_loop,
+        })
+    }
+}
+
+impl Drop for Loopback {
+    fn drop(&mut self) {
+        for stream in [self.capture.take(), self.playback.take()].into_iter().flatten() {
+            let _ = stream.disconnect();
+        }
+    }
+}
@@ -1,11 +1,21 @@
 mod channelbox;
+mod channelmapwindow;
 mod devicebox;
+mod feedbacksound;
+mod groupheader;
 mod levelprovider;
+mod listenbutton;
+mod loopback;
+mod outputswitcher;
 mod peakmeter;
+mod performanceview;
+mod preferences;
 mod profile_dropdown;
+mod propertieswindow;
 mod profilerow;
 mod route_dropdown;
 mod sinkbox;
+mod soundtest;
 mod stream_dropdown;
 mod streambox;
 mod volumebox;
@@ -15,12 +25,21 @@ mod withdefaultlistmodel;
 mod utils;
 
 pub use channelbox::PwChannelBox;
+pub use channelmapwindow::PwChannelMapWindow;
+pub use feedbacksound::FeedbackSound;
+pub use groupheader::PwGroupHeader;
 pub use levelprovider::LevelbarProvider;
+pub use listenbutton::PwListenButton;
+pub use outputswitcher::PwOutputSwitcher;
 pub use peakmeter::PwPeakMeter;
+pub use performanceview::PwPerformanceView;
+pub use preferences::PwPreferencesWindow;
 pub use profile_dropdown::PwProfileDropDown;
+pub use propertieswindow::PwPropertiesWindow;
 pub use profilerow::PwProfileRow;
 pub use route_dropdown::PwRouteDropDown;
 pub use sinkbox::PwSinkBox;
+pub use soundtest::{SoundPlayer, SoundRecorder};
 pub use stream_dropdown::PwStreamDropDown;
 pub use streambox::PwStreamBox;
 pub use volumebox::PwVolumeBox;
@@ -0,0 +1,154 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::{
+    backend::{PwNodeFilterModel, PwNodeObject, PwvucontrolManager},
+    config::APP_ID,
+};
+use glib::{clone, SignalHandlerId};
+use gtk::{gio, prelude::*, subclass::prelude::*};
+use std::cell::{Cell, RefCell};
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, gtk::CompositeTemplate)]
+    #[template(resource = "/com/saivert/pwvucontrol/gtk/outputswitcher.ui")]
+    pub struct PwOutputSwitcher {
+        #[template_child]
+        pub sink_list: TemplateChild<gtk::ListBox>,
+        #[template_child]
+        pub source_list: TemplateChild<gtk::ListBox>,
+        #[template_child]
+        pub move_streams_switch: TemplateChild<gtk::Switch>,
+
+        pub(super) block_toggled: Cell<bool>,
+        pub(super) default_nodes_handler: RefCell<Option<SignalHandlerId>>,
+
+        pub settings: gio::Settings,
+    }
+
+    impl Default for PwOutputSwitcher {
+        fn default() -> Self {
+            Self {
+                sink_list: TemplateChild::default(),
+                source_list: TemplateChild::default(),
+                move_streams_switch: TemplateChild::default(),
+                block_toggled: Default::default(),
+                default_nodes_handler: Default::default(),
+                settings: gio::Settings::new(APP_ID),
+            }
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for PwOutputSwitcher {
+        const NAME: &'static str = "PwOutputSwitcher";
+        type Type = super::PwOutputSwitcher;
+        type ParentType = gtk::Popover;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for PwOutputSwitcher {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            let manager = PwvucontrolManager::default();
+            self.bind_list(&self.sink_list, &manager.sink_model());
+            self.bind_list(&self.source_list, &manager.source_model());
+
+            self.settings.bind("move-streams-to-default", &self.move_streams_switch.get(), "active").build();
+
+            let handler = manager.connect_local(
+                "default-nodes-changed",
+                false,
+                clone!(@weak self as widget => @default-return None, move |_| {
+                    widget.update_defaults();
+                    None
+                }),
+            );
+            self.default_nodes_handler.replace(Some(handler));
+        }
+
+        fn dispose(&self) {
+            if let Some(handler) = self.default_nodes_handler.take() {
+                PwvucontrolManager::default().disconnect(handler);
+            }
+        }
+    }
+
+    impl WidgetImpl for PwOutputSwitcher {
+        fn map(&self) {
+            self.parent_map();
+
+            self.update_defaults();
+        }
+    }
+
+    impl PopoverImpl for PwOutputSwitcher {}
+
+    impl PwOutputSwitcher {
+        fn bind_list(&self, listbox: &gtk::ListBox, model: &PwNodeFilterModel) {
+            listbox.bind_model(
+                Some(model),
+                clone!(@weak self as widget, @weak listbox => @default-panic, move |item| {
+                    let node = item.clone().downcast::<PwNodeObject>().expect("PwNodeObject");
+
+                    let button = gtk::CheckButton::new();
+                    node.bind_property("name", &button, "label").sync_create().build();
+
+                    // Join the radio group of the rows already in the list.
+                    let first = listbox.row_at_index(0).and_then(|row| row.child()).and_downcast::<gtk::CheckButton>();
+                    button.set_group(first.as_ref());
+
+                    button.connect_toggled(clone!(@weak widget, @weak node => move |button| {
+                        if widget.block_toggled.get() || !button.is_active() {
+                            return;
+                        }
+                        PwvucontrolManager::default().set_default_node(&node, widget.move_streams_switch.is_active());
+                    }));
+
+                    gtk::ListBoxRow::builder().child(&button).activatable(false).build().upcast()
+                }),
+            );
+        }
+
+        fn update_defaults(&self) {
+            let manager = PwvucontrolManager::default();
+
+            self.block_toggled.set(true);
+            for (listbox, model) in [(&self.sink_list, manager.sink_model()), (&self.source_list, manager.source_model())] {
+                // Rows are created by bind_model, so they are in the same order as the model.
+                for (index, node) in (0..).zip(model.iter::<PwNodeObject>().map_while(Result::ok)) {
+                    let Some(button) = listbox.row_at_index(index).and_then(|row| row.child()).and_downcast::<gtk::CheckButton>() else {
+                        continue;
+                    };
+                    let Some(media_class) = PwvucontrolManager::default_media_class(&node) else {
+                        continue;
+                    };
+                    button.set_active(manager.default_node_id(media_class) == Some(node.boundid()));
+                }
+            }
+            self.block_toggled.set(false);
+        }
+    }
+}
+
+glib::wrapper! {
+    /// Header bar popover to pick the default output and input.
+    pub struct PwOutputSwitcher(ObjectSubclass<imp::PwOutputSwitcher>)
+        @extends gtk::Widget, gtk::Popover,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Native, gtk::ShortcutManager;
+}
+
+impl Default for PwOutputSwitcher {
+    fn default() -> Self {
+        glib::Object::new()
+    }
+}
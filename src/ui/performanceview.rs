@@ -0,0 +1,182 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::{
+    backend::{NodeProfile, PwPerformanceObject, PwvucontrolManager},
+    ui::PwvucontrolWindow,
+};
+use gettextrs::gettext;
+use glib::{clone, ControlFlow, SourceId};
+use gtk::{gio, prelude::*, subclass::prelude::*};
+use std::{cell::RefCell, collections::HashMap};
+
+mod imp {
+    use super::*;
+
+    /// Scheduling of every node like `pw-top` shows it, to look into crackling and dropouts.
+    #[derive(Debug, gtk::CompositeTemplate)]
+    #[template(resource = "/com/saivert/pwvucontrol/gtk/performanceview.ui")]
+    pub struct PwPerformanceView {
+        #[template_child]
+        pub stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub columnview: TemplateChild<gtk::ColumnView>,
+
+        pub(super) model: gio::ListStore,
+        pub(super) refresh_timeout: RefCell<Option<SourceId>>,
+    }
+
+    impl Default for PwPerformanceView {
+        fn default() -> Self {
+            Self {
+                stack: TemplateChild::default(),
+                columnview: TemplateChild::default(),
+                model: gio::ListStore::new::<PwPerformanceObject>(),
+                refresh_timeout: Default::default(),
+            }
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for PwPerformanceView {
+        const NAME: &'static str = "PwPerformanceView";
+        type Type = super::PwPerformanceView;
+        type ParentType = gtk::Widget;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for PwPerformanceView {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            let microseconds = |value: &glib::Value| format!("{:.1} µs", value.get::<i64>().unwrap_or_default() as f64 / 1000.0);
+            let share = |value: &glib::Value| format!("{:.2}", value.get::<f32>().unwrap_or_default());
+            self.add_column(&gettext("Node"), "name", |value| value.get::<String>().unwrap_or_default(), true);
+            self.add_column(&gettext("Driver"), "driver-name", |value| value.get::<String>().unwrap_or_default(), true);
+            self.add_column(&gettext("Quantum"), "quantum", |value| value.get::<i64>().unwrap_or_default().to_string(), false);
+            self.add_column(&gettext("Rate"), "rate", |value| value.get::<u32>().unwrap_or_default().to_string(), false);
+            self.add_column(&gettext("DSP Load"), "dsp-load", |value| format!("{:.1} %", value.get::<f32>().unwrap_or_default() * 100.0), false);
+            self.add_column(&gettext("Wait"), "wait", microseconds, false);
+            let busy = self.add_column(&gettext("Busy"), "busy", microseconds, false);
+            self.add_column(&gettext("W/Q"), "wait-quantum", share, false);
+            self.add_column(&gettext("B/Q"), "busy-quantum", share, false);
+            self.add_column(&gettext("Xruns"), "xruns", |value| value.get::<i32>().unwrap_or_default().to_string(), false);
+
+            let sorted = gtk::SortListModel::new(Some(self.model.clone()), self.columnview.sorter());
+            self.columnview.set_model(Some(&gtk::NoSelection::new(Some(sorted))));
+            self.columnview.sort_by_column(Some(&busy), gtk::SortType::Descending);
+        }
+
+        fn dispose(&self) {
+            self.stop_refresh();
+            self.dispose_template();
+        }
+    }
+
+    impl WidgetImpl for PwPerformanceView {
+        fn map(&self) {
+            self.parent_map();
+
+            // Only poll the profiler while the tab is shown.
+            self.refresh();
+            let source = glib::timeout_add_seconds_local(
+                1,
+                clone!(@weak self as widget => @default-return ControlFlow::Break, move || {
+                    widget.refresh();
+                    ControlFlow::Continue
+                }),
+            );
+            self.refresh_timeout.replace(Some(source));
+        }
+
+        fn unmap(&self) {
+            self.stop_refresh();
+            self.parent_unmap();
+        }
+    }
+
+    #[gtk::template_callbacks]
+    impl PwPerformanceView {
+        #[template_callback]
+        fn row_activated(&self, _columnview: &gtk::ColumnView, position: u32) {
+            let item = self.columnview.model().and_then(|model| model.item(position)).and_downcast::<PwPerformanceObject>();
+            if let Some(node) = item.and_then(|item| item.node()) {
+                PwvucontrolWindow::default().show_node(&node);
+            }
+        }
+    }
+
+    impl PwPerformanceView {
+        /// Adds a sortable column showing `property` of the rows, formatted by `format`.
+        fn add_column(&self, title: &str, property: &'static str, format: fn(&glib::Value) -> String, expand: bool) -> gtk::ColumnViewColumn {
+            let factory = gtk::SignalListItemFactory::new();
+            factory.connect_setup(move |_, item| {
+                let item = item.downcast_ref::<gtk::ListItem>().expect("ListItem");
+                let label = gtk::Label::builder().xalign(if expand { 0.0 } else { 1.0 }).ellipsize(gtk::pango::EllipsizeMode::End).build();
+                item.property_expression("item")
+                    .chain_property::<PwPerformanceObject>(property)
+                    .chain_closure_with_callback(move |values| format(&values[1]))
+                    .bind(&label, "label", gtk::Widget::NONE);
+                item.set_child(Some(&label));
+            });
+
+            let expression = gtk::PropertyExpression::new(PwPerformanceObject::static_type(), gtk::Expression::NONE, property);
+            let sorter: gtk::Sorter = match property {
+                "name" | "driver-name" => gtk::StringSorter::new(Some(expression)).upcast(),
+                _ => gtk::NumericSorter::new(Some(expression)).upcast(),
+            };
+
+            let column = gtk::ColumnViewColumn::builder().title(title).factory(&factory).sorter(&sorter).expand(expand).resizable(true).build();
+            self.columnview.append_column(&column);
+            column
+        }
+
+        /// Updates the rows in place from the latest profile, so the sort order and scroll position stay.
+        fn refresh(&self) {
+            let manager = PwvucontrolManager::default();
+            let profiles = manager.node_profiles();
+            let available = !profiles.is_empty() || manager.profiler_available();
+            self.stack.set_visible_child_name(if available { "table" } else { "unavailable" });
+
+            let mut remaining: HashMap<u32, &NodeProfile> = profiles.iter().map(|profile| (profile.id, profile)).collect();
+            let mut index = 0;
+            while let Some(item) = self.model.item(index).and_downcast::<PwPerformanceObject>() {
+                match remaining.remove(&item.id()) {
+                    Some(profile) => {
+                        item.update(profile, manager.get_node_by_id(profile.id).as_ref());
+                        index += 1;
+                    }
+                    None => self.model.remove(index),
+                }
+            }
+
+            let added: Vec<PwPerformanceObject> =
+                remaining.into_values().map(|profile| PwPerformanceObject::new(profile, manager.get_node_by_id(profile.id).as_ref())).collect();
+            self.model.extend_from_slice(&added);
+
+            // Sorting only looks at the values when items change position, so ask for it again.
+            if let Some(sorter) = self.columnview.sorter() {
+                sorter.changed(gtk::SorterChange::Different);
+            }
+        }
+
+        fn stop_refresh(&self) {
+            if let Some(source) = self.refresh_timeout.take() {
+                source.remove();
+            }
+        }
+    }
+}
+
+glib::wrapper! {
+    pub struct PwPerformanceView(ObjectSubclass<imp::PwPerformanceView>)
+        @extends gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget;
+}
@@ -0,0 +1,377 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::{
+    backend::{NodeRule, PwvucontrolManager},
+    config::APP_ID,
+    macros::*,
+};
+use adw::{prelude::*, subclass::prelude::*};
+use glib::{clone, SignalHandlerId};
+use gtk::{gdk, gio};
+use std::cell::{Cell, RefCell};
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, gtk::CompositeTemplate)]
+    #[template(resource = "/com/saivert/pwvucontrol/gtk/preferences.ui")]
+    pub struct PwPreferencesWindow {
+        #[template_child]
+        pub ramp_duration_row: TemplateChild<adw::SpinRow>,
+        #[template_child]
+        pub mute_on_unplug_row: TemplateChild<adw::SwitchRow>,
+        #[template_child]
+        pub remember_route_volumes_row: TemplateChild<adw::SwitchRow>,
+        #[template_child]
+        pub feedback_sound_switch_row: TemplateChild<adw::SwitchRow>,
+        #[template_child]
+        pub feedback_sound_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub auto_switch_row: TemplateChild<adw::SwitchRow>,
+        #[template_child]
+        pub priority_rules_group: TemplateChild<adw::PreferencesGroup>,
+        #[template_child]
+        pub priority_key_entry: TemplateChild<adw::EntryRow>,
+        #[template_child]
+        pub priority_pattern_entry: TemplateChild<adw::EntryRow>,
+
+        #[template_child]
+        pub show_hidden_row: TemplateChild<adw::SwitchRow>,
+        #[template_child]
+        pub hide_rules_group: TemplateChild<adw::PreferencesGroup>,
+        #[template_child]
+        pub rule_key_entry: TemplateChild<adw::EntryRow>,
+        #[template_child]
+        pub rule_pattern_entry: TemplateChild<adw::EntryRow>,
+
+        #[template_child]
+        pub clock_group: TemplateChild<adw::PreferencesGroup>,
+        #[template_child]
+        pub force_rate_row: TemplateChild<adw::SpinRow>,
+        #[template_child]
+        pub force_quantum_row: TemplateChild<adw::SpinRow>,
+        #[template_child]
+        pub allowed_rates_row: TemplateChild<adw::EntryRow>,
+        #[template_child]
+        pub log_level_row: TemplateChild<adw::ComboRow>,
+
+        pub(super) hide_rule_rows: RefCell<Vec<adw::ActionRow>>,
+        pub(super) priority_rule_rows: RefCell<Vec<adw::ActionRow>>,
+        pub(super) settings_changed: RefCell<Option<SignalHandlerId>>,
+        pub(super) block_clock: Cell<bool>,
+
+        pub settings: gio::Settings,
+    }
+
+    impl Default for PwPreferencesWindow {
+        fn default() -> Self {
+            Self {
+                ramp_duration_row: TemplateChild::default(),
+                mute_on_unplug_row: TemplateChild::default(),
+                remember_route_volumes_row: TemplateChild::default(),
+                feedback_sound_switch_row: TemplateChild::default(),
+                feedback_sound_row: TemplateChild::default(),
+                auto_switch_row: TemplateChild::default(),
+                priority_rules_group: TemplateChild::default(),
+                priority_key_entry: TemplateChild::default(),
+                priority_pattern_entry: TemplateChild::default(),
+                show_hidden_row: TemplateChild::default(),
+                hide_rules_group: TemplateChild::default(),
+                rule_key_entry: TemplateChild::default(),
+                rule_pattern_entry: TemplateChild::default(),
+                clock_group: TemplateChild::default(),
+                force_rate_row: TemplateChild::default(),
+                force_quantum_row: TemplateChild::default(),
+                allowed_rates_row: TemplateChild::default(),
+                log_level_row: TemplateChild::default(),
+                hide_rule_rows: Default::default(),
+                priority_rule_rows: Default::default(),
+                settings_changed: Default::default(),
+                block_clock: Default::default(),
+                settings: gio::Settings::new(APP_ID),
+            }
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for PwPreferencesWindow {
+        const NAME: &'static str = "PwPreferencesWindow";
+        type Type = super::PwPreferencesWindow;
+        type ParentType = adw::PreferencesWindow;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for PwPreferencesWindow {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            self.settings.bind("volume-ramp-duration", &self.ramp_duration_row.get(), "value").build();
+            self.settings.bind("mute-on-unplug", &self.mute_on_unplug_row.get(), "active").build();
+            self.settings.bind("remember-route-volumes", &self.remember_route_volumes_row.get(), "active").build();
+            self.settings.bind("beep-on-volume-changes", &self.feedback_sound_switch_row.get(), "active").build();
+            self.settings.bind("beep-on-volume-changes", &self.feedback_sound_row.get(), "sensitive").get_only().build();
+            self.settings.connect_changed(
+                Some("feedback-sound"),
+                clone!(@weak self as widget => move |_, _| {
+                    widget.update_feedback_sound();
+                }),
+            );
+            self.update_feedback_sound();
+
+            self.settings.bind("auto-switch-default-device", &self.auto_switch_row.get(), "active").build();
+            self.settings.connect_changed(
+                Some("default-device-priority"),
+                clone!(@weak self as widget => move |_, _| {
+                    widget.update_priority_rules();
+                }),
+            );
+            self.update_priority_rules();
+
+            self.settings.bind("show-hidden-streams", &self.show_hidden_row.get(), "active").build();
+
+            self.settings.connect_changed(
+                Some("hide-rules"),
+                clone!(@weak self as widget => move |_, _| {
+                    widget.update_hide_rules();
+                }),
+            );
+            self.update_hide_rules();
+
+            self.setup_clock_settings();
+        }
+
+        fn dispose(&self) {
+            if let Some(handler) = self.settings_changed.take() {
+                PwvucontrolManager::default().disconnect(handler);
+            }
+        }
+    }
+    impl WidgetImpl for PwPreferencesWindow {}
+    impl WindowImpl for PwPreferencesWindow {}
+    impl AdwWindowImpl for PwPreferencesWindow {}
+    impl PreferencesWindowImpl for PwPreferencesWindow {}
+
+    #[gtk::template_callbacks]
+    impl PwPreferencesWindow {
+        #[template_callback]
+        fn add_rule_clicked(&self, _button: &gtk::Button) {
+            self.add_rule("hide-rules", &self.rule_key_entry, &self.rule_pattern_entry);
+        }
+
+        #[template_callback]
+        fn add_priority_rule_clicked(&self, _button: &gtk::Button) {
+            self.add_rule("default-device-priority", &self.priority_key_entry, &self.priority_pattern_entry);
+        }
+
+        #[template_callback]
+        fn choose_feedback_sound_clicked(&self, _button: &gtk::Button) {
+            let filter = gtk::FileFilter::new();
+            filter.set_name(Some(&gettextrs::gettext("WAV files")));
+            filter.add_mime_type("audio/x-wav");
+            filter.add_suffix("wav");
+            let filters = gio::ListStore::new::<gtk::FileFilter>();
+            filters.append(&filter);
+
+            let dialog = gtk::FileDialog::builder().title(gettextrs::gettext("Feedback Sound")).modal(true).filters(&filters).build();
+            dialog.open(
+                Some(&*self.obj()),
+                gio::Cancellable::NONE,
+                clone!(@weak self as widget => move |result| {
+                    if let Some(path) = result.ok().and_then(|file| file.path()) {
+                        if widget.settings.set_string("feedback-sound", &path.to_string_lossy()).is_err() {
+                            pwvucontrol_warning!("Unable to save feedback sound to gsettings");
+                        }
+                    }
+                }),
+            );
+        }
+
+        #[template_callback]
+        fn reset_feedback_sound_clicked(&self, _button: &gtk::Button) {
+            self.settings.reset("feedback-sound");
+        }
+
+        fn update_feedback_sound(&self) {
+            let path = self.settings.string("feedback-sound");
+            let subtitle = std::path::Path::new(path.as_str())
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| gettextrs::gettext("Built-in"));
+            self.feedback_sound_row.set_subtitle(&subtitle);
+        }
+
+        #[template_callback]
+        fn reset_clock_clicked(&self, _button: &gtk::Button) {
+            let manager = PwvucontrolManager::default();
+            manager.set_metadata_setting("clock.force-rate", Some("0"));
+            manager.set_metadata_setting("clock.force-quantum", Some("0"));
+        }
+
+        #[template_callback]
+        fn allowed_rates_applied(&self, row: &adw::EntryRow) {
+            let text = row.text();
+            PwvucontrolManager::default().set_metadata_setting("clock.allowed-rates", Some(text.as_str()).filter(|text| !text.is_empty()));
+        }
+
+        fn setup_clock_settings(&self) {
+            let manager = PwvucontrolManager::default();
+
+            let spin_rows = [(&self.force_rate_row, "clock.force-rate"), (&self.force_quantum_row, "clock.force-quantum")];
+            for (row, key) in spin_rows {
+                row.connect_value_notify(clone!(@weak self as widget => move |row| {
+                    if !widget.block_clock.get() {
+                        PwvucontrolManager::default().set_metadata_setting(key, Some(&(row.value() as u32).to_string()));
+                    }
+                }));
+            }
+
+            self.log_level_row.connect_selected_notify(clone!(@weak self as widget => move |row| {
+                if !widget.block_clock.get() {
+                    PwvucontrolManager::default().set_metadata_setting("log.level", Some(&row.selected().to_string()));
+                }
+            }));
+
+            let handler = manager.connect_local(
+                "settings-changed",
+                false,
+                clone!(@weak self as widget => @default-return None, move |_| {
+                    widget.update_clock_settings();
+                    None
+                }),
+            );
+            self.settings_changed.replace(Some(handler));
+            self.update_clock_settings();
+        }
+
+        fn update_clock_settings(&self) {
+            let manager = PwvucontrolManager::default();
+            self.clock_group.set_sensitive(manager.has_settings());
+            let setting = |key| manager.metadata_setting(key).and_then(|value| value.trim().parse::<u32>().ok()).unwrap_or_default();
+
+            self.block_clock.set(true);
+            self.force_rate_row.set_value(setting("clock.force-rate") as f64);
+            self.force_quantum_row.set_value(setting("clock.force-quantum") as f64);
+            self.log_level_row.set_selected(setting("log.level").min(5));
+            if self.allowed_rates_row.focus_child().is_none() {
+                self.allowed_rates_row.set_text(&manager.metadata_setting("clock.allowed-rates").unwrap_or_default());
+            }
+            self.block_clock.set(false);
+        }
+
+        fn update_hide_rules(&self) {
+            self.update_rule_list("hide-rules", &self.hide_rules_group, &self.hide_rule_rows, false);
+        }
+
+        /// Priority rules are tried in order, so they can be reordered by dragging.
+        fn update_priority_rules(&self) {
+            self.update_rule_list("default-device-priority", &self.priority_rules_group, &self.priority_rule_rows, true);
+        }
+
+        /// Appends the rule typed into `key_entry` and `pattern_entry` to the rule list in the settings key `key`.
+        fn add_rule(&self, key: &str, key_entry: &adw::EntryRow, pattern_entry: &adw::EntryRow) {
+            let rule_key = key_entry.text();
+            let pattern = pattern_entry.text();
+            if rule_key.is_empty() || pattern.is_empty() {
+                return;
+            }
+
+            let mut rules = NodeRule::load(&self.settings, key);
+            rules.push(NodeRule::new(&rule_key, &pattern));
+            self.store_rules(key, &rules);
+
+            key_entry.set_text("");
+            pattern_entry.set_text("");
+        }
+
+        /// Shows the rules of the settings key `key` in `group`, each with a button to remove it,
+        /// replacing the `rows` shown before.
+        fn update_rule_list(&self, key: &'static str, group: &adw::PreferencesGroup, rows: &RefCell<Vec<adw::ActionRow>>, reorderable: bool) {
+            for row in rows.take() {
+                group.remove(&row);
+            }
+
+            let mut new_rows = Vec::new();
+            for (index, rule) in NodeRule::load(&self.settings, key).iter().enumerate() {
+                let row = adw::ActionRow::builder().title(&rule.key).subtitle(&rule.pattern).build();
+
+                let remove_button = gtk::Button::builder()
+                    .icon_name("user-trash-symbolic")
+                    .valign(gtk::Align::Center)
+                    .tooltip_text(gettextrs::gettext("Remove rule"))
+                    .css_classes(["flat"])
+                    .build();
+                remove_button.connect_clicked(clone!(@weak self as widget => move |_| {
+                    let mut rules = NodeRule::load(&widget.settings, key);
+                    if index < rules.len() {
+                        rules.remove(index);
+                        widget.store_rules(key, &rules);
+                    }
+                }));
+                row.add_suffix(&remove_button);
+
+                if reorderable {
+                    self.make_rule_row_reorderable(key, &row, index);
+                }
+
+                group.add(&row);
+                new_rows.push(row);
+            }
+            rows.replace(new_rows);
+        }
+
+        fn make_rule_row_reorderable(&self, key: &'static str, row: &adw::ActionRow, index: usize) {
+            row.add_prefix(&gtk::Image::from_icon_name("list-drag-handle-symbolic"));
+
+            // Rows are dragged by their index and dropped onto the row whose place they take.
+            let drag_source = gtk::DragSource::builder().actions(gdk::DragAction::MOVE).build();
+            drag_source.connect_prepare(move |_, _, _| Some(gdk::ContentProvider::for_value(&(index as u32).to_value())));
+            drag_source.connect_drag_begin(clone!(@weak row => move |source, _| {
+                source.set_icon(Some(&gtk::WidgetPaintable::new(Some(&row))), 0, 0);
+            }));
+            row.add_controller(drag_source);
+
+            let drop_target = gtk::DropTarget::new(u32::static_type(), gdk::DragAction::MOVE);
+            drop_target.connect_drop(clone!(@weak self as widget => @default-return false, move |_, value, _, _| {
+                let Ok(from) = value.get::<u32>() else {
+                    return false;
+                };
+                let mut rules = NodeRule::load(&widget.settings, key);
+                let from = from as usize;
+                if from == index || from >= rules.len() || index >= rules.len() {
+                    return false;
+                }
+                let rule = rules.remove(from);
+                rules.insert(index, rule);
+                widget.store_rules(key, &rules);
+                true
+            }));
+            row.add_controller(drop_target);
+        }
+
+        fn store_rules(&self, key: &str, rules: &[NodeRule]) {
+            if NodeRule::store(&self.settings, key, rules).is_err() {
+                pwvucontrol_warning!("Unable to save {key} to gsettings");
+            }
+        }
+    }
+}
+
+glib::wrapper! {
+    pub struct PwPreferencesWindow(ObjectSubclass<imp::PwPreferencesWindow>)
+        @extends gtk::Widget, gtk::Window, adw::Window, adw::PreferencesWindow,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Native, gtk::Root, gtk::ShortcutManager;
+}
+
+impl PwPreferencesWindow {
+    pub fn new(parent: &impl IsA<gtk::Window>) -> Self {
+        glib::Object::builder().property("transient-for", parent).build()
+    }
+}
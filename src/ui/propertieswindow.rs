@@ -0,0 +1,132 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::backend::{sections_to_string, DumpSection};
+use adw::{prelude::*, subclass::prelude::*};
+use std::cell::RefCell;
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, Default, gtk::CompositeTemplate)]
+    #[template(resource = "/com/saivert/pwvucontrol/gtk/propertieswindow.ui")]
+    pub struct PwPropertiesWindow {
+        #[template_child]
+        pub window_title: TemplateChild<adw::WindowTitle>,
+        #[template_child]
+        pub search_entry: TemplateChild<gtk::SearchEntry>,
+        #[template_child]
+        pub search_bar: TemplateChild<gtk::SearchBar>,
+        #[template_child]
+        pub sections_box: TemplateChild<gtk::Box>,
+
+        pub(super) sections: RefCell<Vec<DumpSection>>,
+        pub(super) groups: RefCell<Vec<adw::PreferencesGroup>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for PwPropertiesWindow {
+        const NAME: &'static str = "PwPropertiesWindow";
+        type Type = super::PwPropertiesWindow;
+        type ParentType = adw::Window;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for PwPropertiesWindow {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            self.search_bar.connect_entry(&self.search_entry.get());
+            self.search_bar.set_key_capture_widget(Some(self.obj().upcast_ref::<gtk::Widget>()));
+        }
+    }
+    impl WidgetImpl for PwPropertiesWindow {}
+    impl WindowImpl for PwPropertiesWindow {}
+    impl AdwWindowImpl for PwPropertiesWindow {}
+
+    #[gtk::template_callbacks]
+    impl PwPropertiesWindow {
+        #[template_callback]
+        fn search_changed(&self, _entry: &gtk::SearchEntry) {
+            self.update_sections();
+        }
+
+        #[template_callback]
+        fn copy_clicked(&self, _button: &gtk::Button) {
+            let text = sections_to_string(&self.filtered_sections());
+            self.obj().clipboard().set_text(&text);
+        }
+
+        fn filtered_sections(&self) -> Vec<DumpSection> {
+            let needle = self.search_entry.text().to_lowercase();
+
+            self.sections
+                .borrow()
+                .iter()
+                .map(|section| DumpSection {
+                    title: section.title.clone(),
+                    entries: section
+                        .entries
+                        .iter()
+                        .filter(|(key, value)| key.to_lowercase().contains(&needle) || value.to_lowercase().contains(&needle))
+                        .cloned()
+                        .collect(),
+                })
+                .filter(|section| !section.entries.is_empty())
+                .collect()
+        }
+
+        pub(super) fn update_sections(&self) {
+            for group in self.groups.take() {
+                self.sections_box.remove(&group);
+            }
+
+            let mut groups = Vec::new();
+            for section in self.filtered_sections() {
+                let group = adw::PreferencesGroup::builder().title(&section.title).build();
+
+                for (key, value) in section.entries.iter() {
+                    let row = adw::ActionRow::builder()
+                        .title(key)
+                        .subtitle(value)
+                        .use_markup(false)
+                        .subtitle_selectable(true)
+                        .css_classes(["property"])
+                        .build();
+                    group.add(&row);
+                }
+
+                self.sections_box.append(&group);
+                groups.push(group);
+            }
+            self.groups.replace(groups);
+        }
+    }
+}
+
+glib::wrapper! {
+    /// Lists the properties and params of a node or device, like `pw-dump` does.
+    pub struct PwPropertiesWindow(ObjectSubclass<imp::PwPropertiesWindow>)
+        @extends gtk::Widget, gtk::Window, adw::Window,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Native, gtk::Root, gtk::ShortcutManager;
+}
+
+impl PwPropertiesWindow {
+    pub fn new(parent: Option<&gtk::Window>, title: &str, sections: Vec<DumpSection>) -> Self {
+        let window: Self = glib::Object::builder().property("transient-for", parent).build();
+        let imp = window.imp();
+
+        imp.window_title.set_subtitle(title);
+        imp.sections.replace(sections);
+        imp.update_sections();
+
+        window
+    }
+}
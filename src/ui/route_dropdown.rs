@@ -9,8 +9,6 @@ use glib::clone;
 use glib::closure_local;
 use gtk::{self, prelude::*, subclass::prelude::*};
 use std::cell::{Cell, RefCell};
-use wireplumber as wp;
-use wp::pw::ProxyExt;
 
 mod imp {
     use super::*;
@@ -84,7 +82,7 @@ mod imp {
                 let deviceobject = nodeobject.device().expect("nodeobject with associated device on PwRouteDropDown");
 
                 self.block_signal.set(true);
-                pwvucontrol_info!("self.route_dropdown.set_model({});", deviceobject.wpdevice().bound_id());
+                pwvucontrol_info!("self.route_dropdown.set_model({});", deviceobject.id());
                 self.route_dropdown.set_model(self.get_route_model().as_ref());
                 if let Some(index) = self.get_route_index() {
                     pwvucontrol_info!("self.route_dropdown.set_selected({index});");
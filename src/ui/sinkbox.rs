@@ -1,18 +1,22 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use crate::{
-    backend::{NodeType, PwNodeObject, PwvucontrolManager},
+    backend::{NodeType, PwNodeObject, PwvucontrolManager, SupportedFormats},
     pwvucontrol_info,
-    ui::{PwRouteDropDown, PwVolumeBox, PwvucontrolWindow},
+    ui::{soundtest, PwListenButton, PwRouteDropDown, PwVolumeBox, PwvucontrolWindow, SoundPlayer, SoundRecorder},
 };
 
 use crate::pwvucontrol_warning;
+use gettextrs::gettext;
 use glib::clone;
 use glib::closure_local;
 use gtk::{prelude::*, subclass::prelude::*};
 use std::cell::Cell;
 use std::cell::RefCell;
-use wireplumber as wp;
+use std::time::Duration;
+
+const TEST_RECORDING_LENGTH: Duration = Duration::from_secs(5);
+const TEST_RATE: u32 = 48000;
 
 mod imp {
     use super::*;
@@ -34,6 +38,26 @@ mod imp {
 
         #[template_child]
         pub route_dropdown: TemplateChild<PwRouteDropDown>,
+
+        #[template_child]
+        pub format_button: TemplateChild<gtk::MenuButton>,
+
+        #[template_child]
+        pub rate_dropdown: TemplateChild<gtk::DropDown>,
+
+        #[template_child]
+        pub sample_format_dropdown: TemplateChild<gtk::DropDown>,
+
+        #[template_child]
+        pub test_button: TemplateChild<gtk::Button>,
+
+        #[template_child]
+        pub listen_button: TemplateChild<PwListenButton>,
+
+        pub(super) supported_formats: RefCell<SupportedFormats>,
+
+        pub(super) sound_player: RefCell<Option<SoundPlayer>>,
+        pub(super) sound_recorder: RefCell<Option<SoundRecorder>>,
     }
 
     #[glib::object_subclass]
@@ -61,18 +85,26 @@ mod imp {
             let item = obj.node_object().expect("nodeobj");
 
             self.volumebox.set_node_object(&item);
+            self.format_button.set_visible(item.is_alsa());
+            // Tests and listening need streams of our own, which the demo backend can't provide.
+            let can_create_streams = PwvucontrolManager::default().backend().can_create_streams();
+            self.test_button.set_visible(can_create_streams);
+            if can_create_streams && matches!(item.nodetype(), NodeType::Source) {
+                self.listen_button.set_nodeobj(Some(&item));
+                self.listen_button.set_visible(true);
+            }
+            self.update_test_button();
 
             if matches!(item.nodetype(), NodeType::Sink) {
-                self.volumebox.imp().volume_scale.connect_volume_notify(|_| {
+                self.volumebox.imp().volume_scale.connect_volume_notify(clone!(@weak item => move |_| {
                     let window = PwvucontrolWindow::default();
-                    window.play_beep();
-                });
+                    window.play_beep(&item);
+                }));
             }
             let manager = PwvucontrolManager::default();
-            let defaultnodesapi = manager.default_nodes_api();
             let widget = self.obj();
-            let defaultnodesapi_closure = closure_local!(@watch widget => move |_: wp::plugin::Plugin| widget.imp().default_node_changed());
-            defaultnodesapi.connect_closure("changed", false, defaultnodesapi_closure);
+            let default_nodes_closure = closure_local!(@watch widget => move |_: PwvucontrolManager| widget.imp().default_node_changed());
+            manager.connect_closure("default-nodes-changed", false, default_nodes_closure);
             self.default_node_changed();
 
             // Only set nodeobject once it has a device associated.
@@ -100,37 +132,124 @@ mod imp {
             let node = self.volumebox.node_object().expect("nodeobj");
             let node_name: String = if _togglebutton.is_active() { node.node_property("node.name").unwrap_or_default() } else { "".to_string() };
 
-            let manager = PwvucontrolManager::default();
+            let type_name = PwvucontrolManager::default_media_class(&node).expect("sink or source");
 
-            let core = manager.imp().wp_core.get().expect("Core");
-            let defaultnodesapi = wp::plugin::Plugin::find(core, "default-nodes-api").expect("Get mixer-api");
+            let result = PwvucontrolManager::default().set_default_configured_node_name(type_name, &node_name);
+            pwvucontrol_info!("set-default-configured-node-name result: {result:?}");
+        }
 
-            let type_name = match node.nodetype() {
-                NodeType::Sink => "Audio/Sink",
-                NodeType::Source => match node.is_virtual() {
-                    true => "Audio/Source/Virtual",
-                    false => "Audio/Source",
-                },
-                _ => unreachable!(),
-            };
+        #[template_callback]
+        fn format_popover_shown(&self, _popover: &gtk::Popover) {
+            let node = self.obj().node_object().expect("nodeobj");
+            let supported = node.supported_formats();
+
+            // The first entry keeps whatever the node currently uses.
+            let current = gettext("Unchanged");
+            let rates: Vec<String> = supported.rates.iter().map(|rate| format!("{rate} Hz")).collect();
+            let rate_model = gtk::StringList::new(&[current.as_str()]);
+            rate_model.splice(1, 0, &rates.iter().map(String::as_str).collect::<Vec<_>>());
+            self.rate_dropdown.set_model(Some(&rate_model));
+
+            let format_model = gtk::StringList::new(&[current.as_str()]);
+            format_model.splice(1, 0, &supported.formats.iter().map(String::as_str).collect::<Vec<_>>());
+            self.sample_format_dropdown.set_model(Some(&format_model));
+
+            self.supported_formats.replace(supported);
+        }
+
+        #[template_callback]
+        fn format_apply_clicked(&self, _button: &gtk::Button) {
+            let node = self.obj().node_object().expect("nodeobj");
+            let supported = self.supported_formats.borrow();
+
+            let rate = (self.rate_dropdown.selected() as usize).checked_sub(1).and_then(|i| supported.rates.get(i).copied());
+            let format = (self.sample_format_dropdown.selected() as usize).checked_sub(1).and_then(|i| supported.formats.get(i));
+
+            node.set_preferred_format(rate, format.map(String::as_str));
+            self.format_button.popdown();
+        }
+
+        #[template_callback]
+        fn test_button_clicked(&self, _button: &gtk::Button) {
+            if self.sound_player.borrow().is_some() || self.sound_recorder.borrow().is_some() {
+                self.stop_sound_test();
+                return;
+            }
 
-            let result: bool = defaultnodesapi.emit_by_name("set-default-configured-node-name", &[&type_name, &node_name]);
-            wp::info!("set-default-configured-node-name result: {result:?}");
+            let node = self.obj().node_object().expect("nodeobj");
+            let format = node.format();
+            let rate = format.map(|format| format.rate).filter(|rate| *rate > 0).unwrap_or(TEST_RATE);
+            let positions: Vec<u32> = format
+                .map(|format| format.positions[..(format.channels as usize).min(64)].to_vec())
+                .filter(|positions| !positions.is_empty())
+                .unwrap_or_else(|| soundtest::STEREO.to_vec());
+
+            match node.nodetype() {
+                NodeType::Sink => {
+                    let samples = soundtest::speaker_test_samples(rate, positions.len());
+                    self.play(Some(node.boundid()), rate, &positions, samples);
+                }
+                _ => {
+                    let done = clone!(@weak self as widget, @strong positions => move |samples: Vec<f32>| {
+                        widget.sound_recorder.take();
+                        // Played back wherever sounds go by default, the source may well be a headset.
+                        widget.play(None, rate, &positions, samples);
+                    });
+                    match SoundRecorder::new(node.boundid(), rate, &positions, TEST_RECORDING_LENGTH, done) {
+                        Ok(recorder) => {
+                            self.sound_recorder.replace(Some(recorder));
+                        }
+                        Err(e) => pwvucontrol_warning!("Cannot record from {}: {e}", node.name()),
+                    }
+                }
+            }
+            self.update_test_button();
+        }
+
+        fn play(&self, target: Option<u32>, rate: u32, positions: &[u32], samples: Vec<f32>) {
+            let done = clone!(@weak self as widget => move || widget.stop_sound_test());
+            match SoundPlayer::new(target, rate, positions, samples, done) {
+                Ok(player) => {
+                    self.sound_player.replace(Some(player));
+                }
+                Err(e) => pwvucontrol_warning!("Cannot play test sound: {e}"),
+            }
+            self.update_test_button();
+        }
+
+        fn stop_sound_test(&self) {
+            self.sound_player.take();
+            self.sound_recorder.take();
+            self.update_test_button();
+        }
+
+        fn update_test_button(&self) {
+            let node = self.obj().node_object().expect("nodeobj");
+            let (icon, tooltip) = if self.sound_recorder.borrow().is_some() {
+                ("media-playback-stop-symbolic", gettext("Recording, click to stop"))
+            } else if self.sound_player.borrow().is_some() {
+                ("media-playback-stop-symbolic", gettext("Playing, click to stop"))
+            } else if matches!(node.nodetype(), NodeType::Sink) {
+                ("audio-speakers-symbolic", gettext("Play a tone on each speaker"))
+            } else {
+                ("media-record-symbolic", gettext("Record 5 seconds and play back"))
+            };
+            self.test_button.set_icon_name(icon);
+            self.test_button.set_tooltip_text(Some(&tooltip));
         }
 
         fn default_node_changed(&self) {
             let manager = PwvucontrolManager::default();
-            let defaultnodesapi = manager.default_nodes_api();
 
             let node = self.obj().node_object().expect("nodeobj");
             let Some(media_class) = node.node_property::<String>("media.class") else {
                 pwvucontrol_warning!("{} is missing media.class property", node.name());
                 return;
             };
-            let id: u32 = defaultnodesapi.emit_by_name("get-default-node", &[&media_class]);
+            let id = manager.default_node_id(&media_class);
 
             self.block_default_node_toggle_signal.set(true);
-            self.default_sink_toggle.set_active(node.boundid() == id);
+            self.default_sink_toggle.set_active(Some(node.boundid()) == id);
             self.block_default_node_toggle_signal.set(false);
         }
     }
@@ -0,0 +1,298 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Speaker tests and test recordings, played and recorded through PipeWire streams of their own
+//! so they work without `speaker-test` or `pw-cat`.
+
+use std::{f32::consts::PI, fmt::Debug, time::Duration};
+
+use glib::{self, ControlFlow, SourceId};
+use pipewire::{context::Context, core::Core, loop_::Loop, properties::*, spa, spa::utils::Direction, stream::*};
+use std::os::fd::AsRawFd;
+
+use crate::config::APP_ID;
+
+const TONE_FREQUENCY: f32 = 440.0;
+const TONE_LENGTH: Duration = Duration::from_millis(600);
+const TONE_GAP: Duration = Duration::from_millis(300);
+const TONE_FADE: Duration = Duration::from_millis(10);
+const TONE_AMPLITUDE: f32 = 0.3;
+
+/// Silence played after the sound, so it isn't cut off while the last buffers are still queued.
+const TAIL: Duration = Duration::from_millis(500);
+
+/// Channels used when the node has not negotiated a format yet.
+pub const STEREO: [u32; 2] = [spa::sys::SPA_AUDIO_CHANNEL_FL, spa::sys::SPA_AUDIO_CHANNEL_FR];
+
+/// A PipeWire loop of our own, iterated from the GLib main loop like the one of `LevelbarProvider`.
+pub(super) struct StreamLoop {
+    _loop: Loop,
+    _context: Context,
+    pub(super) core: Core,
+    sig: Option<SourceId>,
+}
+
+impl StreamLoop {
+    pub(super) fn new() -> Result<Self, anyhow::Error> {
+        let loop_ = Loop::new(None)?;
+        let context = Context::new(&loop_)?;
+        let core = context.connect(None)?;
+
+        let fd = loop_.fd();
+        let sig = glib::source::unix_fd_add_local(fd.as_raw_fd(), glib::IOCondition::all(), {
+            let loop_ = loop_.clone();
+            move |_, _| {
+                loop_.iterate(Duration::ZERO);
+
+                ControlFlow::Continue
+            }
+        });
+
+        Ok(Self { _loop: loop_, _context: context, core, sig: Some(sig) })
+    }
+}
+
+impl Drop for StreamLoop {
+    fn drop(&mut self) {
+        if let Some(sig) = self.sig.take() {
+            sig.remove();
+        }
+    }
+}
+
+struct PlayState {
+    samples: Vec<f32>,
+    channels: usize,
+    position: usize,
+    done: Option<Box<dyn FnOnce()>>,
+}
+
+/// Plays interleaved F32 samples once.
+pub struct SoundPlayer {
+    stream: Option<Stream>,
+    _listener: StreamListener<PlayState>,
+    _loop: StreamLoop,
+}
+
+impl Debug for SoundPlayer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SoundPlayer")
+    }
+}
+
+impl SoundPlayer {
+    /// Plays `samples` to the node `target`, or wherever the session manager puts it when `None`,
+    /// and calls `done` from the main loop once it is through.
+    pub fn new(
+        target: Option<u32>,
+        rate: u32,
+        positions: &[u32],
+        mut samples: Vec<f32>,
+        done: impl FnOnce() + 'static,
+    ) -> Result<Self, anyhow::Error> {
+        let stream_loop = StreamLoop::new()?;
+
+        let props = properties! {
+            "media.type" => "Audio",
+            "media.category" => "Playback",
+            "media.role" => "Test",
+            "media.name" => "Sound test",
+            "node.name" => "pwvucontrol-sound-test",
+            "application.id" => APP_ID,
+        };
+        let stream = Stream::new(&stream_loop.core, "sound-test", props)?;
+
+        let channels = positions.len();
+        samples.resize(samples.len() + samples_for(rate, channels, TAIL), 0.0);
+        let state = PlayState { samples, channels, position: 0, done: Some(Box::new(done)) };
+
+        let listener = stream
+            .add_local_listener_with_user_data(state)
+            .process(|stream, state| {
+                let Some(mut buffer) = stream.dequeue_buffer() else {
+                    return;
+                };
+                let stride = state.channels * std::mem::size_of::<f32>();
+                let data = &mut buffer.datas_mut()[0];
+
+                let mut frames = 0;
+                if let Some(slice) = data.data() {
+                    frames = slice.len() / stride;
+                    for (i, bytes) in slice[..frames * stride].chunks_exact_mut(std::mem::size_of::<f32>()).enumerate() {
+                        let sample = state.samples.get(state.position + i).copied().unwrap_or(0.0);
+                        bytes.copy_from_slice(&sample.to_le_bytes());
+                    }
+                    state.position += frames * state.channels;
+                }
+
+                let chunk = data.chunk_mut();
+                *chunk.offset_mut() = 0;
+                *chunk.stride_mut() = stride as _;
+                *chunk.size_mut() = (frames * stride) as _;
+
+                // Not from here, the callback of `done` may well drop this stream.
+                if state.position >= state.samples.len() {
+                    if let Some(done) = state.done.take() {
+                        glib::idle_add_local_once(done);
+                    }
+                }
+            })
+            .register()?;
+
+        let mut buffer: Vec<u8> = Vec::new();
+        let fmtpod = create_audio_format_pod(&mut buffer, rate, positions);
+
+        stream.connect(
+            Direction::Output,
+            target,
+            StreamFlags::AUTOCONNECT | StreamFlags::MAP_BUFFERS | StreamFlags::DONT_RECONNECT,
+            &mut [fmtpod],
+        )?;
+
+        Ok(Self { stream: Some(stream), _listener: listener, _loop: stream_loop })
+    }
+}
+
+impl Drop for SoundPlayer {
+    fn drop(&mut self) {
+        if let Some(stream) = self.stream.take() {
+            let _ = stream.disconnect();
+        }
+    }
+}
+
+struct RecordState {
+    samples: Vec<f32>,
+    wanted: usize,
+    done: Option<Box<dyn FnOnce(Vec<f32>)>>,
+}
+
+/// Records interleaved F32 samples for a fixed time.
+pub struct SoundRecorder {
+    stream: Option<Stream>,
+    _listener: StreamListener<RecordState>,
+    _loop: StreamLoop,
+}
+
+impl Debug for SoundRecorder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SoundRecorder")
+    }
+}
+
+impl SoundRecorder {
+    /// Records `duration` from the node `source` and hands the samples to `done` from the main loop.
+    pub fn new(
+        source: u32,
+        rate: u32,
+        positions: &[u32],
+        duration: Duration,
+        done: impl FnOnce(Vec<f32>) + 'static,
+    ) -> Result<Self, anyhow::Error> {
+        let stream_loop = StreamLoop::new()?;
+
+        let props = properties! {
+            "media.type" => "Audio",
+            "media.category" => "Capture",
+            "media.role" => "Test",
+            "media.name" => "Test recording",
+            "node.name" => "pwvucontrol-test-recording",
+            "application.id" => APP_ID,
+        };
+        let stream = Stream::new(&stream_loop.core, "test-recording", props)?;
+
+        let wanted = samples_for(rate, positions.len(), duration);
+        let state = RecordState { samples: Vec::with_capacity(wanted), wanted, done: Some(Box::new(done)) };
+
+        let listener = stream
+            .add_local_listener_with_user_data(state)
+            .process(|stream, state| {
+                let Some(mut buffer) = stream.dequeue_buffer() else {
+                    return;
+                };
+                let data = &mut buffer.datas_mut()[0];
+                let offset = data.chunk().offset() as usize;
+                let size = data.chunk().size() as usize;
+
+                if let Some(bytes) = data.data().and_then(|slice| slice.get(offset..offset + size)) {
+                    let missing = state.wanted.saturating_sub(state.samples.len());
+                    let samples = bytes.chunks_exact(std::mem::size_of::<f32>()).map(|b| f32::from_le_bytes(b.try_into().unwrap()));
+                    state.samples.extend(samples.take(missing));
+                }
+
+                if state.samples.len() >= state.wanted {
+                    if let Some(done) = state.done.take() {
+                        let samples = std::mem::take(&mut state.samples);
+                        glib::idle_add_local_once(move || done(samples));
+                    }
+                }
+            })
+            .register()?;
+
+        let mut buffer: Vec<u8> = Vec::new();
+        let fmtpod = create_audio_format_pod(&mut buffer, rate, positions);
+
+        stream.connect(
+            Direction::Input,
+            Some(source),
+            StreamFlags::AUTOCONNECT | StreamFlags::MAP_BUFFERS | StreamFlags::DONT_RECONNECT,
+            &mut [fmtpod],
+        )?;
+
+        Ok(Self { stream: Some(stream), _listener: listener, _loop: stream_loop })
+    }
+}
+
+impl Drop for SoundRecorder {
+    fn drop(&mut self) {
+        if let Some(stream) = self.stream.take() {
+            let _ = stream.disconnect();
+        }
+    }
+}
+
+/// A tone on each of `channels` in turn, the way `speaker-test` walks through the speakers.
+pub fn speaker_test_samples(rate: u32, channels: usize) -> Vec<f32> {
+    let tone = samples_for(rate, 1, TONE_LENGTH);
+    let gap = samples_for(rate, 1, TONE_GAP);
+    let fade = samples_for(rate, 1, TONE_FADE).max(1);
+
+    let mut samples = vec![0.0; (tone + gap) * channels * channels];
+    for channel in 0..channels {
+        let start = channel * (tone + gap);
+        for frame in 0..tone {
+            let envelope = (frame.min(tone - frame) as f32 / fade as f32).min(1.0);
+            let value = (2.0 * PI * TONE_FREQUENCY * frame as f32 / rate as f32).sin() * TONE_AMPLITUDE * envelope;
+            samples[(start + frame) * channels + channel] = value;
+        }
+    }
+    samples
+}
+
+fn samples_for(rate: u32, channels: usize, duration: Duration) -> usize {
+    (rate as f64 * duration.as_secs_f64()) as usize * channels
+}
+
+pub(super) fn create_audio_format_pod<'a>(buffer: &'a mut Vec<u8>, rate: u32, positions: &[u32]) -> &'a spa::pod::Pod {
+    let mut position = [0; 64];
+    position[..positions.len()].copy_from_slice(positions);
+
+    let mut audio_info = spa::param::audio::AudioInfoRaw::new();
+    audio_info.set_format(spa::param::audio::AudioFormat::F32LE);
+    audio_info.set_rate(rate);
+    audio_info.set_channels(positions.len() as u32);
+    audio_info.set_position(position);
+
+    let values = spa::pod::serialize::PodSerializer::serialize(
+        std::io::Cursor::new(buffer),
+        &spa::pod::Value::Object(pipewire::spa::pod::Object {
+            type_: spa::sys::SPA_TYPE_OBJECT_Format,
+            id: spa::sys::SPA_PARAM_EnumFormat,
+            properties: audio_info.into(),
+        }),
+    )
+    .unwrap()
+    .0
+    .into_inner();
+
+    spa::pod::Pod::from_bytes(values).unwrap()
+}
@@ -1,14 +1,13 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use crate::{
-    backend::{PwNodeObject, PwvucontrolManager},
+    backend::{NodeType, PwNodeObject, PwvucontrolManager},
     macros::*,
-    ui::{PwStreamDropDown, PwVolumeBox},
+    ui::{PwListenButton, PwStreamDropDown, PwVolumeBox},
 };
 use glib::{clone, closure_local};
 use gtk::{prelude::*, subclass::prelude::*};
 use std::cell::OnceCell;
-use wireplumber as wp;
 
 mod imp {
 
@@ -26,6 +25,9 @@ mod imp {
 
         #[template_child]
         pub output_dropdown: TemplateChild<PwStreamDropDown>,
+
+        #[template_child]
+        pub listen_button: TemplateChild<PwListenButton>,
     }
 
     #[glib::object_subclass]
@@ -53,31 +55,30 @@ mod imp {
 
             self.volumebox.set_node_object(&item);
 
-            let defaultnodesapi = manager.default_nodes_api();
             let widget = self.obj();
-            let defaultnodesapi_closure = closure_local!(@watch widget => move |_: wp::plugin::Plugin| widget.update_output_device_dropdown());
-            defaultnodesapi.connect_closure("changed", false, defaultnodesapi_closure);
+            let default_nodes_closure = closure_local!(@watch widget => move |_: PwvucontrolManager| widget.update_output_device_dropdown());
+            manager.connect_closure("default-nodes-changed", false, default_nodes_closure);
             widget.update_output_device_dropdown();
 
             self.parent_constructed();
 
-            if let Some(metadata) = manager.metadata() {
-                let boundid = item.boundid();
-                let widget = self.obj();
-                let changed_closure = closure_local!(@watch widget =>
-                    move |_obj: &wp::pw::Metadata, id: u32, key: Option<String>, _type: Option<String>, _value: Option<String>| {
-                    let key = key.unwrap_or_default();
-                    if id == boundid && key.contains("target.") {
-                        pwvucontrol_info!("metadata changed handler id: {boundid} {key:?} {_value:?}!");
-                        widget.update_output_device_dropdown();
-                    }
-                });
-                metadata.connect_closure("changed", false, changed_closure);
-            }
+            let boundid = item.boundid();
+            let changed_closure = closure_local!(@watch widget => move |_: PwvucontrolManager, id: u32, key: String| {
+                if id == boundid && key.contains("target.") {
+                    pwvucontrol_info!("metadata changed handler id: {boundid} {key:?}!");
+                    widget.update_output_device_dropdown();
+                }
+            });
+            manager.connect_closure("metadata-changed", false, changed_closure);
 
             // Create our custom output dropdown widget and add it to the layout
             self.output_dropdown.set_nodeobj(Some(&item));
 
+            if matches!(item.nodetype(), NodeType::StreamInput) && manager.backend().can_create_streams() {
+                self.listen_button.set_nodeobj(Some(&item));
+                self.listen_button.set_visible(true);
+            }
+
             glib::idle_add_local_once(clone!(@weak self as widget => move || {
                 widget.obj().update_output_device_dropdown();
             }));
@@ -134,7 +135,7 @@ impl PwStreamBox {
 
         if let Some(deftarget) = item.default_target() {
             if let Some(pos) = stream_model.get_node_pos_from_id(deftarget.boundid()) {
-                pwvucontrol_info!("switching to preferred target pos={pos} boundid={} serial={}", deftarget.boundid(), deftarget.serial());
+                pwvucontrol_info!("switching to preferred target pos={pos} boundid={} serial={:?}", deftarget.boundid(), deftarget.serial());
                 output_dropdown.set_selected_no_send(pos + 1);
             }
         } else {
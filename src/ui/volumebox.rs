@@ -1,12 +1,20 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use crate::{
-    backend::{NodeType, PwChannelObject, PwNodeObject, PwvucontrolManager},
-    ui::{LevelbarProvider, PwChannelBox, PwPeakMeter, PwVolumeScale},
+    backend::{NodeType, PropsParamValue, PwChannelObject, PwNodeObject, PwvucontrolManager},
+    ui::{LevelbarProvider, PwChannelBox, PwChannelMapWindow, PwPeakMeter, PwPropertiesWindow, PwVolumeScale},
 };
-use glib::{clone, ControlFlow, SignalHandlerId};
-use gtk::{prelude::*, subclass::prelude::*};
-use std::cell::{Cell, RefCell};
+use adw::prelude::*;
+use gettextrs::gettext;
+use glib::{clone, ControlFlow, SourceId};
+use gtk::subclass::prelude::*;
+use std::{
+    cell::{Cell, RefCell},
+    time::Duration,
+};
+
+/// How long the forced quantum has to stay put before it is sent, so stepping through values doesn't reconfigure the graph each time.
+const FORCE_QUANTUM_DELAY: Duration = Duration::from_millis(500);
 
 mod imp {
     use super::*;
@@ -18,7 +26,18 @@ mod imp {
         #[property(get, set = Self::set_node_object)]
         pub(super) node_object: RefCell<Option<PwNodeObject>>,
 
-        metadata_changed_event: Cell<Option<SignalHandlerId>>,
+        /// Reveals the latency and scheduling info section.
+        #[property(get, set = Self::set_show_info)]
+        pub(super) show_info: Cell<bool>,
+        info_timeout: RefCell<Option<SourceId>>,
+        block_info: Cell<bool>,
+        /// Waits for the spin row to settle before the forced quantum is sent.
+        force_quantum_timeout: RefCell<Option<SourceId>>,
+        /// The node properties last shown, the rows are only refreshed when these change, as
+        /// setting them through the Props param doesn't update the properties.
+        shown_latency: RefCell<Option<String>>,
+        shown_force_quantum: Cell<Option<i32>>,
+
         levelbarprovider: Cell<Option<LevelbarProvider>>,
         timeoutid: Cell<Option<gtk::TickCallbackId>>,
         pub(super) level: Cell<f32>,
@@ -50,6 +69,28 @@ mod imp {
         pub monitorvolumescale: TemplateChild<PwVolumeScale>,
         #[template_child]
         pub container: TemplateChild<gtk::Box>,
+        #[template_child]
+        pub balance_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub balance_scale: TemplateChild<gtk::Scale>,
+        #[template_child]
+        pub fade_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub fade_scale: TemplateChild<gtk::Scale>,
+        #[template_child]
+        pub quantum_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub latency_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub driver_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub xruns_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub requested_rate_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub requested_latency_row: TemplateChild<adw::EntryRow>,
+        #[template_child]
+        pub force_quantum_row: TemplateChild<adw::SpinRow>,
     }
 
     #[glib::object_subclass]
@@ -62,6 +103,35 @@ mod imp {
         fn class_init(klass: &mut Self::Class) {
             PwPeakMeter::ensure_type();
             klass.bind_template();
+            klass.bind_template_callbacks();
+
+            klass.install_property_action("node.show-info", "show-info");
+
+            klass.install_action("node.hide", None, |widget, _, _| {
+                if let Some(node) = widget.node_object() {
+                    PwvucontrolManager::default().hide_node(&node);
+                }
+            });
+
+            klass.install_action("node.hide-application", None, |widget, _, _| {
+                if let Some(node) = widget.node_object() {
+                    PwvucontrolManager::default().hide_application(&node);
+                }
+            });
+
+            klass.install_action("node.properties", None, |widget, _, _| {
+                if let Some(node) = widget.node_object() {
+                    let parent = widget.root().and_downcast::<gtk::Window>();
+                    PwPropertiesWindow::new(parent.as_ref(), &node.name(), node.backend().dump()).present();
+                }
+            });
+
+            klass.install_action("node.channels", None, |widget, _, _| {
+                if let Some(node) = widget.node_object() {
+                    let parent = widget.root().and_downcast::<gtk::Window>();
+                    PwChannelMapWindow::new(parent.as_ref(), &node).present();
+                }
+            });
         }
 
         fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
@@ -80,16 +150,30 @@ mod imp {
 
             let window = crate::ui::PwvucontrolWindow::default();
             window.imp().settings.bind("use-peakmeter-led", &self.peak_meter.get(), "use-led").get_only().build();
+
+            self.force_quantum_row.connect_value_notify(clone!(@weak self as widget => move |_| {
+                if widget.block_info.get() {
+                    return;
+                }
+                if let Some(source) = widget.force_quantum_timeout.take() {
+                    source.remove();
+                }
+                let source = glib::timeout_add_local_once(
+                    FORCE_QUANTUM_DELAY,
+                    clone!(@weak widget => move || {
+                        widget.force_quantum_timeout.take();
+                        widget.force_quantum_applied();
+                    }),
+                );
+                widget.force_quantum_timeout.replace(Some(source));
+            }));
         }
 
         fn dispose(&self) {
-            if let Some(sid) = self.metadata_changed_event.take() {
-                let manager = PwvucontrolManager::default();
-                if let Some(metadata) = manager.metadata() {
-                    metadata.disconnect(sid);
-                };
-            };
-
+            if let Some(source) = self.force_quantum_timeout.take() {
+                source.remove();
+                self.force_quantum_applied();
+            }
             self.dispose_template();
         }
     }
@@ -99,6 +183,7 @@ mod imp {
             if let Some(t) = self.timeoutid.take() {
                 t.remove();
             }
+            self.stop_info_updates();
             self.levelbarprovider.take();
             self.parent_unmap();
         }
@@ -114,6 +199,10 @@ mod imp {
             } else {
                 self.peak_meter.set_visible(false);
             }
+
+            if self.show_info.get() {
+                self.start_info_updates();
+            }
         }
     }
     impl ListBoxRowImpl for PwVolumeBox {}
@@ -134,7 +223,111 @@ mod imp {
         }
     }
 
+    #[gtk::template_callbacks]
     impl PwVolumeBox {
+        #[template_callback]
+        fn latency_applied(&self, row: &adw::EntryRow) {
+            if let Some(node) = self.node_object.borrow().as_ref() {
+                node.set_props_params(&[("node.latency", PropsParamValue::String(row.text().to_string()))]);
+            }
+        }
+
+        fn force_quantum_applied(&self) {
+            if let Some(node) = self.node_object.borrow().as_ref() {
+                node.set_props_params(&[("node.force-quantum", PropsParamValue::Int(self.force_quantum_row.value() as i32))]);
+            }
+        }
+
+        fn set_show_info(&self, show_info: bool) {
+            self.show_info.set(show_info);
+            if show_info && self.obj().is_mapped() {
+                self.start_info_updates();
+            } else {
+                self.stop_info_updates();
+            }
+        }
+
+        /// Refreshes the info section every second while it is shown, as the profiler data changes every cycle.
+        fn start_info_updates(&self) {
+            if self.info_timeout.borrow().is_some() {
+                return;
+            }
+            self.update_info();
+            let source = glib::timeout_add_seconds_local(
+                1,
+                clone!(@weak self as widget => @default-return ControlFlow::Break, move || {
+                    widget.update_info();
+                    ControlFlow::Continue
+                }),
+            );
+            self.info_timeout.replace(Some(source));
+        }
+
+        fn stop_info_updates(&self) {
+            if let Some(source) = self.info_timeout.take() {
+                source.remove();
+            }
+        }
+
+        fn update_info(&self) {
+            let Some(node) = self.node_object.borrow().clone() else {
+                return;
+            };
+            let manager = PwvucontrolManager::default();
+            let profile = manager.node_profile(node.boundid());
+
+            let not_available = if manager.profiler_available() { gettext("Not running") } else { gettext("Profiler unavailable") };
+
+            match &profile {
+                Some(profile) => {
+                    let quantum_ms = if profile.rate > 0 { profile.quantum as f64 * 1000.0 / profile.rate as f64 } else { 0.0 };
+                    self.quantum_row.set_subtitle(&format!("{} @ {} Hz ({quantum_ms:.2} ms)", profile.quantum, profile.rate));
+
+                    let (num, denom) = profile.latency;
+                    if denom > 0 {
+                        self.latency_row.set_subtitle(&format!("{num}/{denom} ({:.2} ms)", num as f64 * 1000.0 / denom as f64));
+                    } else {
+                        self.latency_row.set_subtitle(&gettext("None requested"));
+                    }
+                    self.xruns_row.set_subtitle(&profile.xruns.to_string());
+                }
+                None => {
+                    self.quantum_row.set_subtitle(&not_available);
+                    self.latency_row.set_subtitle(&not_available);
+                    self.xruns_row.set_subtitle(&not_available);
+                }
+            }
+
+            let driver_id = node.node_property::<i32>("node.driver-id").map(|id| id as u32).or_else(|| profile.as_ref().map(|p| p.driver_id));
+            let driver = match driver_id {
+                Some(id) if id == node.boundid() => gettext("This node"),
+                Some(id) => manager
+                    .get_node_by_id(id)
+                    .map(|driver| driver.name())
+                    .or_else(|| profile.as_ref().map(|p| p.driver_name.clone()).filter(|name| !name.is_empty()))
+                    .unwrap_or_else(|| format!("#{id}")),
+                None => not_available.clone(),
+            };
+            self.driver_row.set_subtitle(&driver);
+
+            self.requested_rate_row.set_subtitle(&node.node_property::<String>("node.rate").unwrap_or_else(|| gettext("None")));
+
+            // Don't overwrite what the user is typing, or what they set and the properties don't reflect.
+            let latency = node.node_property::<String>("node.latency");
+            if self.requested_latency_row.focus_child().is_none() && *self.shown_latency.borrow() != latency {
+                self.requested_latency_row.set_text(latency.as_deref().unwrap_or_default());
+                self.shown_latency.replace(latency);
+            }
+
+            let force_quantum = node.node_property::<i32>("node.force-quantum");
+            if self.force_quantum_timeout.borrow().is_none() && self.shown_force_quantum.get() != force_quantum {
+                self.block_info.set(true);
+                self.force_quantum_row.set_value(force_quantum.unwrap_or_default() as f64);
+                self.block_info.set(false);
+                self.shown_force_quantum.set(force_quantum);
+            }
+        }
+
         fn set_node_object(&self, node_object: PwNodeObject) {
             self.node_object.set(Some(node_object));
             let item = self.node_object.borrow();
@@ -147,6 +340,11 @@ mod imp {
                 self.icon.set_icon_name(Some(&item.iconname()));
             }
 
+            // Sinks and sources are hidden one by one, streams only by application.
+            let is_stream = matches!(item.nodetype(), NodeType::StreamInput | NodeType::StreamOutput);
+            self.obj().action_set_enabled("node.hide", !is_stream);
+            self.obj().action_set_enabled("node.hide-application", is_stream && item.node_property::<String>("application.name").is_some());
+
             item.bind_property("name", &self.title_label.get(), "label").sync_create().build();
 
             item.bind_property("description", &self.subtitle_label.get(), "label").sync_create().build();
@@ -163,6 +361,21 @@ mod imp {
 
             item.bind_property("mainvolume", &self.mainvolumescale.get(), "volume").sync_create().bidirectional().build();
 
+            for (property, scale, label) in
+                [("balance", &self.balance_scale, &self.balance_label), ("fade", &self.fade_scale, &self.fade_label)]
+            {
+                item.bind_property(property, &scale.adjustment(), "value")
+                    .transform_to(|_, value: f32| Some(value as f64))
+                    .transform_from(|_, value: f64| Some(value as f32))
+                    .sync_create()
+                    .bidirectional()
+                    .build();
+
+                let has_property = format!("has-{property}");
+                item.bind_property(&has_property, &scale.get(), "visible").sync_create().build();
+                item.bind_property(&has_property, &label.get(), "visible").sync_create().build();
+            }
+
             self.channel_listbox.bind_model(
                 Some(&item.channelmodel()),
                 clone!(@weak self as widget => @default-panic, move |item| {
@@ -179,6 +392,11 @@ mod imp {
             let item = self.node_object.borrow();
             let item = item.as_ref().unwrap();
 
+            // The mock backend has nothing to record from.
+            if !PwvucontrolManager::default().backend().can_monitor() {
+                return;
+            }
+
             if let Ok(provider) = LevelbarProvider::new(&self.obj(), item.boundid()) {
                 self.levelbarprovider.set(Some(provider));
 
@@ -4,15 +4,21 @@ use std::time::Duration;
 
 use crate::{
     application::PwvucontrolApplication,
-    backend::{PwDeviceObject, PwNodeObject, PwvucontrolManager},
+    backend::{NodeSortMode, NodeType, PwDeviceObject, PwNodeGroupObject, PwNodeObject, PwNodeSearchFilter, PwNodeSorter, PwvucontrolManager},
     config::{APP_ID, PROFILE},
-    ui::{devicebox::PwDeviceBox, PwSinkBox, PwStreamBox},
+    ui::{
+        devicebox::PwDeviceBox, FeedbackSound, PwGroupHeader, PwOutputSwitcher, PwPerformanceView, PwPreferencesWindow, PwSinkBox, PwStreamBox,
+        SoundPlayer,
+    },
 };
 use adw::subclass::prelude::*;
-use gettextrs::gettext;
-use glib::clone;
+use gettextrs::{gettext, ngettext};
+use glib::{clone, closure_local};
 use gtk::{gio, prelude::*};
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
 use std::time;
 
 pub enum PwvucontrolWindowView {
@@ -45,6 +51,12 @@ mod imp {
         pub reconnectbtn: TemplateChild<gtk::Button>,
         #[template_child]
         pub info_banner: TemplateChild<adw::Banner>,
+        #[template_child]
+        pub search_bar: TemplateChild<gtk::SearchBar>,
+        #[template_child]
+        pub search_entry: TemplateChild<gtk::SearchEntry>,
+        #[template_child]
+        pub active_only_toggle: TemplateChild<gtk::ToggleButton>,
 
         #[template_child]
         pub playbackviewstack: TemplateChild<gtk::Stack>,
@@ -57,7 +69,13 @@ mod imp {
 
         pub settings: gio::Settings,
 
+        pub search_filter: PwNodeSearchFilter,
+        pub stream_sorter: PwNodeSorter,
+
         pub beep_elapsed: Cell<time::Instant>,
+        pub(super) beep_player: RefCell<Option<SoundPlayer>>,
+        /// The sound last read, with the value of `feedback-sound` it was read for.
+        pub(super) feedback_sound: RefCell<Option<(String, FeedbackSound)>>,
     }
 
     impl Default for PwvucontrolWindow {
@@ -74,11 +92,18 @@ mod imp {
                 reconnectbtn: TemplateChild::default(),
                 settings: gio::Settings::new(APP_ID),
                 info_banner: TemplateChild::default(),
+                search_bar: TemplateChild::default(),
+                search_entry: TemplateChild::default(),
+                active_only_toggle: TemplateChild::default(),
+                search_filter: PwNodeSearchFilter::new(),
+                stream_sorter: PwNodeSorter::new(),
                 playbackviewstack: TemplateChild::default(),
                 recordviewstack: TemplateChild::default(),
                 inputviewstack: TemplateChild::default(),
                 outputviewstack: TemplateChild::default(),
                 beep_elapsed: Cell::new(std::time::Instant::now()),
+                beep_player: Default::default(),
+                feedback_sound: Default::default(),
             }
         }
     }
@@ -90,6 +115,8 @@ mod imp {
         type ParentType = adw::ApplicationWindow;
 
         fn class_init(klass: &mut Self::Class) {
+            PwOutputSwitcher::ensure_type();
+            PwPerformanceView::ensure_type();
             klass.bind_template();
         }
 
@@ -132,7 +159,26 @@ mod imp {
                     window.select_tab(parameter);
                 })
                 .build();
-            self.obj().add_action_entries([action_switchtab]);
+            let action_preferences = gio::ActionEntry::builder("preferences")
+                .activate(move |window: &super::PwvucontrolWindow, _action, _parameter| {
+                    PwPreferencesWindow::new(window).present();
+                })
+                .build();
+            self.obj().add_action_entries([action_switchtab, action_preferences]);
+
+            let search_action = gio::PropertyAction::new("search", &self.search_bar.get(), "search-mode-enabled");
+            self.obj().add_action(&search_action);
+            self.search_bar.connect_entry(&self.search_entry.get());
+            self.search_bar.set_key_capture_widget(Some(self.obj().upcast_ref::<gtk::Widget>()));
+
+            self.search_entry
+                .bind_property("text", &self.search_filter, "search-text")
+                .sync_create()
+                .build();
+            self.active_only_toggle
+                .bind_property("active", &self.search_filter, "active-only")
+                .sync_create()
+                .build();
 
             crate::ui::remember_window_size(self.obj().upcast_ref(), &self.settings);
 
@@ -143,16 +189,14 @@ mod imp {
 
             let manager = PwvucontrolManager::default();
 
-            let wp_core = manager.wp_core();
-
-            wp_core.connect_connected(clone!(@weak self as window => move |_obj| {
-                window.obj().set_view(PwvucontrolWindowView::Connected);
+            manager.connect_connected_notify(clone!(@weak self as window => move |manager| {
+                window.obj().update_view(manager.connected());
             }));
+            self.obj().update_view(manager.connected());
 
-            wp_core.connect_disconnected(clone!(@weak self as window => move |_obj| {
-                window.obj().set_view(PwvucontrolWindowView::Disconnected);
+            manager.connect_degraded_notify(clone!(@weak self as widget => move |_| {
+                widget.obj().update_info_bar();
             }));
-
             manager.node_model().connect_items_changed(clone!(@weak self as widget => move |_,_,_,_| {
                 widget.obj().update_info_bar();
             }));
@@ -162,8 +206,34 @@ mod imp {
 
             glib::idle_add_local_once(clone!(@weak self as widget => move || {widget.obj().update_info_bar();}));
 
+            self.search_filter.watch_model(&manager.node_model());
+
+            self.settings.bind("stream-sort-mode", &self.stream_sorter, "sort-mode").get_only().build();
+            self.settings.bind("group-streams", &self.stream_sorter, "grouped").get_only().build();
+            self.stream_sorter.watch_model(&manager.node_model());
+
+            // Stream targets live in the default metadata, so re-sort when one changes while sorting by target.
+            let sorter = self.stream_sorter.clone();
+            manager.connect_closure(
+                "metadata-changed",
+                false,
+                closure_local!(@watch sorter => move |_: PwvucontrolManager, _: u32, key: String| {
+                    if sorter.sort_mode() == NodeSortMode::Target && key.starts_with("target.") {
+                        sorter.changed(gtk::SorterChange::Different);
+                    }
+                }),
+            );
+
+            let stream_output_model = self.obj().sorted_model(&self.obj().filtered_model(&manager.stream_output_model()));
+            let stream_input_model = self.obj().sorted_model(&self.obj().filtered_model(&manager.stream_input_model()));
+            self.obj().setup_grouping(&self.playbacklist, &stream_output_model);
+            self.obj().setup_grouping(&self.recordlist, &stream_input_model);
+            let source_model = self.obj().filtered_model(&manager.source_model());
+            let sink_model = self.obj().filtered_model(&manager.sink_model());
+            let device_model = self.obj().filtered_model(&manager.device_model());
+
             self.playbacklist.bind_model(
-                Some(&manager.stream_output_model()),
+                Some(&stream_output_model),
                 clone!(@weak self as window => @default-panic, move |item| {
                     PwStreamBox::new(
                         item.downcast_ref::<PwNodeObject>()
@@ -173,7 +243,7 @@ mod imp {
                 }),
             );
 
-            manager.stream_output_model().connect_items_changed(clone!(@weak self as widget => move |x, _, _, _| {
+            stream_output_model.connect_items_changed(clone!(@weak self as widget => move |x, _, _, _| {
                 match x.n_items() {
                     0 => widget.playbackviewstack.set_visible_child_name("empty"),
                     _ => widget.playbackviewstack.set_visible_child_name("notempty")
@@ -181,7 +251,7 @@ mod imp {
             }));
 
             self.recordlist.bind_model(
-                Some(&manager.stream_input_model()),
+                Some(&stream_input_model),
                 clone!(@weak self as window => @default-panic, move |item| {
                     PwStreamBox::new(
                         item.downcast_ref::<PwNodeObject>()
@@ -191,7 +261,7 @@ mod imp {
                 }),
             );
             
-            manager.stream_input_model().connect_items_changed(clone!(@weak self as widget => move |x, _, _, _| {
+            stream_input_model.connect_items_changed(clone!(@weak self as widget => move |x, _, _, _| {
                 match x.n_items() {
                     0 => widget.recordviewstack.set_visible_child_name("empty"),
                     _ => widget.recordviewstack.set_visible_child_name("notempty")
@@ -199,7 +269,7 @@ mod imp {
             }));
 
             self.inputlist.bind_model(
-                Some(&manager.source_model()),
+                Some(&source_model),
                 clone!(@weak self as window => @default-panic, move |item| {
                     PwSinkBox::new(
                         item.downcast_ref::<PwNodeObject>()
@@ -209,7 +279,7 @@ mod imp {
                 }),
             );
 
-            manager.source_model().connect_items_changed(clone!(@weak self as widget => move |x, _, _, _| {
+            source_model.connect_items_changed(clone!(@weak self as widget => move |x, _, _, _| {
                 match x.n_items() {
                     0 => widget.inputviewstack.set_visible_child_name("empty"),
                     _ => widget.inputviewstack.set_visible_child_name("notempty")
@@ -217,7 +287,7 @@ mod imp {
             }));
 
             self.outputlist.bind_model(
-                Some(&manager.sink_model()),
+                Some(&sink_model),
                 clone!(@weak self as window => @default-panic, move |item| {
                     PwSinkBox::new(
                         item.downcast_ref::<PwNodeObject>()
@@ -227,7 +297,7 @@ mod imp {
                 }),
             );
 
-            manager.sink_model().connect_items_changed(clone!(@weak self as widget => move |x, _, _, _| {
+            sink_model.connect_items_changed(clone!(@weak self as widget => move |x, _, _, _| {
                 match x.n_items() {
                     0 => widget.outputviewstack.set_visible_child_name("empty"),
                     _ => widget.outputviewstack.set_visible_child_name("notempty")
@@ -235,7 +305,7 @@ mod imp {
             }));
 
             self.cardlist.bind_model(
-                Some(&manager.device_model()),
+                Some(&device_model),
                 clone!(@weak self as window => @default-panic, move |item| {
                     let obj: &PwDeviceObject = item.downcast_ref().expect("PwDeviceObject");
                     PwDeviceBox::new(obj).upcast::<gtk::Widget>()
@@ -243,10 +313,7 @@ mod imp {
             );
 
             self.reconnectbtn.connect_clicked(|_| {
-                let manager = PwvucontrolManager::default();
-                if let Some(core) = manager.imp().wp_core.get() {
-                    core.connect();
-                }
+                PwvucontrolManager::default().reconnect();
             });
 
             let overamplification_action = self.settings.create_action("enable-overamplification");
@@ -255,6 +322,12 @@ mod imp {
             self.obj().add_action(&use_led_peakmeter_action);
             let beep_on_volume_changes_action = self.settings.create_action("beep-on-volume-changes");
             self.obj().add_action(&beep_on_volume_changes_action);
+            let show_hidden_streams_action = self.settings.create_action("show-hidden-streams");
+            self.obj().add_action(&show_hidden_streams_action);
+            let stream_sort_mode_action = self.settings.create_action("stream-sort-mode");
+            self.obj().add_action(&stream_sort_mode_action);
+            let group_streams_action = self.settings.create_action("group-streams");
+            self.obj().add_action(&group_streams_action);
 
         }
     }
@@ -278,6 +351,10 @@ impl PwvucontrolWindow {
         glib::Object::builder().property("application", application).build()
     }
 
+    fn update_view(&self, connected: bool) {
+        self.set_view(if connected { PwvucontrolWindowView::Connected } else { PwvucontrolWindowView::Disconnected });
+    }
+
     pub(crate) fn set_view(&self, view: PwvucontrolWindowView) {
         let imp = self.imp();
         match view {
@@ -287,6 +364,87 @@ impl PwvucontrolWindow {
     }
 
 
+    /// Wraps one of the manager models with the search filter shared by all tabs.
+    fn filtered_model(&self, model: &impl IsA<gio::ListModel>) -> gtk::FilterListModel {
+        gtk::FilterListModel::new(Some(model.clone()), Some(self.imp().search_filter.clone()))
+    }
+
+    fn sorted_model(&self, model: &impl IsA<gio::ListModel>) -> gtk::SortListModel {
+        gtk::SortListModel::new(Some(model.clone()), Some(self.imp().stream_sorter.clone()))
+    }
+
+    /// Shows a [`PwGroupHeader`] above the first stream of every group when grouping is enabled.
+    fn setup_grouping(&self, listbox: &gtk::ListBox, model: &gtk::SortListModel) {
+        let sorter = self.imp().stream_sorter.clone();
+
+        // Group members by key, collected once per change rather than for every header.
+        let groups: Rc<RefCell<HashMap<String, Vec<PwNodeObject>>>> = Default::default();
+
+        listbox.set_header_func(clone!(@weak self as window, @strong groups => move |row, before| {
+            window.update_group_header(&groups.borrow(), row, before);
+        }));
+
+        let update_groups = clone!(@weak sorter, @weak model, @weak listbox => move || {
+            let mut groups = groups.borrow_mut();
+            groups.clear();
+            if sorter.grouped() {
+                for node in model.iter::<PwNodeObject>().map_while(Result::ok) {
+                    groups.entry(sorter.group_key(&node)).or_default().push(node);
+                }
+            }
+            listbox.invalidate_headers();
+        });
+        update_groups();
+
+        model.connect_items_changed(clone!(@strong update_groups => move |_, _, _, _| update_groups()));
+        sorter.connect_changed(move |_, _| update_groups());
+    }
+
+    fn update_group_header(&self, groups: &HashMap<String, Vec<PwNodeObject>>, row: &gtk::ListBoxRow, before: Option<&gtk::ListBoxRow>) {
+        let sorter = &self.imp().stream_sorter;
+
+        let node_of = |row: &gtk::ListBoxRow| row.downcast_ref::<PwStreamBox>().map(|streambox| streambox.node_object());
+
+        let Some(node) = node_of(row).filter(|_| sorter.grouped()) else {
+            row.set_header(gtk::Widget::NONE);
+            return;
+        };
+
+        let key = sorter.group_key(&node);
+        if before.and_then(node_of).is_some_and(|before| sorter.group_key(&before) == key) {
+            row.set_header(gtk::Widget::NONE);
+            return;
+        }
+
+        let members = groups.get(&key).map(Vec::as_slice).unwrap_or_default();
+
+        let group_name = match (sorter.sort_mode(), key.is_empty()) {
+            (NodeSortMode::Target, true) => gettext("Default"),
+            _ => key.clone(),
+        };
+        let n = members.len() as u32;
+        let title = formatx::formatx!(ngettext("{} ({} stream)", "{} ({} streams)", n), group_name, n).unwrap_or_default();
+
+        let header = row.header().and_downcast::<PwGroupHeader>().unwrap_or_default();
+
+        // Application groups are shared with the manager so their volume also applies to streams opened later.
+        let application = match sorter.sort_mode() {
+            NodeSortMode::Target => None,
+            _ => PwvucontrolManager::default().get_application_for_node(&node),
+        };
+        let group = application.unwrap_or_else(|| {
+            let group = header
+                .group()
+                .filter(|group| !group.apply_to_new_members())
+                .unwrap_or_else(|| PwNodeGroupObject::new(&key, &group_name, NodeType::Undefined, false));
+            group.set_members(members);
+            group
+        });
+
+        header.set_group(&title, &group);
+        row.set_header(Some(&header));
+    }
+
     /// This prevents child widgets from capturing scroll events
     fn setup_scroll_blocker(&self, listbox: &gtk::ListBox) {
         let scrolledwindow = listbox
@@ -324,23 +482,68 @@ impl PwvucontrolWindow {
         let manager = PwvucontrolManager::default();
         let imp = self.imp();
 
-        let message = if manager.device_model().n_items() == 0 {
+        let message = if manager.node_model().n_items() > 0 && manager.degraded() {
+            gettext("WirePlumber's mixer or default device API is missing, volumes and defaults are set without it.")
+        } else if manager.device_model().n_items() == 0 {
             gettext("No sound cards detected. Check pipewire configuration.")
         } else {
             gettext("No sound devices detected. Check profiles in Card tab.")
         };
         imp.info_banner.set_title(&message);
-        imp.info_banner.set_revealed(manager.node_model().n_items() == 0);
+        imp.info_banner.set_revealed(manager.node_model().n_items() == 0 || manager.degraded());
     }
 
-    pub(crate) fn play_beep(&self) {
-        if !self.imp().settings.boolean("beep-on-volume-changes") {
+    /// Plays the feedback sound on `sink`, so it is heard at its new volume on the very device being changed.
+    pub(crate) fn play_beep(&self, sink: &PwNodeObject) {
+        let imp = self.imp();
+        if !imp.settings.boolean("beep-on-volume-changes") {
             return;
         }
-        if self.imp().beep_elapsed.get().elapsed() > Duration::from_secs(1) {
+        if imp.beep_elapsed.get().elapsed() <= Duration::from_secs(1) {
+            return;
+        }
+        imp.beep_elapsed.set(time::Instant::now());
+
+        // The demo backend has no PipeWire to play through.
+        if !PwvucontrolManager::default().backend().can_create_streams() {
             self.display().beep();
-            self.imp().beep_elapsed.set(time::Instant::now());
+            return;
         }
+
+        let sound = self.feedback_sound();
+        let done = clone!(@weak self as window => move || {
+            window.imp().beep_player.take();
+        });
+        match SoundPlayer::new(Some(sink.boundid()), sound.rate, &sound.positions, sound.samples, done) {
+            Ok(player) => {
+                imp.beep_player.replace(Some(player));
+            }
+            Err(e) => {
+                crate::pwvucontrol_warning!("Cannot play feedback sound: {e}");
+                self.display().beep();
+            }
+        }
+    }
+
+    /// The sound set in `feedback-sound`, or the built-in one when it is unset or unreadable.
+    fn feedback_sound(&self) -> FeedbackSound {
+        let imp = self.imp();
+        let path = imp.settings.string("feedback-sound").to_string();
+        if let Some((cached_path, sound)) = imp.feedback_sound.borrow().as_ref() {
+            if *cached_path == path {
+                return sound.clone();
+            }
+        }
+
+        let sound = match path.as_str() {
+            "" => FeedbackSound::builtin(),
+            path => FeedbackSound::load(Path::new(path)).unwrap_or_else(|e| {
+                crate::pwvucontrol_warning!("Cannot read feedback sound {path}: {e}");
+                FeedbackSound::builtin()
+            }),
+        };
+        imp.feedback_sound.replace(Some((path, sound.clone())));
+        sound
     }
 
     pub(crate) fn select_tab(&self, tab: i32) {
@@ -350,9 +553,30 @@ impl PwvucontrolWindow {
             3 => self.imp().stack.set_visible_child_name("inputdevices"),
             4 => self.imp().stack.set_visible_child_name("outputdevices"),
             5 => self.imp().stack.set_visible_child_name("cards"),
+            6 => self.imp().stack.set_visible_child_name("performance"),
             _ => {}
         }
     }
+
+    /// Switches to the tab listing `node` and focuses its row.
+    pub(crate) fn show_node(&self, node: &PwNodeObject) {
+        let imp = self.imp();
+        let (tab, listbox) = match node.nodetype() {
+            NodeType::StreamOutput => ("playback", &imp.playbacklist),
+            NodeType::StreamInput => ("recording", &imp.recordlist),
+            NodeType::Source => ("inputdevices", &imp.inputlist),
+            NodeType::Sink => ("outputdevices", &imp.outputlist),
+            NodeType::Undefined => return,
+        };
+        imp.stack.set_visible_child_name(tab);
+
+        let row = (0..)
+            .map_while(|index| listbox.row_at_index(index))
+            .find(|row| row.property::<Option<PwNodeObject>>("node-object").as_ref() == Some(node));
+        if let Some(row) = row {
+            row.grab_focus();
+        }
+    }
 }
 
 impl Default for PwvucontrolWindow {